@@ -41,7 +41,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             for ep in &info.endpoints {
                 println!("    - {}", ep);
             }
-            println!("  TTL remaining: {} seconds", info.remaining_ttl());
+            println!("  TTL remaining: {} seconds", info.remaining_ttl_at(client.now()));
             println!("  Source: {}", info.source_addr);
         }
         None => {
@@ -22,7 +22,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         instance_id: InstanceId(0x0001),
         major_version: 1,
         minor_version: 0,
-        endpoint: Endpoint::tcp("127.0.0.1:30500".parse()?),
+        endpoints: vec![Endpoint::tcp("127.0.0.1:30500".parse()?)],
         ttl: 10, // 10 seconds TTL
     };
 
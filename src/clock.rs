@@ -0,0 +1,79 @@
+//! Time sources for expiry and timeout logic.
+//!
+//! Components that track TTLs or timeouts read the current time through a
+//! [`Clock`], so tests can substitute a [`MockClock`] instead of sleeping.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A source of the current time.
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// Get the current instant.
+    fn now(&self) -> Instant;
+}
+
+/// Clock backed by [`Instant::now`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Manually advanced clock for tests.
+///
+/// Clones share the same time, so a test can keep one handle and pass
+/// another to the component under test.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl MockClock {
+    /// Create a mock clock starting at the current instant.
+    pub fn new() -> Self {
+        Self {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Move the clock forward.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+}
+
+/// Get the default system clock.
+pub(crate) fn system() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_advance() {
+        let clock = MockClock::new();
+        let handle = clock.clone();
+        let start = clock.now();
+
+        handle.advance(Duration::from_secs(30));
+        assert_eq!(clock.now() - start, Duration::from_secs(30));
+    }
+}
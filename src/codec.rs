@@ -1,6 +1,6 @@
 //! SOME/IP message framing and codec utilities.
 
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
 
 use crate::error::Result;
 use crate::header::{SomeIpHeader, HEADER_SIZE};
@@ -27,6 +27,58 @@ pub fn read_message<R: Read>(reader: &mut R) -> Result<SomeIpMessage> {
     Ok(SomeIpMessage::new(header, payload))
 }
 
+/// Size of the chunks passed to the sink by [`read_message_streaming`].
+const STREAMING_CHUNK_SIZE: usize = 8192;
+
+/// Read a SOME/IP message from a stream, passing the payload to `sink` in chunks.
+///
+/// The payload is never buffered as a whole, which allows forwarding or
+/// hashing very large messages incrementally. Returns the message header.
+///
+/// If `sink` fails, the rest of the payload is read and discarded before the
+/// error is returned, so the stream stays at a message boundary.
+pub fn read_message_streaming<R: Read>(
+    reader: &mut R,
+    sink: impl FnMut(&[u8]) -> Result<()>,
+) -> Result<SomeIpHeader> {
+    let mut header_buf = [0u8; HEADER_SIZE];
+    reader.read_exact(&mut header_buf)?;
+
+    let header = SomeIpHeader::from_bytes(&header_buf)?;
+    read_payload_streaming(reader, &header, sink)?;
+    Ok(header)
+}
+
+/// Pass the payload following an already parsed header to `sink` in chunks.
+pub(crate) fn read_payload_streaming<R: Read>(
+    reader: &mut R,
+    header: &SomeIpHeader,
+    mut sink: impl FnMut(&[u8]) -> Result<()>,
+) -> Result<()> {
+    let mut remaining = header.payload_length() as usize;
+
+    let mut chunk = [0u8; STREAMING_CHUNK_SIZE];
+    while remaining > 0 {
+        let len = remaining.min(STREAMING_CHUNK_SIZE);
+        reader.read_exact(&mut chunk[..len])?;
+        remaining -= len;
+        if let Err(e) = sink(&chunk[..len]) {
+            io::copy(&mut reader.take(remaining as u64), &mut io::sink())?;
+            return Err(e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse only the 16-byte header of a datagram, ignoring the payload.
+///
+/// Succeeds even if the payload is truncated, so error responses can be
+/// addressed to requests that fail full parsing.
+pub fn try_parse_header(data: &[u8]) -> Option<SomeIpHeader> {
+    SomeIpHeader::from_bytes(data.get(..HEADER_SIZE)?).ok()
+}
+
 /// Write a complete SOME/IP message to a stream.
 pub fn write_message<W: Write>(writer: &mut W, message: &SomeIpMessage) -> Result<()> {
     writer.write_all(&message.header.to_bytes())?;
@@ -34,6 +86,24 @@ pub fn write_message<W: Write>(writer: &mut W, message: &SomeIpMessage) -> Resul
     Ok(())
 }
 
+/// Write a request to a stream and read messages until its response arrives.
+///
+/// The request is sent as-is, so its client and session IDs must already be
+/// set. Messages whose request ID does not match (e.g. notifications) are
+/// discarded.
+pub fn exchange<S: Read + Write>(stream: &mut S, request: &SomeIpMessage) -> Result<SomeIpMessage> {
+    write_message(stream, request)?;
+    stream.flush()?;
+
+    let request_id = request.header.request_id();
+    loop {
+        let message = read_message(stream)?;
+        if message.is_response() && message.header.request_id() == request_id {
+            return Ok(message);
+        }
+    }
+}
+
 /// A buffered reader for SOME/IP messages.
 ///
 /// This handles partial reads and accumulates data until a complete
@@ -134,6 +204,16 @@ impl MessageReader {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// Get the expected total size of the next buffered message.
+    ///
+    /// Returns `None` until a full header is buffered. Compare with
+    /// [`len`](Self::len) to see how many more bytes are needed.
+    pub fn pending_message_size(&self) -> Option<usize> {
+        let header = self.buffer.get(self.position..self.position + HEADER_SIZE)?;
+        let length = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+        Some(HEADER_SIZE + length.saturating_sub(8) as usize)
+    }
 }
 
 impl Default for MessageReader {
@@ -204,6 +284,49 @@ mod tests {
         assert_eq!(original, parsed);
     }
 
+    #[test]
+    fn test_exchange() {
+        use crate::header::{ClientId, SessionId};
+        use std::net::{TcpListener, TcpStream};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let request = read_message(&mut stream).unwrap();
+            let event = SomeIpMessage::notification(ServiceId(0x1234), MethodId::event(0x0001))
+                .build();
+            write_message(&mut stream, &event).unwrap();
+            let response = request.create_response().payload(b"pong".as_slice()).build();
+            write_message(&mut stream, &response).unwrap();
+        });
+
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .client_id(ClientId(0x0100))
+            .session_id(SessionId(0x0007))
+            .payload(b"ping".as_slice())
+            .build();
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let response = exchange(&mut stream, &request).unwrap();
+        server.join().unwrap();
+
+        assert!(response.is_response_to(&request));
+        assert_eq!(response.payload.as_ref(), b"pong");
+    }
+
+    #[test]
+    fn test_try_parse_header() {
+        let msg = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .payload(b"truncated".as_slice())
+            .build();
+        let bytes = msg.to_bytes();
+
+        assert!(SomeIpMessage::from_bytes(&bytes[..HEADER_SIZE + 2]).is_err());
+        let header = try_parse_header(&bytes[..HEADER_SIZE + 2]).unwrap();
+        assert_eq!(header, msg.header);
+        assert!(try_parse_header(&bytes[..HEADER_SIZE - 1]).is_none());
+    }
+
     #[test]
     fn test_message_reader_complete() {
         let msg = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
@@ -241,6 +364,27 @@ mod tests {
         assert_eq!(parsed.unwrap(), msg);
     }
 
+    #[test]
+    fn test_message_reader_pending_message_size() {
+        let msg = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .payload(vec![0xAB; 20])
+            .build();
+        let bytes = msg.to_bytes();
+
+        let mut reader = MessageReader::new();
+        reader.feed(&bytes[..HEADER_SIZE - 1]);
+        assert_eq!(reader.pending_message_size(), None);
+
+        reader.feed(&bytes[HEADER_SIZE - 1..HEADER_SIZE + 10]);
+        assert!(reader.try_parse().unwrap().is_none());
+        assert_eq!(reader.pending_message_size(), Some(HEADER_SIZE + 20));
+        assert_eq!(reader.len(), HEADER_SIZE + 10);
+
+        reader.feed(&bytes[HEADER_SIZE + 10..]);
+        assert!(reader.try_parse().unwrap().is_some());
+        assert_eq!(reader.pending_message_size(), None);
+    }
+
     #[test]
     fn test_message_reader_multiple() {
         let msg1 = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
@@ -1,7 +1,10 @@
 //! Connection management configuration types.
 
+use std::io;
 use std::time::Duration;
 
+use crate::error::SomeIpError;
+
 /// Backoff strategy for reconnection attempts.
 #[derive(Debug, Clone)]
 pub enum BackoffStrategy {
@@ -126,6 +129,21 @@ impl RetryPolicy {
     pub fn delay_for_attempt(&self, attempt: u32) -> Duration {
         self.backoff.delay_for_attempt(attempt)
     }
+
+    /// Check if an error is retryable under this policy.
+    pub fn is_retryable(&self, err: &SomeIpError) -> bool {
+        match err {
+            SomeIpError::Timeout => self.retry_on_timeout,
+            SomeIpError::Io(io_err) => match io_err.kind() {
+                io::ErrorKind::ConnectionReset | io::ErrorKind::BrokenPipe => {
+                    self.retry_on_connection_reset
+                }
+                io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => self.retry_on_timeout,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
 }
 
 /// Keep-alive configuration.
@@ -164,6 +182,8 @@ pub struct ConnectionConfig {
     pub read_timeout: Option<Duration>,
     /// Write timeout.
     pub write_timeout: Option<Duration>,
+    /// SO_LINGER duration applied to new connections.
+    pub linger: Option<Duration>,
 }
 
 impl Default for ConnectionConfig {
@@ -175,6 +195,7 @@ impl Default for ConnectionConfig {
             connect_timeout: Duration::from_secs(5),
             read_timeout: Some(Duration::from_secs(30)),
             write_timeout: Some(Duration::from_secs(30)),
+            linger: None,
         }
     }
 }
@@ -189,6 +210,7 @@ impl ConnectionConfig {
             connect_timeout: Duration::from_secs(5),
             read_timeout: None,
             write_timeout: None,
+            linger: None,
         }
     }
 
@@ -238,6 +260,12 @@ impl ConnectionConfig {
         self.write_timeout = Some(timeout);
         self
     }
+
+    /// Set the SO_LINGER duration.
+    pub fn with_linger(mut self, linger: Duration) -> Self {
+        self.linger = Some(linger);
+        self
+    }
 }
 
 /// Connection pool configuration.
@@ -4,14 +4,18 @@ use std::io;
 use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
 use std::sync::atomic::{AtomicU16, Ordering};
 use std::thread;
+use std::time::{Duration, Instant};
+
+use socket2::SockRef;
 
 use crate::codec::{read_message, write_message};
-use crate::error::Result;
+use crate::error::{Result, SomeIpError};
 use crate::header::{ClientId, SessionId};
 use crate::message::SomeIpMessage;
+use crate::transport::{map_io_timeout, read_until};
 
 use super::config::ConnectionConfig;
-use super::state::{ConnectionState, ConnectionStats};
+use super::state::{ConnectionState, ConnectionStats, LatencyHistogram};
 
 /// A managed TCP client with auto-reconnect capability.
 ///
@@ -34,10 +38,17 @@ pub struct ManagedTcpClient {
     session_counter: AtomicU16,
     /// Connection statistics.
     stats: ConnectionStats,
+    /// Request/response latency histogram.
+    latency: LatencyHistogram,
     /// Current reconnection attempt count.
     reconnect_attempts: u32,
+    /// Callback invoked when dropped while reconnecting.
+    drop_warning: Option<DropWarningCallback>,
 }
 
+/// Callback invoked with the target address and reconnection attempt count.
+type DropWarningCallback = Box<dyn Fn(SocketAddr, u32) + Send>;
+
 impl ManagedTcpClient {
     /// Create a new managed client for the given address.
     pub fn new<A: ToSocketAddrs>(addr: A, config: ConnectionConfig) -> io::Result<Self> {
@@ -54,7 +65,9 @@ impl ManagedTcpClient {
             client_id: ClientId(0x0001),
             session_counter: AtomicU16::new(1),
             stats: ConnectionStats::default(),
+            latency: LatencyHistogram::new(),
             reconnect_attempts: 0,
+            drop_warning: None,
         })
     }
 
@@ -75,6 +88,17 @@ impl ManagedTcpClient {
         &self.stats
     }
 
+    /// Get the request/response latency histogram as (upper bound, count) pairs.
+    pub fn latency_histogram(&self) -> &[(Duration, u64)] {
+        self.latency.buckets()
+    }
+
+    /// Reset connection statistics and the latency histogram.
+    pub fn reset_stats(&mut self) {
+        self.stats = ConnectionStats::default();
+        self.latency.reset();
+    }
+
     /// Set the client ID.
     pub fn set_client_id(&mut self, client_id: ClientId) {
         self.client_id = client_id;
@@ -95,6 +119,17 @@ impl ManagedTcpClient {
         self.state.is_connected()
     }
 
+    /// Set a callback invoked if the client is dropped while reconnecting.
+    ///
+    /// The callback receives the target address and the number of
+    /// reconnection attempts made so far.
+    pub fn on_drop_while_reconnecting(
+        &mut self,
+        callback: impl Fn(SocketAddr, u32) + Send + 'static,
+    ) {
+        self.drop_warning = Some(Box::new(callback));
+    }
+
     /// Get the next session ID.
     fn next_session_id(&self) -> SessionId {
         let id = self.session_counter.fetch_add(1, Ordering::Relaxed);
@@ -117,9 +152,14 @@ impl ManagedTcpClient {
 
     /// Perform the actual connection.
     fn do_connect(&mut self) -> Result<()> {
+        self.connect_within(self.config.connect_timeout)
+    }
+
+    /// Connect, waiting at most `timeout` for the connection to be established.
+    fn connect_within(&mut self, timeout: Duration) -> Result<()> {
         self.state = ConnectionState::Connecting;
 
-        match TcpStream::connect_timeout(&self.addr, self.config.connect_timeout) {
+        match TcpStream::connect_timeout(&self.addr, timeout) {
             Ok(stream) => {
                 // Apply timeouts
                 if let Some(timeout) = self.config.read_timeout {
@@ -128,6 +168,9 @@ impl ManagedTcpClient {
                 if let Some(timeout) = self.config.write_timeout {
                     let _ = stream.set_write_timeout(Some(timeout));
                 }
+                if let Some(linger) = self.config.linger {
+                    let _ = SockRef::from(&stream).set_linger(Some(linger));
+                }
 
                 self.stream = Some(stream);
                 self.state = ConnectionState::Connected;
@@ -181,25 +224,13 @@ impl ManagedTcpClient {
         self.state = ConnectionState::Disconnected;
         self.stats.record_disconnect();
 
-        match &err {
-            crate::error::SomeIpError::Io(io_err) => {
-                let should_retry = match io_err.kind() {
-                    io::ErrorKind::ConnectionReset | io::ErrorKind::BrokenPipe => {
-                        self.config.retry_policy.retry_on_connection_reset
-                    }
-                    io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => {
-                        self.config.retry_policy.retry_on_timeout
-                    }
-                    _ => false,
-                };
-
-                if should_retry && self.config.auto_reconnect {
-                    self.try_reconnect()?;
-                    // After reconnection, the caller should retry the operation
-                    return Err(err);
-                }
-            }
-            _ => {}
+        if matches!(err, crate::error::SomeIpError::Io(_))
+            && self.config.retry_policy.is_retryable(&err)
+            && self.config.auto_reconnect
+        {
+            self.try_reconnect()?;
+            // After reconnection, the caller should retry the operation
+            return Err(err);
         }
 
         Err(err)
@@ -217,6 +248,7 @@ impl ManagedTcpClient {
         // Send request
         let bytes = message.to_bytes();
         let stream = self.stream.as_mut().unwrap();
+        let started = Instant::now();
 
         if let Err(e) = write_message(stream, &message) {
             return self.handle_error(e);
@@ -225,19 +257,113 @@ impl ManagedTcpClient {
         self.stats.record_send(bytes.len());
 
         // Receive response
-        loop {
-            match read_message(stream) {
-                Ok(response) => {
-                    self.stats.record_receive(response.to_bytes().len());
-                    if response.header.request_id() == request_id {
-                        return Ok(response);
-                    }
+        match self.read_response(request_id, started, None) {
+            Ok(response) => Ok(response),
+            Err(e) => self.handle_error(e),
+        }
+    }
+
+    /// Send a request and wait for a response until an absolute deadline.
+    ///
+    /// The time remaining until `deadline` bounds connecting, writing the
+    /// request and every read of the call. Returns [`SomeIpError::Timeout`]
+    /// without sending if the deadline has already passed. A timeout that
+    /// fires while a message is only partially sent or received leaves the
+    /// connection unusable, so it is disconnected.
+    pub fn call_deadline(
+        &mut self,
+        mut message: SomeIpMessage,
+        deadline: Instant,
+    ) -> Result<SomeIpMessage> {
+        if deadline <= Instant::now() {
+            return Err(SomeIpError::Timeout);
+        }
+
+        message.header.client_id = self.client_id;
+        message.header.session_id = self.next_session_id();
+
+        if self.stream.is_none() || self.state != ConnectionState::Connected {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(SomeIpError::Timeout);
+            }
+            if let Err(e) = self.connect_within(remaining.min(self.config.connect_timeout)) {
+                return Err(if Instant::now() >= deadline { SomeIpError::Timeout } else { e });
+            }
+        }
+
+        let request_id = message.header.request_id();
+        let bytes = message.to_bytes();
+        let stream = self.stream.as_mut().unwrap();
+        let started = Instant::now();
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(SomeIpError::Timeout);
+        }
+        let written = stream
+            .set_write_timeout(Some(remaining))
+            .map_err(SomeIpError::io)
+            .and_then(|()| write_message(stream, &message));
+        let _ = stream.set_write_timeout(self.config.write_timeout);
+        match written.map_err(map_io_timeout) {
+            Ok(()) => {}
+            Err(SomeIpError::Timeout) => {
+                self.disconnect();
+                return Err(SomeIpError::Timeout);
+            }
+            Err(e) => return self.handle_error(e),
+        }
+
+        self.stats.record_send(bytes.len());
+
+        match self.read_response(request_id, started, Some(deadline)) {
+            Ok(response) => {
+                if let Some(stream) = &self.stream {
+                    let _ = stream.set_read_timeout(self.config.read_timeout);
                 }
-                Err(e) => return self.handle_error(e),
+                Ok(response)
+            }
+            Err(SomeIpError::Timeout) => {
+                self.disconnect();
+                Err(SomeIpError::Timeout)
             }
+            Err(e) => self.handle_error(e),
         }
     }
 
+    /// Read messages until the response to `request_id` arrives.
+    ///
+    /// With a deadline, every read is bounded by the time left until it.
+    fn read_response(
+        &mut self,
+        request_id: u32,
+        started: Instant,
+        deadline: Option<Instant>,
+    ) -> Result<SomeIpMessage> {
+        let stream = self.stream.as_mut().ok_or(SomeIpError::ConnectionClosed)?;
+        let stats = &mut self.stats;
+        let mut read = |timeout: Option<Duration>| {
+            if timeout.is_some() {
+                stream.set_read_timeout(timeout)?;
+            }
+            let response = read_message(stream)?;
+            stats.record_receive(response.to_bytes().len());
+            Ok((response.header.request_id() == request_id).then_some(response))
+        };
+
+        let response = match deadline {
+            Some(deadline) => read_until(deadline, |remaining| read(Some(remaining)))?,
+            None => loop {
+                if let Some(response) = read(None)? {
+                    break response;
+                }
+            },
+        };
+        self.latency.record(started.elapsed());
+        Ok(response)
+    }
+
     /// Send a fire-and-forget message.
     pub fn send(&mut self, mut message: SomeIpMessage) -> Result<()> {
         message.header.client_id = self.client_id;
@@ -289,6 +415,16 @@ impl ManagedTcpClient {
     }
 }
 
+impl Drop for ManagedTcpClient {
+    fn drop(&mut self) {
+        if let Some(callback) = &self.drop_warning {
+            if self.state == ConnectionState::Reconnecting || self.reconnect_attempts > 0 {
+                callback(self.addr, self.reconnect_attempts);
+            }
+        }
+    }
+}
+
 impl std::fmt::Debug for ManagedTcpClient {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("ManagedTcpClient")
@@ -324,4 +460,140 @@ mod tests {
 
         assert_eq!(client.client_id(), ClientId(0x1234));
     }
+
+    #[test]
+    fn test_managed_client_linger() {
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let config = ConnectionConfig::simple().with_linger(Duration::from_secs(2));
+        let client = ManagedTcpClient::connect(listener.local_addr().unwrap(), config).unwrap();
+
+        let stream = client.stream.as_ref().unwrap();
+        assert_eq!(SockRef::from(stream).linger().unwrap(), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_managed_client_call_deadline() {
+        use crate::header::{MethodId, ServiceId};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client =
+            ManagedTcpClient::new(listener.local_addr().unwrap(), ConnectionConfig::simple())
+                .unwrap();
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001)).build();
+
+        // An expired deadline fails without connecting
+        let started = Instant::now();
+        assert!(matches!(
+            client.call_deadline(request.clone(), started),
+            Err(SomeIpError::Timeout)
+        ));
+        assert!(started.elapsed() < Duration::from_millis(100));
+        assert!(!client.is_connected());
+
+        // A silent server runs into the deadline
+        let deadline = Instant::now() + Duration::from_millis(50);
+        assert!(matches!(
+            client.call_deadline(request, deadline),
+            Err(SomeIpError::Timeout)
+        ));
+        assert!(Instant::now() >= deadline);
+        assert!(!client.is_connected());
+
+        // A server that never reads makes the write run into the deadline.
+        // Small socket buffers keep the payload needed to fill them small.
+        SockRef::from(&listener).set_recv_buffer_size(4096).unwrap();
+        client.reconnect().unwrap();
+        let stream = client.stream.as_ref().unwrap();
+        SockRef::from(stream).set_send_buffer_size(4096).unwrap();
+        let large = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0002))
+            .payload(vec![0u8; 4 * 1024 * 1024])
+            .build();
+        let deadline = Instant::now() + Duration::from_millis(100);
+        assert!(matches!(
+            client.call_deadline(large, deadline),
+            Err(SomeIpError::Timeout)
+        ));
+        assert!(deadline.elapsed() < Duration::from_secs(1));
+        assert!(!client.is_connected());
+    }
+
+    #[test]
+    fn test_managed_client_drop_warning() {
+        use std::sync::Arc;
+        use std::sync::atomic::AtomicU32;
+
+        let warnings = Arc::new(AtomicU32::new(0));
+
+        for state in [
+            ConnectionState::Disconnected,
+            ConnectionState::Connecting,
+            ConnectionState::Connected,
+            ConnectionState::Reconnecting,
+            ConnectionState::Failed,
+        ] {
+            let mut client =
+                ManagedTcpClient::new("127.0.0.1:30490", ConnectionConfig::simple()).unwrap();
+            let counter = Arc::clone(&warnings);
+            client.on_drop_while_reconnecting(move |_, _| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            });
+            client.state = state;
+            drop(client);
+
+            // Without a callback, dropping is silent
+            let mut client =
+                ManagedTcpClient::new("127.0.0.1:30490", ConnectionConfig::simple()).unwrap();
+            client.state = state;
+            drop(client);
+        }
+
+        assert_eq!(warnings.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_managed_client_latency_histogram() {
+        use crate::header::{MethodId, ServiceId};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // Slow echo server: the first payload byte is the delay in milliseconds
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            for _ in 0..3 {
+                let request = read_message(&mut stream).unwrap();
+                thread::sleep(Duration::from_millis(request.payload[0] as u64));
+                write_message(&mut stream, &request.create_response().build()).unwrap();
+            }
+        });
+
+        let mut client = ManagedTcpClient::connect(addr, ConnectionConfig::simple()).unwrap();
+        for delay in [40u8, 40, 150] {
+            let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+                .payload(vec![delay])
+                .build();
+            client.call(request).unwrap();
+        }
+        server.join().unwrap();
+
+        let histogram = client.latency_histogram();
+        let count_for = |bound_ms| {
+            histogram
+                .iter()
+                .find(|(bound, _)| *bound == Duration::from_millis(bound_ms))
+                .unwrap()
+                .1
+        };
+        assert_eq!(count_for(64), 2);
+        assert_eq!(count_for(256), 1);
+        assert_eq!(histogram.iter().map(|(_, count)| count).sum::<u64>(), 3);
+
+        client.reset_stats();
+        assert!(client.latency_histogram().iter().all(|(_, count)| *count == 0));
+        assert_eq!(client.stats().messages_sent, 0);
+    }
 }
@@ -4,10 +4,12 @@ use std::io;
 use std::net::SocketAddr;
 use std::sync::atomic::{AtomicU16, Ordering};
 
+use socket2::SockRef;
 use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::sync::{mpsc, oneshot};
 use tokio::time::{sleep, timeout};
 
-use crate::error::Result;
+use crate::error::{Result, SomeIpError};
 use crate::header::{ClientId, SessionId};
 use crate::message::SomeIpMessage;
 use crate::transport_async::AsyncTcpConnection;
@@ -118,6 +120,9 @@ impl AsyncManagedTcpClient {
 
         match timeout(self.config.connect_timeout, TcpStream::connect(self.addr)).await {
             Ok(Ok(stream)) => {
+                if let Some(linger) = self.config.linger {
+                    let _ = SockRef::from(&stream).set_linger(Some(linger));
+                }
                 let connection = AsyncTcpConnection::new(stream)?;
                 self.connection = Some(connection);
                 self.state = ConnectionState::Connected;
@@ -179,24 +184,21 @@ impl AsyncManagedTcpClient {
         self.state = ConnectionState::Disconnected;
         self.stats.record_disconnect();
 
-        match &err {
-            crate::error::SomeIpError::Io(io_err) => {
-                let should_retry = match io_err.kind() {
-                    io::ErrorKind::ConnectionReset | io::ErrorKind::BrokenPipe => {
-                        self.config.retry_policy.retry_on_connection_reset
-                    }
-                    io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => {
-                        self.config.retry_policy.retry_on_timeout
-                    }
-                    _ => false,
-                };
-
-                if should_retry && self.config.auto_reconnect {
-                    self.try_reconnect().await?;
-                    return Err(err);
+        if let crate::error::SomeIpError::Io(io_err) = &err {
+            let should_retry = match io_err.kind() {
+                io::ErrorKind::ConnectionReset | io::ErrorKind::BrokenPipe => {
+                    self.config.retry_policy.retry_on_connection_reset
                 }
+                io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => {
+                    self.config.retry_policy.retry_on_timeout
+                }
+                _ => false,
+            };
+
+            if should_retry && self.config.auto_reconnect {
+                self.try_reconnect().await?;
+                return Err(err);
             }
-            _ => {}
         }
 
         Err(err)
@@ -299,6 +301,52 @@ impl AsyncManagedTcpClient {
         self.reconnect_attempts = 0;
         self.ensure_connected().await
     }
+
+    /// Move the client into a driver task and return a cloneable handle to it.
+    ///
+    /// Requests from all handles are serialized through a queue of
+    /// `queue_depth` entries; callers wait while it is full. The driver task
+    /// stops once every handle is dropped. Must be called within a Tokio runtime.
+    pub fn into_handle(mut self, queue_depth: usize) -> ClientHandle {
+        let (sender, mut receiver) = mpsc::channel::<QueuedRequest>(queue_depth.max(1));
+
+        tokio::spawn(async move {
+            while let Some(request) = receiver.recv().await {
+                let result = self.call(request.message).await;
+                let _ = request.reply.send(result);
+            }
+        });
+
+        ClientHandle { sender }
+    }
+}
+
+/// A request waiting for the driver task of a [`ClientHandle`].
+struct QueuedRequest {
+    message: SomeIpMessage,
+    reply: oneshot::Sender<Result<SomeIpMessage>>,
+}
+
+/// Cloneable handle to an [`AsyncManagedTcpClient`] running in a driver task.
+///
+/// Created by [`AsyncManagedTcpClient::into_handle`].
+#[derive(Debug, Clone)]
+pub struct ClientHandle {
+    sender: mpsc::Sender<QueuedRequest>,
+}
+
+impl ClientHandle {
+    /// Send a request and wait for a response.
+    ///
+    /// Waits for queue space first if the queue is full.
+    pub async fn call(&self, message: SomeIpMessage) -> Result<SomeIpMessage> {
+        let (reply, response) = oneshot::channel();
+        self.sender
+            .send(QueuedRequest { message, reply })
+            .await
+            .map_err(|_| SomeIpError::ConnectionClosed)?;
+        response.await.map_err(|_| SomeIpError::ConnectionClosed)?
+    }
 }
 
 impl std::fmt::Debug for AsyncManagedTcpClient {
@@ -340,4 +388,44 @@ mod tests {
 
         assert_eq!(client.client_id(), ClientId(0x1234));
     }
+
+    #[tokio::test]
+    async fn test_async_managed_client_handle() {
+        use crate::header::{MethodId, ServiceId};
+        use crate::transport_async::AsyncTcpServer;
+
+        let server = AsyncTcpServer::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr();
+
+        // Echo server
+        tokio::spawn(async move {
+            let (mut conn, _) = server.accept().await.unwrap();
+            while let Ok(request) = conn.read_message().await {
+                let response = request.create_response().payload(request.payload.clone()).build();
+                conn.write_message(&response).await.unwrap();
+            }
+        });
+
+        let client = AsyncManagedTcpClient::connect(addr, ConnectionConfig::simple())
+            .await
+            .unwrap();
+        let handle = client.into_handle(2);
+
+        let tasks: Vec<_> = (0..10u8)
+            .map(|i| {
+                let handle = handle.clone();
+                tokio::spawn(async move {
+                    let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+                        .payload(vec![i])
+                        .build();
+                    let response = handle.call(request).await.unwrap();
+                    assert_eq!(response.payload.as_ref(), &[i]);
+                })
+            })
+            .collect();
+
+        for task in tasks {
+            task.await.unwrap();
+        }
+    }
 }
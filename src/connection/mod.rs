@@ -4,6 +4,7 @@
 //! - Auto-reconnecting TCP clients
 //! - Connection pooling
 //! - Configuration for retries, timeouts, and keep-alive
+//! - A generic [`retry`] helper for arbitrary operations
 //!
 //! # Example
 //!
@@ -51,12 +52,14 @@
 mod config;
 mod managed_tcp;
 mod pool;
+mod retry;
 mod state;
 
 pub use config::{BackoffStrategy, ConnectionConfig, KeepAliveConfig, PoolConfig, RetryPolicy};
 pub use managed_tcp::ManagedTcpClient;
-pub use pool::{ConnectionPool, PooledTcpClient};
-pub use state::{ConnectionState, ConnectionStats};
+pub use pool::{ConnectionPool, EvictionReason, PoolEvent, PooledTcpClient};
+pub use retry::retry;
+pub use state::{ConnectionState, ConnectionStats, LatencyHistogram};
 
 // Async variants (require tokio feature)
 #[cfg(feature = "tokio")]
@@ -65,6 +68,6 @@ mod managed_tcp_async;
 mod pool_async;
 
 #[cfg(feature = "tokio")]
-pub use managed_tcp_async::AsyncManagedTcpClient;
+pub use managed_tcp_async::{AsyncManagedTcpClient, ClientHandle};
 #[cfg(feature = "tokio")]
 pub use pool_async::{AsyncConnectionPool, AsyncPooledTcpClient};
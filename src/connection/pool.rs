@@ -3,14 +3,59 @@
 use std::collections::HashMap;
 use std::io;
 use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::error::Result;
+use crate::clock::{self, Clock};
+use crate::error::{Result, SomeIpError};
 use crate::transport::TcpClient;
 
 use super::config::PoolConfig;
 
+/// Why a connection was removed from the pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionReason {
+    /// The connection was idle for longer than the idle timeout.
+    IdleTimeout,
+    /// The connection exceeded its maximum lifetime.
+    MaxLifetime,
+    /// The endpoint already had the maximum number of pooled connections.
+    PoolLimit,
+    /// The pool was cleared.
+    Cleared,
+    /// The connection failed and was invalidated by its user.
+    Invalidated,
+}
+
+/// Connection lifecycle events emitted by a [`ConnectionPool`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PoolEvent {
+    /// A new connection was established.
+    ConnectionCreated {
+        /// Endpoint address.
+        addr: SocketAddr,
+    },
+    /// A pooled connection was closed.
+    ConnectionEvicted {
+        /// Endpoint address.
+        addr: SocketAddr,
+        /// Why the connection was evicted.
+        reason: EvictionReason,
+    },
+    /// A connection was returned to the pool for reuse.
+    ConnectionReturned {
+        /// Endpoint address.
+        addr: SocketAddr,
+    },
+    /// A connection was refused because the endpoint limit was reached.
+    PoolFull {
+        /// Endpoint address.
+        addr: SocketAddr,
+    },
+}
+
 /// Entry in the connection pool.
 struct PoolEntry {
     /// The client connection.
@@ -24,8 +69,7 @@ struct PoolEntry {
 }
 
 impl PoolEntry {
-    fn new(client: TcpClient) -> Self {
-        let now = Instant::now();
+    fn new(client: TcpClient, now: Instant) -> Self {
         Self {
             client,
             created_at: now,
@@ -34,20 +78,20 @@ impl PoolEntry {
         }
     }
 
-    fn is_expired(&self, config: &PoolConfig) -> bool {
+    fn expiry_reason(&self, config: &PoolConfig, now: Instant) -> Option<EvictionReason> {
         // Check idle timeout
-        if self.last_used.elapsed() > config.idle_timeout {
-            return true;
+        if now.saturating_duration_since(self.last_used) > config.idle_timeout {
+            return Some(EvictionReason::IdleTimeout);
         }
 
         // Check max lifetime
         if let Some(max_lifetime) = config.max_lifetime {
-            if self.created_at.elapsed() > max_lifetime {
-                return true;
+            if now.saturating_duration_since(self.created_at) > max_lifetime {
+                return Some(EvictionReason::MaxLifetime);
             }
         }
 
-        false
+        None
     }
 }
 
@@ -59,9 +103,25 @@ pub struct PooledTcpClient {
     pool: Arc<Mutex<PoolInner>>,
     /// Address of this connection.
     addr: SocketAddr,
+    /// When this connection was created.
+    created_at: Instant,
+    /// When this connection was last checked out or used.
+    last_used: Instant,
+    /// Pool clock, used to compute age and idle time.
+    clock: Arc<dyn Clock>,
 }
 
 impl PooledTcpClient {
+    /// Get how long ago the underlying connection was established.
+    pub fn age(&self) -> Duration {
+        self.clock.now().saturating_duration_since(self.created_at)
+    }
+
+    /// Get how long the connection has been idle since checkout or its last use.
+    pub fn idle_time(&self) -> Duration {
+        self.clock.now().saturating_duration_since(self.last_used)
+    }
+
     /// Get a reference to the underlying client.
     pub fn client(&self) -> &TcpClient {
         self.client.as_ref().unwrap()
@@ -77,25 +137,39 @@ impl PooledTcpClient {
         &mut self,
         message: crate::message::SomeIpMessage,
     ) -> Result<crate::message::SomeIpMessage> {
+        self.last_used = self.clock.now();
         self.client_mut().call(message)
     }
 
     /// Send a fire-and-forget message.
     pub fn send(&mut self, message: crate::message::SomeIpMessage) -> Result<()> {
+        self.last_used = self.clock.now();
         self.client_mut().send(message)
     }
 
     /// Receive a message.
     pub fn receive(&mut self) -> Result<crate::message::SomeIpMessage> {
+        self.last_used = self.clock.now();
         self.client_mut().receive()
     }
+
+    /// Discard a broken connection instead of returning it to the pool.
+    pub fn invalidate(mut self) {
+        if self.client.take().is_some() {
+            let addr = self.addr;
+            self.pool.lock().unwrap().emit(PoolEvent::ConnectionEvicted {
+                addr,
+                reason: EvictionReason::Invalidated,
+            });
+        }
+    }
 }
 
 impl Drop for PooledTcpClient {
     fn drop(&mut self) {
         if let Some(client) = self.client.take() {
             let mut pool = self.pool.lock().unwrap();
-            pool.return_connection(self.addr, client);
+            pool.return_connection(self.addr, client, self.created_at);
         }
     }
 }
@@ -120,6 +194,10 @@ struct PoolInner {
     config: PoolConfig,
     /// Connections by address.
     connections: HashMap<SocketAddr, Vec<PoolEntry>>,
+    /// Lifecycle event subscribers.
+    subscribers: Vec<Sender<PoolEvent>>,
+    /// Clock used for idle and lifetime expiry.
+    clock: Arc<dyn Clock>,
 }
 
 impl PoolInner {
@@ -127,46 +205,69 @@ impl PoolInner {
         Self {
             config,
             connections: HashMap::new(),
+            subscribers: Vec::new(),
+            clock: clock::system(),
         }
     }
 
-    /// Get an available connection for the given address.
-    fn get_connection(&mut self, addr: SocketAddr) -> Option<TcpClient> {
-        let entries = self.connections.entry(addr).or_default();
+    /// Send an event to all subscribers, dropping disconnected ones.
+    fn emit(&mut self, event: PoolEvent) {
+        self.subscribers.retain(|tx| tx.send(event.clone()).is_ok());
+    }
 
+    /// Remove expired connections for an address, returning the eviction reasons.
+    fn evict_expired(&mut self, addr: SocketAddr) -> Vec<EvictionReason> {
+        let mut reasons = Vec::new();
+        let now = self.clock.now();
+        if let Some(entries) = self.connections.get_mut(&addr) {
+            entries.retain(|e| match e.expiry_reason(&self.config, now) {
+                Some(reason) if !e.in_use => {
+                    reasons.push(reason);
+                    false
+                }
+                _ => true,
+            });
+        }
+        reasons
+    }
+
+    /// Get an available connection for the given address.
+    fn get_connection(&mut self, addr: SocketAddr) -> Option<PoolEntry> {
         // Clean up expired connections first
-        entries.retain(|e| !e.in_use && !e.is_expired(&self.config));
-
-        // Find an available connection
-        for entry in entries.iter_mut() {
-            if !entry.in_use {
-                entry.in_use = true;
-                entry.last_used = Instant::now();
-                // We need to take ownership, so we'll swap with a placeholder
-                // Actually, we need to remove and return
-            }
+        for reason in self.evict_expired(addr) {
+            self.emit(PoolEvent::ConnectionEvicted { addr, reason });
         }
 
+        let entries = self.connections.entry(addr).or_default();
+
         // Find and remove an available entry
         if let Some(pos) = entries.iter().position(|e| !e.in_use) {
             let mut entry = entries.remove(pos);
             entry.in_use = true;
-            entry.last_used = Instant::now();
-            return Some(entry.client);
+            entry.last_used = self.clock.now();
+            return Some(entry);
         }
 
         None
     }
 
-    /// Return a connection to the pool.
-    fn return_connection(&mut self, addr: SocketAddr, client: TcpClient) {
+    /// Return a connection to the pool, keeping its original creation time.
+    fn return_connection(&mut self, addr: SocketAddr, client: TcpClient, created_at: Instant) {
         let entries = self.connections.entry(addr).or_default();
 
         // Only add back if we're under the limit
         if entries.len() < self.config.max_connections_per_endpoint {
-            entries.push(PoolEntry::new(client));
+            let mut entry = PoolEntry::new(client, self.clock.now());
+            entry.created_at = created_at;
+            entries.push(entry);
+            self.emit(PoolEvent::ConnectionReturned { addr });
+        } else {
+            // Otherwise the connection is just dropped
+            self.emit(PoolEvent::ConnectionEvicted {
+                addr,
+                reason: EvictionReason::PoolLimit,
+            });
         }
-        // Otherwise the connection is just dropped
     }
 
     /// Get the current count of connections for an address.
@@ -182,15 +283,32 @@ impl PoolInner {
     /// Clean up expired connections across all endpoints.
     fn cleanup(&mut self) -> usize {
         let mut removed = 0;
-        for entries in self.connections.values_mut() {
-            let before = entries.len();
-            entries.retain(|e| !e.is_expired(&self.config));
-            removed += before - entries.len();
+        let addrs: Vec<_> = self.connections.keys().copied().collect();
+        for addr in addrs {
+            for reason in self.evict_expired(addr) {
+                self.emit(PoolEvent::ConnectionEvicted { addr, reason });
+                removed += 1;
+            }
         }
         // Remove empty endpoint entries
         self.connections.retain(|_, v| !v.is_empty());
         removed
     }
+
+    /// Remove all pooled connections.
+    fn clear(&mut self) {
+        let evicted: Vec<_> = self
+            .connections
+            .drain()
+            .flat_map(|(addr, entries)| std::iter::repeat_n(addr, entries.len()))
+            .collect();
+        for addr in evicted {
+            self.emit(PoolEvent::ConnectionEvicted {
+                addr,
+                reason: EvictionReason::Cleared,
+            });
+        }
+    }
 }
 
 /// A connection pool for TCP clients.
@@ -224,7 +342,7 @@ impl ConnectionPool {
     pub fn get<A: ToSocketAddrs>(&self, addr: A) -> Result<PooledTcpClient> {
         let addr = addr
             .to_socket_addrs()
-            .map_err(|e| crate::error::SomeIpError::Io(e))?
+            .map_err(crate::error::SomeIpError::Io)?
             .next()
             .ok_or_else(|| {
                 crate::error::SomeIpError::Io(io::Error::new(
@@ -233,21 +351,31 @@ impl ConnectionPool {
                 ))
             })?;
 
-        let mut pool = self.inner.lock().unwrap();
-
         // Try to get an existing connection
-        if let Some(client) = pool.get_connection(addr) {
+        let mut pool = self.inner.lock().unwrap();
+        if let Some(entry) = pool.get_connection(addr) {
             return Ok(PooledTcpClient {
-                client: Some(client),
+                client: Some(entry.client),
                 pool: self.inner.clone(),
                 addr,
+                created_at: entry.created_at,
+                last_used: entry.last_used,
+                clock: pool.clock.clone(),
             });
         }
+        drop(pool);
+
+        self.connect_new(addr)
+    }
+
+    /// Establish a new connection, bypassing idle pooled ones.
+    fn connect_new(&self, addr: SocketAddr) -> Result<PooledTcpClient> {
+        let mut pool = self.inner.lock().unwrap();
 
         // Check if we can create a new connection
         if pool.connection_count(&addr) >= pool.config.max_connections_per_endpoint {
-            return Err(crate::error::SomeIpError::Io(io::Error::new(
-                io::ErrorKind::Other,
+            pool.emit(PoolEvent::PoolFull { addr });
+            return Err(crate::error::SomeIpError::Io(io::Error::other(
                 "Connection pool limit reached for endpoint",
             )));
         }
@@ -256,10 +384,22 @@ impl ConnectionPool {
         let connect_timeout = pool.config.connection_config.connect_timeout;
         let read_timeout = pool.config.connection_config.read_timeout;
         let write_timeout = pool.config.connection_config.write_timeout;
+        let retry_policy = pool.config.connection_config.retry_policy.clone();
+        let clock = pool.clock.clone();
         drop(pool);
 
-        // Create new connection
-        let client = TcpClient::connect_timeout(&addr, connect_timeout)?;
+        // Create new connection, retrying transient connect failures
+        let mut attempt = 0;
+        let client = loop {
+            match TcpClient::connect_timeout(&addr, connect_timeout) {
+                Ok(client) => break client,
+                Err(_) if retry_policy.should_retry(attempt) => {
+                    thread::sleep(retry_policy.delay_for_attempt(attempt));
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        };
 
         if let Some(timeout) = read_timeout {
             let _ = client.set_read_timeout(Some(timeout));
@@ -268,13 +408,46 @@ impl ConnectionPool {
             let _ = client.set_write_timeout(Some(timeout));
         }
 
+        self.inner
+            .lock()
+            .unwrap()
+            .emit(PoolEvent::ConnectionCreated { addr });
+
+        let now = clock.now();
         Ok(PooledTcpClient {
             client: Some(client),
             pool: self.inner.clone(),
             addr,
+            created_at: now,
+            last_used: now,
+            clock,
         })
     }
 
+    /// Run an operation with a pooled connection.
+    ///
+    /// If the operation fails with a transport error, the connection is
+    /// invalidated and the operation is retried once on a fresh connection.
+    pub fn with_connection<A: ToSocketAddrs, T>(
+        &self,
+        addr: A,
+        mut op: impl FnMut(&mut PooledTcpClient) -> Result<T>,
+    ) -> Result<T> {
+        let addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "No address provided"))?;
+
+        let mut conn = self.get(addr)?;
+        match op(&mut conn) {
+            Err(SomeIpError::Io(_) | SomeIpError::ConnectionClosed) => {
+                conn.invalidate();
+                op(&mut self.connect_new(addr)?)
+            }
+            result => result,
+        }
+    }
+
     /// Get the number of pooled connections for an address.
     pub fn connection_count<A: ToSocketAddrs>(&self, addr: A) -> io::Result<usize> {
         let addr = addr.to_socket_addrs()?.next().ok_or_else(|| {
@@ -302,7 +475,23 @@ impl ConnectionPool {
     /// Clear all pooled connections.
     pub fn clear(&self) {
         let mut pool = self.inner.lock().unwrap();
-        pool.connections.clear();
+        pool.clear();
+    }
+
+    /// Set the clock used for idle timeout and lifetime expiry.
+    pub fn set_clock(&self, clock: Arc<dyn Clock>) {
+        let mut pool = self.inner.lock().unwrap();
+        pool.clock = clock;
+    }
+
+    /// Subscribe to connection lifecycle events for the whole pool.
+    ///
+    /// Events are buffered until received; dropping the receiver unsubscribes.
+    pub fn subscribe_events(&self) -> Receiver<PoolEvent> {
+        let (tx, rx) = mpsc::channel();
+        let mut pool = self.inner.lock().unwrap();
+        pool.subscribers.push(tx);
+        rx
     }
 }
 
@@ -331,6 +520,132 @@ mod tests {
         assert_eq!(config.idle_timeout, Duration::from_secs(30));
     }
 
+    #[test]
+    fn test_pool_get_retries_connect() {
+        use crate::connection::{ConnectionConfig, RetryPolicy};
+        use std::net::TcpListener;
+
+        // Reserve a port, then free it so the first attempt is refused
+        let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+
+        let listener_handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            let listener = TcpListener::bind(addr).unwrap();
+            let _ = listener.accept().unwrap();
+        });
+
+        let config = PoolConfig::default().with_connection_config(
+            ConnectionConfig::simple()
+                .with_retry_policy(RetryPolicy::fixed(20, Duration::from_millis(25))),
+        );
+        let pool = ConnectionPool::new(config);
+
+        let conn = pool.get(addr).unwrap();
+        drop(conn);
+        assert_eq!(pool.connection_count(addr).unwrap(), 1);
+
+        listener_handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_pool_events() {
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let pool = ConnectionPool::new(
+            PoolConfig::default().with_idle_timeout(Duration::from_millis(10)),
+        );
+        let events = pool.subscribe_events();
+
+        let conn = pool.get(addr).unwrap();
+        drop(conn);
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(pool.cleanup(), 1);
+
+        let received: Vec<_> = events.try_iter().collect();
+        assert_eq!(
+            received,
+            vec![
+                PoolEvent::ConnectionCreated { addr },
+                PoolEvent::ConnectionReturned { addr },
+                PoolEvent::ConnectionEvicted {
+                    addr,
+                    reason: EvictionReason::IdleTimeout,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pool_with_connection_retries_broken() {
+        use crate::codec::{read_message, write_message};
+        use crate::header::{MethodId, ServiceId};
+        use crate::message::SomeIpMessage;
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let pool = ConnectionPool::with_defaults();
+        // Pool a connection that the server then closes
+        drop(pool.get(addr).unwrap());
+        drop(listener.accept().unwrap());
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let request = read_message(&mut stream).unwrap();
+            write_message(&mut stream, &request.create_response().build()).unwrap();
+        });
+
+        let events = pool.subscribe_events();
+        let mut attempts = 0;
+        let response = pool
+            .with_connection(addr, |conn| {
+                attempts += 1;
+                conn.call(SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001)).build())
+            })
+            .unwrap();
+        server.join().unwrap();
+
+        assert!(response.is_response());
+        assert_eq!(attempts, 2);
+        assert!(events.try_iter().any(|event| event
+            == PoolEvent::ConnectionEvicted {
+                addr,
+                reason: EvictionReason::Invalidated,
+            }));
+    }
+
+    #[test]
+    fn test_pooled_client_age_and_idle_time() {
+        use crate::clock::MockClock;
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let pool = ConnectionPool::with_defaults();
+        let clock = MockClock::new();
+        pool.set_clock(Arc::new(clock.clone()));
+
+        let conn = pool.get(addr).unwrap();
+        assert_eq!(conn.idle_time(), Duration::ZERO);
+        assert_eq!(conn.age(), Duration::ZERO);
+        drop(conn);
+
+        // Reuse keeps the original creation time but resets idle time
+        clock.advance(Duration::from_secs(10));
+        let conn = pool.get(addr).unwrap();
+        assert_eq!(conn.idle_time(), Duration::ZERO);
+        assert_eq!(conn.age(), Duration::from_secs(10));
+
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(conn.idle_time(), Duration::from_secs(2));
+        assert_eq!(conn.age(), Duration::from_secs(12));
+    }
+
     #[test]
     fn test_pool_new() {
         let pool = ConnectionPool::with_defaults();
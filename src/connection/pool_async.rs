@@ -212,7 +212,7 @@ impl AsyncConnectionPool {
     pub async fn get<A: ToSocketAddrs>(&self, addr: A) -> Result<AsyncPooledTcpClient> {
         let addr = tokio::net::lookup_host(addr)
             .await
-            .map_err(|e| crate::error::SomeIpError::Io(e))?
+            .map_err(crate::error::SomeIpError::Io)?
             .next()
             .ok_or_else(|| {
                 crate::error::SomeIpError::Io(io::Error::new(
@@ -234,8 +234,7 @@ impl AsyncConnectionPool {
 
         // Check if we can create a new connection
         if pool.connection_count(&addr) >= pool.config.max_connections_per_endpoint {
-            return Err(crate::error::SomeIpError::Io(io::Error::new(
-                io::ErrorKind::Other,
+            return Err(crate::error::SomeIpError::Io(io::Error::other(
                 "Connection pool limit reached for endpoint",
             )));
         }
@@ -0,0 +1,101 @@
+//! Generic retry helper.
+
+use std::thread;
+
+use crate::error::Result;
+
+use super::config::RetryPolicy;
+
+/// Run an operation, retrying it according to a retry policy.
+///
+/// The operation receives the 0-indexed attempt number. Errors that the
+/// policy does not consider retryable are returned immediately; otherwise the
+/// last error is returned once the policy is exhausted.
+///
+/// # Example
+///
+/// ```no_run
+/// use someip_rs::connection::{retry, RetryPolicy};
+/// use someip_rs::transport::UdpClient;
+/// use someip_rs::{SomeIpMessage, ServiceId, MethodId};
+/// use std::time::Duration;
+///
+/// let mut client = UdpClient::new().unwrap();
+/// client.connect("127.0.0.1:30490").unwrap();
+/// client.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+///
+/// let policy = RetryPolicy::fixed(3, Duration::from_millis(100));
+/// let response = retry(&policy, |_| {
+///     let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001)).build();
+///     client.call(request)
+/// })
+/// .unwrap();
+/// ```
+pub fn retry<T>(policy: &RetryPolicy, mut op: impl FnMut(u32) -> Result<T>) -> Result<T> {
+    let mut attempt = 0;
+    loop {
+        match op(attempt) {
+            Ok(value) => return Ok(value),
+            Err(e) => {
+                if !policy.is_retryable(&e) || !policy.should_retry(attempt) {
+                    return Err(e);
+                }
+                thread::sleep(policy.delay_for_attempt(attempt));
+                attempt += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::SomeIpError;
+    use std::time::Duration;
+
+    #[test]
+    fn test_retry_succeeds_after_failures() {
+        let policy = RetryPolicy::fixed(3, Duration::from_millis(1));
+        let mut calls = 0;
+
+        let result = retry(&policy, |attempt| {
+            calls += 1;
+            if attempt < 2 {
+                Err(SomeIpError::Timeout)
+            } else {
+                Ok(attempt)
+            }
+        });
+
+        assert_eq!(result.unwrap(), 2);
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn test_retry_exhausted() {
+        let policy = RetryPolicy::fixed(3, Duration::from_millis(1));
+        let mut calls = 0;
+
+        let result: Result<()> = retry(&policy, |_| {
+            calls += 1;
+            Err(SomeIpError::Timeout)
+        });
+
+        assert!(matches!(result, Err(SomeIpError::Timeout)));
+        assert_eq!(calls, 4);
+    }
+
+    #[test]
+    fn test_retry_not_retryable() {
+        let policy = RetryPolicy::fixed(3, Duration::from_millis(1));
+        let mut calls = 0;
+
+        let result: Result<()> = retry(&policy, |_| {
+            calls += 1;
+            Err(SomeIpError::UnknownMessageType(0xFF))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+}
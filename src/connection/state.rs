@@ -1,11 +1,12 @@
 //! Connection state management.
 
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 /// Connection state.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum ConnectionState {
     /// Not connected.
+    #[default]
     Disconnected,
     /// Currently attempting to connect.
     Connecting,
@@ -34,14 +35,8 @@ impl ConnectionState {
     }
 }
 
-impl Default for ConnectionState {
-    fn default() -> Self {
-        ConnectionState::Disconnected
-    }
-}
-
 /// Connection statistics.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ConnectionStats {
     /// Number of successful connections.
     pub connect_count: u64,
@@ -65,23 +60,6 @@ pub struct ConnectionStats {
     pub last_error: Option<Instant>,
 }
 
-impl Default for ConnectionStats {
-    fn default() -> Self {
-        Self {
-            connect_count: 0,
-            failure_count: 0,
-            reconnect_count: 0,
-            messages_sent: 0,
-            messages_received: 0,
-            bytes_sent: 0,
-            bytes_received: 0,
-            last_connected: None,
-            last_disconnected: None,
-            last_error: None,
-        }
-    }
-}
-
 impl ConnectionStats {
     /// Record a successful connection.
     pub fn record_connect(&mut self) {
@@ -123,6 +101,59 @@ impl ConnectionStats {
     }
 }
 
+/// Number of latency histogram buckets (1 ms to 1024 ms, plus overflow).
+const LATENCY_BUCKETS: usize = 12;
+
+/// Request latency histogram with power-of-two millisecond buckets.
+///
+/// Each bucket is an inclusive upper bound and a count. The last bucket
+/// (`Duration::MAX`) collects everything slower than 1024 ms.
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    buckets: Vec<(Duration, u64)>,
+}
+
+impl LatencyHistogram {
+    /// Create an empty histogram.
+    pub fn new() -> Self {
+        let buckets = (0..LATENCY_BUCKETS as u32 - 1)
+            .map(|i| (Duration::from_millis(1 << i), 0))
+            .chain(std::iter::once((Duration::MAX, 0)))
+            .collect();
+        Self { buckets }
+    }
+
+    /// Record a latency sample.
+    pub fn record(&mut self, latency: Duration) {
+        if let Some(bucket) = self.buckets.iter_mut().find(|(bound, _)| latency <= *bound) {
+            bucket.1 += 1;
+        }
+    }
+
+    /// Get the buckets as (upper bound, count) pairs.
+    pub fn buckets(&self) -> &[(Duration, u64)] {
+        &self.buckets
+    }
+
+    /// Get the total number of samples.
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().map(|(_, count)| count).sum()
+    }
+
+    /// Reset all bucket counts to zero.
+    pub fn reset(&mut self) {
+        for bucket in &mut self.buckets {
+            bucket.1 = 0;
+        }
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -155,4 +186,24 @@ mod tests {
         assert_eq!(stats.failure_count, 1);
         assert!(stats.last_error.is_some());
     }
+
+    #[test]
+    fn test_latency_histogram() {
+        let mut histogram = LatencyHistogram::new();
+        assert_eq!(histogram.buckets().len(), LATENCY_BUCKETS);
+
+        histogram.record(Duration::from_micros(500));
+        histogram.record(Duration::from_millis(1));
+        histogram.record(Duration::from_millis(3));
+        histogram.record(Duration::from_secs(5));
+
+        let buckets = histogram.buckets();
+        assert_eq!(buckets[0], (Duration::from_millis(1), 2));
+        assert_eq!(buckets[2], (Duration::from_millis(4), 1));
+        assert_eq!(buckets[LATENCY_BUCKETS - 1], (Duration::MAX, 1));
+        assert_eq!(histogram.count(), 4);
+
+        histogram.reset();
+        assert_eq!(histogram.count(), 0);
+    }
 }
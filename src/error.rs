@@ -1,5 +1,7 @@
 //! Error types for SOME/IP operations.
 
+use crate::header::ServiceId;
+use crate::sd::{EventgroupId, InstanceId};
 use crate::types::ReturnCode;
 use std::io;
 use thiserror::Error;
@@ -38,6 +40,30 @@ pub enum SomeIpError {
         actual_length: usize,
     },
 
+    /// Malformed service discovery data.
+    #[error("SD parse error at offset {offset}: {reason}")]
+    SdParse { reason: String, offset: usize },
+
+    /// Received datagram was larger than the receive buffer.
+    #[error("Datagram truncated: larger than the {buffer_size}-byte receive buffer")]
+    DatagramTruncated { buffer_size: usize },
+
+    /// A corrupted TCP stream was resynchronized by skipping bytes.
+    #[error("Stream resynchronized after skipping {skipped} bytes")]
+    Resynchronized { skipped: usize },
+
+    /// TP segment offset beyond the largest representable payload.
+    #[error("TP segment at byte offset {byte_offset} exceeds maximum payload of {max} bytes")]
+    TpOffsetOutOfRange { byte_offset: usize, max: usize },
+
+    /// Too many concurrent TP reassemblies.
+    #[error("Too many concurrent reassemblies: limit is {max}")]
+    TooManyReassemblies { max: usize },
+
+    /// TP reassembly buffers would exceed the global byte limit.
+    #[error("TP reassembly memory limit of {limit} bytes exceeded")]
+    TpMemoryLimit { limit: usize },
+
     /// Payload too large.
     #[error("Payload too large: {size} bytes exceeds maximum of {max} bytes")]
     PayloadTooLarge { size: usize, max: usize },
@@ -46,6 +72,20 @@ pub enum SomeIpError {
     #[error("Protocol error: {0:?}")]
     ProtocolError(ReturnCode),
 
+    /// Service answered with an error response.
+    #[error("Service error: {return_code:?}")]
+    ServiceError { return_code: ReturnCode },
+
+    /// Eventgroup subscription was rejected by the server.
+    #[error(
+        "Subscription to eventgroup {eventgroup_id} of service {service_id}.{instance_id} rejected"
+    )]
+    SubscriptionRejected {
+        service_id: ServiceId,
+        instance_id: InstanceId,
+        eventgroup_id: EventgroupId,
+    },
+
     /// Connection closed unexpectedly.
     #[error("Connection closed")]
     ConnectionClosed,
@@ -54,6 +94,10 @@ pub enum SomeIpError {
     #[error("Operation timed out")]
     Timeout,
 
+    /// Operation was cancelled before it completed.
+    #[error("Operation cancelled")]
+    Cancelled,
+
     /// No response received for request.
     #[error("No response received for request (client={client_id:04X}, session={session_id:04X})")]
     NoResponse { client_id: u16, session_id: u16 },
@@ -68,6 +112,25 @@ impl SomeIpError {
         Self::InvalidHeader(msg.into())
     }
 
+    /// Create a new SD parse error at a byte offset.
+    pub fn sd_parse(reason: impl Into<String>, offset: usize) -> Self {
+        Self::SdParse {
+            reason: reason.into(),
+            offset,
+        }
+    }
+
+    /// Shift the offset of an SD parse error by the position of its input.
+    pub(crate) fn shift_sd_offset(self, base: usize) -> Self {
+        match self {
+            Self::SdParse { reason, offset } => Self::SdParse {
+                reason,
+                offset: base + offset,
+            },
+            other => other,
+        }
+    }
+
     /// Create a new I/O error.
     pub fn io(err: io::Error) -> Self {
         Self::Io(err)
@@ -84,6 +147,25 @@ impl SomeIpError {
     }
 }
 
+impl From<SomeIpError> for io::Error {
+    fn from(err: SomeIpError) -> Self {
+        if let SomeIpError::Io(e) = err {
+            return e;
+        }
+
+        let kind = match &err {
+            SomeIpError::Timeout | SomeIpError::NoResponse { .. } => io::ErrorKind::TimedOut,
+            SomeIpError::ConnectionClosed => io::ErrorKind::ConnectionAborted,
+            SomeIpError::Cancelled => io::ErrorKind::Interrupted,
+            SomeIpError::ProtocolError(_)
+            | SomeIpError::ServiceError { .. }
+            | SomeIpError::SubscriptionRejected { .. } => io::ErrorKind::Other,
+            _ => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -103,6 +185,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_into_io_error() {
+        let err = SomeIpError::Io(io::Error::new(io::ErrorKind::ConnectionRefused, "test"));
+        let io_err: io::Error = err.into();
+        assert_eq!(io_err.kind(), io::ErrorKind::ConnectionRefused);
+        assert_eq!(io_err.to_string(), "test");
+
+        let err = SomeIpError::MessageTooShort {
+            expected: 16,
+            actual: 8,
+        };
+        let io_err: io::Error = err.into();
+        assert_eq!(io_err.kind(), io::ErrorKind::InvalidData);
+        assert!(matches!(
+            io_err.get_ref().and_then(|e| e.downcast_ref::<SomeIpError>()),
+            Some(SomeIpError::MessageTooShort { .. })
+        ));
+
+        let io_err: io::Error = SomeIpError::Timeout.into();
+        assert_eq!(io_err.kind(), io::ErrorKind::TimedOut);
+    }
+
     #[test]
     fn test_from_io_error() {
         let io_err = io::Error::new(io::ErrorKind::ConnectionRefused, "test");
@@ -179,6 +179,28 @@ impl SomeIpHeader {
         self.length = payload_len + 8;
     }
 
+    /// Set the length field from a total wire size (header plus payload).
+    ///
+    /// The payload is not touched, so this can produce deliberately
+    /// inconsistent messages for negative tests.
+    pub fn set_total_size(&mut self, total: usize) -> Result<()> {
+        if total < HEADER_SIZE {
+            return Err(SomeIpError::MessageTooShort {
+                expected: HEADER_SIZE,
+                actual: total,
+            });
+        }
+        self.length = u32::try_from(total - 8).map_err(|_| {
+            SomeIpError::invalid_header(format!("Total size {} exceeds length field", total))
+        })?;
+        Ok(())
+    }
+
+    /// Get the total wire size declared by the length field.
+    pub fn declared_total_size(&self) -> usize {
+        HEADER_SIZE + self.payload_length() as usize
+    }
+
     /// Parse a header from bytes.
     pub fn from_bytes(data: &[u8]) -> Result<Self> {
         if data.len() < HEADER_SIZE {
@@ -244,6 +266,16 @@ impl SomeIpHeader {
     pub fn request_id(&self) -> u32 {
         ((self.client_id.0 as u32) << 16) | (self.session_id.0 as u32)
     }
+
+    /// Format the request ID as `client:session` in hex (e.g. `0100:0001`).
+    pub fn request_id_string(&self) -> String {
+        format!("{:04x}:{:04x}", self.client_id.0, self.session_id.0)
+    }
+
+    /// Format the message ID as `service.method` in hex (e.g. `1234.0001`).
+    pub fn message_id_string(&self) -> String {
+        format!("{:04x}.{:04x}", self.service_id.0, self.method_id.0)
+    }
 }
 
 impl Default for SomeIpHeader {
@@ -256,6 +288,16 @@ impl Default for SomeIpHeader {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_id_strings() {
+        let mut header = SomeIpHeader::request(ServiceId(0x12AB), MethodId(0x8001));
+        header.client_id = ClientId(0x0100);
+        header.session_id = SessionId(0x0001);
+
+        assert_eq!(header.request_id_string(), "0100:0001");
+        assert_eq!(header.message_id_string(), "12ab.8001");
+    }
+
     #[test]
     fn test_header_roundtrip() {
         let header = SomeIpHeader {
@@ -333,6 +375,28 @@ mod tests {
         assert_eq!(response.message_type, MessageType::Response);
     }
 
+    #[test]
+    fn test_set_total_size() {
+        let mut header = SomeIpHeader::request(ServiceId(0x1234), MethodId(0x0001));
+        assert_eq!(header.declared_total_size(), HEADER_SIZE);
+
+        header.set_total_size(40).unwrap();
+        assert_eq!(header.length, 32);
+        assert_eq!(header.payload_length(), 24);
+        assert_eq!(header.declared_total_size(), 40);
+    }
+
+    #[test]
+    fn test_set_total_size_too_small() {
+        let mut header = SomeIpHeader::request(ServiceId(0x1234), MethodId(0x0001));
+        let result = header.set_total_size(HEADER_SIZE - 1);
+        assert!(matches!(
+            result,
+            Err(SomeIpError::MessageTooShort { expected: HEADER_SIZE, actual: 15 })
+        ));
+        assert_eq!(header.length, 8);
+    }
+
     #[test]
     fn test_parse_too_short() {
         let data = [0u8; 10];
@@ -342,8 +406,10 @@ mod tests {
 
     #[test]
     fn test_parse_wrong_protocol_version() {
-        let mut header = SomeIpHeader::default();
-        header.protocol_version = 0x02;
+        let header = SomeIpHeader {
+            protocol_version: 0x02,
+            ..Default::default()
+        };
         let mut bytes = header.to_bytes();
         bytes[12] = 0x02; // Wrong protocol version
 
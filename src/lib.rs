@@ -50,6 +50,7 @@
 //! +--------+--------+--------+--------+
 //! ```
 
+pub mod clock;
 pub mod codec;
 pub mod connection;
 pub mod error;
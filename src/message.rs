@@ -4,6 +4,7 @@ use bytes::Bytes;
 
 use crate::error::{Result, SomeIpError};
 use crate::header::{ClientId, MethodId, ServiceId, SessionId, SomeIpHeader, HEADER_SIZE};
+use crate::sd::{SdMessage, SD_METHOD_ID, SD_SERVICE_ID};
 use crate::types::{MessageType, ReturnCode};
 
 /// Maximum payload size (default: 1400 bytes for UDP compatibility).
@@ -26,6 +27,17 @@ impl SomeIpMessage {
         Self { header, payload }
     }
 
+    /// Create a message from a header and payload, keeping the header length verbatim.
+    ///
+    /// Unlike [`new`](Self::new), the length field is not recomputed, so the
+    /// caller is responsible for keeping it consistent with the payload. This
+    /// is mainly useful for crafting malformed messages in negative tests;
+    /// note that [`to_bytes`](Self::to_bytes) panics on such messages in debug
+    /// builds, so serialize the header and payload separately.
+    pub fn from_parts(header: SomeIpHeader, payload: Bytes) -> Self {
+        Self { header, payload }
+    }
+
     /// Create a new message with an empty payload.
     pub fn with_header(header: SomeIpHeader) -> Self {
         Self::new(header, Bytes::new())
@@ -46,6 +58,25 @@ impl SomeIpMessage {
         MessageBuilder::new(service_id, method_id, MessageType::Notification)
     }
 
+    /// Generate one message per message type and return code combination.
+    ///
+    /// Useful for seeding fuzzers and conformance tests.
+    pub fn generate_all_combinations(
+        service_id: ServiceId,
+        method_id: MethodId,
+    ) -> Vec<SomeIpMessage> {
+        MessageType::ALL
+            .iter()
+            .flat_map(|&message_type| {
+                ReturnCode::ALL.iter().map(move |&return_code| {
+                    MessageBuilder::new(service_id, method_id, message_type)
+                        .return_code(return_code)
+                        .build()
+                })
+            })
+            .collect()
+    }
+
     /// Create a response to this message.
     pub fn create_response(&self) -> MessageBuilder {
         let mut builder = MessageBuilder::new(
@@ -73,6 +104,19 @@ impl SomeIpMessage {
         builder
     }
 
+    /// Create an error response echoing up to `max_echo` bytes of this message's payload.
+    ///
+    /// Useful for diagnostics, so clients can see which request was rejected.
+    pub fn create_error_response_echoing(
+        &self,
+        return_code: ReturnCode,
+        max_echo: usize,
+    ) -> MessageBuilder {
+        let echo_len = self.payload.len().min(max_echo);
+        self.create_error_response(return_code)
+            .payload(self.payload.slice(..echo_len))
+    }
+
     /// Parse a message from bytes.
     pub fn from_bytes(data: &[u8]) -> Result<Self> {
         if data.len() < HEADER_SIZE {
@@ -97,7 +141,22 @@ impl SomeIpMessage {
         Ok(Self { header, payload })
     }
 
+    /// Check that the header length field matches the payload.
+    pub fn check_consistency(&self) -> Result<()> {
+        let actual_length = 8 + self.payload.len();
+        if self.header.length as usize != actual_length {
+            return Err(SomeIpError::LengthMismatch {
+                header_length: self.header.length,
+                actual_length,
+            });
+        }
+        Ok(())
+    }
+
     /// Serialize the message to bytes.
+    ///
+    /// The header is written as-is, even if its length field does not match
+    /// the payload. Use [`try_to_bytes`](Self::try_to_bytes) to check it.
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut buf = Vec::with_capacity(HEADER_SIZE + self.payload.len());
         buf.extend_from_slice(&self.header.to_bytes());
@@ -105,6 +164,12 @@ impl SomeIpMessage {
         buf
     }
 
+    /// Serialize the message to bytes after checking header consistency.
+    pub fn try_to_bytes(&self) -> Result<Vec<u8>> {
+        self.check_consistency()?;
+        Ok(self.to_bytes())
+    }
+
     /// Get the total message size (header + payload).
     pub fn total_size(&self) -> usize {
         HEADER_SIZE + self.payload.len()
@@ -123,6 +188,52 @@ impl SomeIpMessage {
         self.header.message_type.is_response()
     }
 
+    /// Check if this message is a response (or error) to the given request.
+    ///
+    /// Matches service, method, client and session IDs.
+    pub fn is_response_to(&self, request: &SomeIpMessage) -> bool {
+        self.is_response()
+            && self.header.service_id == request.header.service_id
+            && self.header.method_id == request.header.method_id
+            && self.header.client_id == request.header.client_id
+            && self.header.session_id == request.header.session_id
+    }
+
+    /// Get the return code of an error response.
+    ///
+    /// Returns `Some` for `Error` messages and for responses whose return code
+    /// is not `Ok`.
+    pub fn error_code(&self) -> Option<ReturnCode> {
+        let is_error = matches!(self.header.message_type, MessageType::Error | MessageType::TpError)
+            || (self.is_response() && self.header.return_code.is_error());
+        is_error.then_some(self.header.return_code)
+    }
+
+    /// Turn an error response into [`SomeIpError::ServiceError`].
+    ///
+    /// Allows `client.call(request)?.into_result()?`.
+    pub fn into_result(self) -> Result<Self> {
+        match self.error_code() {
+            Some(return_code) => Err(SomeIpError::ServiceError { return_code }),
+            None => Ok(self),
+        }
+    }
+
+    /// Compare with another message, ignoring the session ID.
+    pub fn equals_ignoring_session(&self, other: &SomeIpMessage) -> bool {
+        let mut header = self.header.clone();
+        header.session_id = other.header.session_id;
+        header == other.header && self.payload == other.payload
+    }
+
+    /// Compare with another message, ignoring the client and session IDs.
+    pub fn equals_ignoring_request_id(&self, other: &SomeIpMessage) -> bool {
+        let mut header = self.header.clone();
+        header.client_id = other.header.client_id;
+        header.session_id = other.header.session_id;
+        header == other.header && self.payload == other.payload
+    }
+
     /// Check if this message expects a response.
     pub fn expects_response(&self) -> bool {
         self.header.message_type.expects_response()
@@ -148,6 +259,11 @@ impl SomeIpMessage {
         self.header.session_id
     }
 
+    /// Check if session handling is active (session ID is non-zero).
+    pub fn session_handling_active(&self) -> bool {
+        self.header.session_id.0 != 0
+    }
+
     /// Get the return code.
     pub fn return_code(&self) -> ReturnCode {
         self.header.return_code
@@ -157,6 +273,68 @@ impl SomeIpMessage {
     pub fn is_ok(&self) -> bool {
         self.header.return_code.is_ok()
     }
+
+    /// Check if this is a SOME/IP-SD message (service 0xFFFF, method 0x8100).
+    pub fn is_sd(&self) -> bool {
+        self.header.service_id == ServiceId(SD_SERVICE_ID)
+            && self.header.method_id == MethodId(SD_METHOD_ID)
+    }
+
+    /// Parse this message as a SOME/IP-SD message.
+    pub fn as_sd(&self) -> Result<SdMessage> {
+        SdMessage::from_someip_message(self)
+    }
+
+    /// Get the hop marker (the ID of the last forwarding gateway), if any.
+    ///
+    /// See [`mark_forwarded`](Self::mark_forwarded) for the convention.
+    pub fn hop_marker(&self) -> Option<u8> {
+        match (self.header.client_id.0 >> 8) as u8 {
+            0 => None,
+            id => Some(id),
+        }
+    }
+
+    /// Check if this message was last forwarded by the given gateway.
+    pub fn is_forwarded_by(&self, gateway_id: u8) -> bool {
+        self.hop_marker() == Some(gateway_id)
+    }
+
+    /// Record that `gateway_id` forwarded this message.
+    ///
+    /// SOME/IP has no header field for routing, so gateways use the high byte
+    /// of the client ID as a hop marker, like an AUTOSAR client ID prefix:
+    /// `0x00` means the message was not forwarded, any other value is the ID of
+    /// the last gateway that forwarded it. Gateway IDs must therefore be
+    /// non-zero and unique within the network, and clients behind a gateway
+    /// should only use client IDs below `0x0100`.
+    ///
+    /// Returns `false` without modifying the message if this gateway already
+    /// forwarded it, meaning it looped back and should be dropped.
+    ///
+    /// The marker holds a single gateway, so only one-hop loops are detected:
+    /// a message forwarded A→B→A carries B's marker when it reaches A again.
+    ///
+    /// Responses copy the marked client ID, so the gateway must call
+    /// [`clear_hop_marker`](Self::clear_hop_marker) before passing them back
+    /// to the client, restoring the client ID it originally sent.
+    pub fn mark_forwarded(&mut self, gateway_id: u8) -> bool {
+        if self.is_forwarded_by(gateway_id) {
+            return false;
+        }
+        let client = self.header.client_id.0 & 0x00FF;
+        self.header.client_id = ClientId(((gateway_id as u16) << 8) | client);
+        true
+    }
+
+    /// Remove the hop marker, restoring the original client ID.
+    ///
+    /// Returns the removed gateway ID, if any.
+    pub fn clear_hop_marker(&mut self) -> Option<u8> {
+        let marker = self.hop_marker();
+        self.header.client_id = ClientId(self.header.client_id.0 & 0x00FF);
+        marker
+    }
 }
 
 /// Builder for constructing SOME/IP messages.
@@ -166,6 +344,7 @@ pub struct MessageBuilder {
     method_id: MethodId,
     client_id: ClientId,
     session_id: SessionId,
+    protocol_version: u8,
     interface_version: u8,
     message_type: MessageType,
     return_code: ReturnCode,
@@ -180,6 +359,7 @@ impl MessageBuilder {
             method_id,
             client_id: ClientId::default(),
             session_id: SessionId::default(),
+            protocol_version: crate::types::PROTOCOL_VERSION,
             interface_version: 1,
             message_type,
             return_code: ReturnCode::Ok,
@@ -199,12 +379,29 @@ impl MessageBuilder {
         self
     }
 
+    /// Set the protocol version, for testing against non-standard stacks.
+    ///
+    /// Note that [`SomeIpMessage::from_bytes`] rejects versions other than
+    /// [`PROTOCOL_VERSION`](crate::types::PROTOCOL_VERSION).
+    pub fn protocol_version(mut self, version: u8) -> Self {
+        self.protocol_version = version;
+        self
+    }
+
     /// Set the interface version.
     pub fn interface_version(mut self, version: u8) -> Self {
         self.interface_version = version;
         self
     }
 
+    /// Set the hop marker in the client ID high byte.
+    ///
+    /// See [`SomeIpMessage::mark_forwarded`] for the convention.
+    pub fn hop_marker(mut self, gateway_id: u8) -> Self {
+        self.client_id = ClientId(((gateway_id as u16) << 8) | (self.client_id.0 & 0x00FF));
+        self
+    }
+
     /// Set the return code.
     pub fn return_code(mut self, code: ReturnCode) -> Self {
         self.return_code = code;
@@ -231,7 +428,7 @@ impl MessageBuilder {
             length: 8 + self.payload.len() as u32,
             client_id: self.client_id,
             session_id: self.session_id,
-            protocol_version: crate::types::PROTOCOL_VERSION,
+            protocol_version: self.protocol_version,
             interface_version: self.interface_version,
             message_type: self.message_type,
             return_code: self.return_code,
@@ -242,6 +439,63 @@ impl MessageBuilder {
             payload: self.payload,
         }
     }
+
+    /// Build an `Error` message with the given return code.
+    pub fn build_error(mut self, code: ReturnCode) -> SomeIpMessage {
+        self.message_type = MessageType::Error;
+        self.return_code = code;
+        self.build()
+    }
+
+    /// Build an `Error` message with [`ReturnCode::UnknownService`].
+    pub fn error_unknown_service(self) -> SomeIpMessage {
+        self.build_error(ReturnCode::UnknownService)
+    }
+
+    /// Build an `Error` message with [`ReturnCode::UnknownMethod`].
+    pub fn error_unknown_method(self) -> SomeIpMessage {
+        self.build_error(ReturnCode::UnknownMethod)
+    }
+
+    /// Build an `Error` message with [`ReturnCode::NotReady`].
+    pub fn error_not_ready(self) -> SomeIpMessage {
+        self.build_error(ReturnCode::NotReady)
+    }
+
+    /// Build an `Error` message with [`ReturnCode::MalformedMessage`].
+    pub fn error_malformed_message(self) -> SomeIpMessage {
+        self.build_error(ReturnCode::MalformedMessage)
+    }
+
+    /// Build the message after checking header consistency.
+    ///
+    /// Rejects TP message types (use the `tp` module for segmentation),
+    /// requests, responses and errors using an event method ID, and non-`Ok`
+    /// return codes on anything other than an `Error` message.
+    pub fn build_validated(self) -> Result<SomeIpMessage> {
+        if self.message_type.is_tp() {
+            return Err(SomeIpError::invalid_header(format!(
+                "TP message type {:?} not allowed for unsegmented messages",
+                self.message_type
+            )));
+        }
+
+        if self.method_id.is_event() && self.message_type != MessageType::Notification {
+            return Err(SomeIpError::invalid_header(format!(
+                "Event method ID {} used with message type {:?}",
+                self.method_id, self.message_type
+            )));
+        }
+
+        if self.return_code != ReturnCode::Ok && self.message_type != MessageType::Error {
+            return Err(SomeIpError::invalid_header(format!(
+                "Return code {:?} requires message type Error, got {:?}",
+                self.return_code, self.message_type
+            )));
+        }
+
+        Ok(self.build())
+    }
 }
 
 #[cfg(test)]
@@ -265,6 +519,18 @@ mod tests {
         assert_eq!(msg.header.length, 8 + 5); // 8 + payload length
     }
 
+    #[test]
+    fn test_message_builder_protocol_version() {
+        let msg = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .protocol_version(0x02)
+            .build();
+        assert_eq!(msg.header.protocol_version, 0x02);
+        assert_eq!(msg.to_bytes()[12], 0x02);
+
+        let msg = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001)).build();
+        assert_eq!(msg.to_bytes()[12], crate::types::PROTOCOL_VERSION);
+    }
+
     #[test]
     fn test_message_roundtrip() {
         let original = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x5678))
@@ -313,6 +579,242 @@ mod tests {
         assert_eq!(error.header.return_code, ReturnCode::UnknownMethod);
     }
 
+    #[test]
+    fn test_builder_error_finishers() {
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .client_id(ClientId(0x0100))
+            .session_id(SessionId(0x0042))
+            .build();
+
+        let cases = [
+            (request.create_response().error_unknown_service(), ReturnCode::UnknownService),
+            (request.create_response().error_unknown_method(), ReturnCode::UnknownMethod),
+            (request.create_response().error_not_ready(), ReturnCode::NotReady),
+            (request.create_response().error_malformed_message(), ReturnCode::MalformedMessage),
+        ];
+        for (error, code) in cases {
+            assert_eq!(error.header.message_type, MessageType::Error);
+            assert_eq!(error.header.return_code, code);
+            assert!(error.is_response_to(&request));
+        }
+    }
+
+    #[test]
+    fn test_create_error_response_echoing() {
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .payload(b"bad request payload".as_slice())
+            .build();
+
+        let error = request
+            .create_error_response_echoing(ReturnCode::MalformedMessage, 3)
+            .build();
+        assert_eq!(error.header.message_type, MessageType::Error);
+        assert_eq!(error.header.return_code, ReturnCode::MalformedMessage);
+        assert_eq!(error.payload.as_ref(), b"bad");
+        assert_eq!(error.header.payload_length(), 3);
+
+        // Shorter payloads are echoed in full
+        let error = request
+            .create_error_response_echoing(ReturnCode::MalformedMessage, 1000)
+            .build();
+        assert_eq!(error.payload, request.payload);
+    }
+
+    #[test]
+    fn test_is_sd() {
+        let sd = SdMessage::find_service(
+            ServiceId(0x1234),
+            crate::sd::InstanceId::ANY,
+            0xFF,
+            0xFFFFFFFF,
+        )
+        .to_someip_message();
+        assert!(sd.is_sd());
+        assert_eq!(sd.as_sd().unwrap().entries.len(), 1);
+
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001)).build();
+        assert!(!request.is_sd());
+        assert!(request.as_sd().is_err());
+    }
+
+    #[test]
+    fn test_from_parts_preserves_length() {
+        let mut header = SomeIpHeader::request(ServiceId(0x1234), MethodId(0x0001));
+        header.length = 0x100;
+
+        let msg = SomeIpMessage::from_parts(header, Bytes::from_static(b"abc"));
+        assert_eq!(msg.header.length, 0x100);
+
+        let bytes = msg.to_bytes();
+        assert_eq!(&bytes[4..8], &0x100u32.to_be_bytes());
+        assert_eq!(&bytes[HEADER_SIZE..], b"abc");
+    }
+
+    #[test]
+    fn test_check_consistency() {
+        let msg = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .payload(b"abc".as_slice())
+            .build();
+        assert!(msg.check_consistency().is_ok());
+
+        let mut header = msg.header.clone();
+        header.length = 8;
+        let inconsistent = SomeIpMessage::from_parts(header, msg.payload.clone());
+        assert!(matches!(
+            inconsistent.check_consistency(),
+            Err(SomeIpError::LengthMismatch { header_length: 8, actual_length: 11 })
+        ));
+        assert!(inconsistent.try_to_bytes().is_err());
+        assert_eq!(msg.try_to_bytes().unwrap(), msg.to_bytes());
+
+        // Messages received with a bogus length field still serialize
+        let mut header = msg.header.clone();
+        header.length = 0;
+        let received = SomeIpMessage::from_parts(header, Bytes::new());
+        assert_eq!(&received.to_bytes()[4..8], &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_build_validated() {
+        assert!(SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .build_validated()
+            .is_ok());
+        assert!(SomeIpMessage::notification(ServiceId(0x1234), MethodId::event(0x0001))
+            .build_validated()
+            .is_ok());
+
+        // Response using an event method ID
+        let result =
+            MessageBuilder::new(ServiceId(0x1234), MethodId::event(0x0001), MessageType::Response)
+                .build_validated();
+        assert!(matches!(result, Err(SomeIpError::InvalidHeader(_))));
+
+        // Error return code on a non-error message
+        let result = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .return_code(ReturnCode::NotOk)
+            .build_validated();
+        assert!(result.is_err());
+
+        // TP message type in a plain builder
+        let result =
+            MessageBuilder::new(ServiceId(0x1234), MethodId(0x0001), MessageType::TpRequest)
+                .build_validated();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_forwarding_loop_detection() {
+        let mut msg = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .client_id(ClientId(0x0042))
+            .build();
+        assert_eq!(msg.hop_marker(), None);
+
+        assert!(msg.mark_forwarded(0x01));
+        assert_eq!(msg.header.client_id, ClientId(0x0142));
+        assert!(msg.is_forwarded_by(0x01));
+
+        // Forwarded back to the same gateway: loop
+        assert!(!msg.mark_forwarded(0x01));
+        assert_eq!(msg.header.client_id, ClientId(0x0142));
+
+        // Another gateway may forward it
+        assert!(msg.mark_forwarded(0x02));
+        assert_eq!(msg.hop_marker(), Some(0x02));
+
+        // The response travels back and the gateway restores the client ID
+        let mut response = msg.create_response().build();
+        assert_eq!(response.clear_hop_marker(), Some(0x02));
+        assert_eq!(response.header.client_id, ClientId(0x0042));
+        assert_eq!(response.clear_hop_marker(), None);
+
+        let built = SomeIpMessage::notification(ServiceId(0x1234), MethodId::event(0x0001))
+            .client_id(ClientId(0x0042))
+            .hop_marker(0x07)
+            .build();
+        assert!(built.is_forwarded_by(0x07));
+        assert_eq!(built.header.client_id, ClientId(0x0742));
+    }
+
+    #[test]
+    fn test_is_response_to() {
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .client_id(ClientId(0x0001))
+            .session_id(SessionId(0x0010))
+            .build();
+
+        let response = request.create_response().build();
+        assert!(response.is_response_to(&request));
+
+        let error = request.create_error_response(ReturnCode::NotOk).build();
+        assert!(error.is_response_to(&request));
+
+        // A request is never a response to itself
+        assert!(!request.is_response_to(&request));
+
+        let other = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .client_id(ClientId(0x0001))
+            .session_id(SessionId(0x0011))
+            .build();
+        assert!(!response.is_response_to(&other));
+    }
+
+    #[test]
+    fn test_into_result() {
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001)).build();
+
+        let ok = request.create_response().payload(b"ok".as_slice()).build();
+        assert_eq!(ok.error_code(), None);
+        assert_eq!(ok.into_result().unwrap().payload.as_ref(), b"ok");
+
+        let error = request.create_error_response(ReturnCode::NotReady).build();
+        assert_eq!(error.error_code(), Some(ReturnCode::NotReady));
+        assert!(matches!(
+            error.into_result(),
+            Err(SomeIpError::ServiceError { return_code: ReturnCode::NotReady })
+        ));
+
+        // Responses with a non-OK code count as errors too
+        let response = request.create_response().return_code(ReturnCode::NotOk).build();
+        assert_eq!(response.error_code(), Some(ReturnCode::NotOk));
+        assert_eq!(request.error_code(), None);
+    }
+
+    #[test]
+    fn test_generate_all_combinations() {
+        let messages = SomeIpMessage::generate_all_combinations(ServiceId(0x1234), MethodId(0x0001));
+        assert_eq!(messages.len(), MessageType::ALL.len() * ReturnCode::ALL.len());
+
+        for &message_type in MessageType::ALL {
+            for &return_code in ReturnCode::ALL {
+                assert!(messages.iter().any(|m| m.header.message_type == message_type
+                    && m.header.return_code == return_code));
+            }
+        }
+        assert!(messages.iter().all(|m| m.header.service_id == ServiceId(0x1234)));
+    }
+
+    #[test]
+    fn test_equals_ignoring_session() {
+        let template = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .client_id(ClientId(0x0010))
+            .session_id(SessionId(0x0001))
+            .payload(b"data".as_slice())
+            .build();
+
+        let mut replay = template.clone();
+        replay.header.session_id = SessionId(0x0042);
+        assert_ne!(template, replay);
+        assert!(template.equals_ignoring_session(&replay));
+        assert!(template.equals_ignoring_request_id(&replay));
+
+        replay.header.client_id = ClientId(0x0020);
+        assert!(!template.equals_ignoring_session(&replay));
+        assert!(template.equals_ignoring_request_id(&replay));
+
+        let other = template.create_response().payload(b"data".as_slice()).build();
+        assert!(!template.equals_ignoring_request_id(&other));
+    }
+
     #[test]
     fn test_total_size() {
         let msg = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
@@ -1,18 +1,25 @@
 //! SOME/IP-SD client for service discovery.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::io;
-use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, UdpSocket};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use socket2::{Domain, Protocol, SockRef, Socket, Type};
+
+use crate::clock::{self, Clock};
+use crate::connection::RetryPolicy;
 use crate::error::{Result, SomeIpError};
 use crate::header::ServiceId;
+use crate::transport::{TcpClient, UdpClient};
 
 use super::entry::SdEntry;
 use super::message::SdMessage;
-use super::option::Endpoint;
+use super::option::{ConfigurationOption, Endpoint, LoadBalancingOption, SdOption};
+use super::rate_limit::TokenBucket;
 use super::types::{
-    EntryType, EventgroupId, InstanceId, SD_DEFAULT_PORT, SD_MULTICAST_ADDR,
+    EntryType, EventgroupId, InstanceId, TransportProtocol, SD_DEFAULT_PORT, SD_MULTICAST_ADDR,
 };
 
 /// Information about a discovered service.
@@ -32,19 +39,85 @@ pub struct ServiceInfo {
     pub expires_at: Instant,
     /// Source address of the service offer.
     pub source_addr: SocketAddr,
+    /// Load balancing priority and weight attached to the offer, if any.
+    pub load_balancing: Option<LoadBalancingOption>,
 }
 
 impl ServiceInfo {
-    /// Check if the service offer has expired.
-    pub fn is_expired(&self) -> bool {
-        Instant::now() >= self.expires_at
+    /// Check if the service offer has expired at the given instant.
+    ///
+    /// `expires_at` comes from the [`SdClient`]'s clock, so pass
+    /// [`SdClient::now`] rather than [`Instant::now`].
+    pub fn is_expired_at(&self, now: Instant) -> bool {
+        now >= self.expires_at
+    }
+
+    /// Get remaining TTL in seconds at the given instant.
+    pub fn remaining_ttl_at(&self, now: Instant) -> u32 {
+        self.expires_at.saturating_duration_since(now).as_secs() as u32
+    }
+
+    /// Create an owned connect target for this service.
+    pub fn to_target(&self) -> ServiceTarget {
+        ServiceTarget {
+            service_id: self.service_id,
+            instance_id: self.instance_id,
+            major_version: self.major_version,
+            minor_version: self.minor_version,
+            endpoints: self.endpoints.clone(),
+        }
+    }
+}
+
+/// An owned connect target for a discovered service.
+///
+/// Unlike [`ServiceInfo`], it carries no expiry and can outlive the [`SdClient`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceTarget {
+    /// Service ID.
+    pub service_id: ServiceId,
+    /// Instance ID.
+    pub instance_id: InstanceId,
+    /// Major version.
+    pub major_version: u8,
+    /// Minor version.
+    pub minor_version: u32,
+    /// Available endpoints for connecting to the service.
+    pub endpoints: Vec<Endpoint>,
+}
+
+impl ServiceTarget {
+    /// Get the first endpoint using the given protocol.
+    pub fn endpoint(&self, protocol: TransportProtocol) -> Option<&Endpoint> {
+        self.endpoints.iter().find(|e| e.protocol == protocol)
     }
 
-    /// Get remaining TTL in seconds.
-    pub fn remaining_ttl(&self) -> u32 {
-        self.expires_at
-            .saturating_duration_since(Instant::now())
-            .as_secs() as u32
+    /// Connect to the service's TCP endpoint.
+    pub fn connect_tcp(&self) -> Result<TcpClient> {
+        let endpoint = self.endpoint(TransportProtocol::Tcp).ok_or_else(|| {
+            SomeIpError::io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Service {} has no TCP endpoint", self.service_id),
+            ))
+        })?;
+        TcpClient::connect(endpoint.address)
+    }
+
+    /// Create a UDP client connected to the service's UDP endpoint.
+    pub fn connect_udp(&self) -> Result<UdpClient> {
+        let endpoint = self.endpoint(TransportProtocol::Udp).ok_or_else(|| {
+            SomeIpError::io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Service {} has no UDP endpoint", self.service_id),
+            ))
+        })?;
+        let bind_addr = match endpoint.address {
+            SocketAddr::V4(_) => "0.0.0.0:0",
+            SocketAddr::V6(_) => "[::]:0",
+        };
+        let client = UdpClient::bind(bind_addr)?;
+        client.connect(endpoint.address)?;
+        Ok(client)
     }
 }
 
@@ -89,12 +162,16 @@ pub struct SdClientConfig {
     pub bind_addr: SocketAddr,
     /// Multicast address for SD.
     pub multicast_addr: SocketAddr,
-    /// Interface address for multicast (None = any).
+    /// Interface address for multicast join and send (None = any).
     pub multicast_interface: Option<Ipv4Addr>,
     /// Default TTL for find requests.
     pub find_ttl: u32,
     /// Default TTL for subscriptions.
     pub subscribe_ttl: u32,
+    /// Maximum FindService messages per second (None = unlimited).
+    pub max_finds_per_second: Option<u32>,
+    /// Retry policy for subscriptions answered with a NACK (None = no retry).
+    pub nack_retry: Option<RetryPolicy>,
 }
 
 impl Default for SdClientConfig {
@@ -105,6 +182,8 @@ impl Default for SdClientConfig {
             multicast_interface: None,
             find_ttl: 0xFFFFFF,
             subscribe_ttl: 0xFFFFFF,
+            max_finds_per_second: None,
+            nack_retry: None,
         }
     }
 }
@@ -114,9 +193,16 @@ pub struct SdClient {
     socket: UdpSocket,
     multicast_addr: SocketAddr,
     services: HashMap<(ServiceId, InstanceId), ServiceInfo>,
+    pending_finds: HashSet<(ServiceId, InstanceId)>,
+    static_services: HashSet<(ServiceId, InstanceId)>,
     recv_buffer: Vec<u8>,
+    pending_events: VecDeque<SdEvent>,
     subscribe_ttl: u32,
     local_endpoint: Option<Endpoint>,
+    request_initial_data: bool,
+    find_limiter: Option<TokenBucket>,
+    nack_retry: Option<RetryPolicy>,
+    clock: Arc<dyn Clock>,
 }
 
 impl SdClient {
@@ -137,6 +223,13 @@ impl SdClient {
                 .map_err(SomeIpError::io)?;
         }
 
+        // Send multicast through the configured interface
+        if let Some(interface) = config.multicast_interface {
+            SockRef::from(&socket)
+                .set_multicast_if_v4(&interface)
+                .map_err(SomeIpError::io)?;
+        }
+
         // Set non-blocking for poll operations
         socket.set_nonblocking(true).map_err(SomeIpError::io)?;
 
@@ -144,17 +237,111 @@ impl SdClient {
             socket,
             multicast_addr: config.multicast_addr,
             services: HashMap::new(),
+            pending_finds: HashSet::new(),
+            static_services: HashSet::new(),
             recv_buffer: vec![0u8; 65535],
+            pending_events: VecDeque::new(),
             subscribe_ttl: config.subscribe_ttl,
             local_endpoint: None,
+            request_initial_data: false,
+            find_limiter: config.max_finds_per_second.map(TokenBucket::new),
+            nack_retry: config.nack_retry,
+            clock: clock::system(),
         })
     }
 
+    /// Set the clock used for service expiry and subscription deadlines.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Get the current time of the clock used for service expiry.
+    pub fn now(&self) -> Instant {
+        self.clock.now()
+    }
+
+    /// Open a socket for receiving events on a multicast endpoint.
+    ///
+    /// Use this with the multicast endpoint from [`SdEvent::SubscriptionAck`].
+    /// The returned socket is bound to the endpoint port and joined to the
+    /// group on `interface`. `SO_REUSEADDR` is set, so other receivers on the
+    /// host can share the port.
+    pub fn join_event_multicast(
+        &self,
+        endpoint: &Endpoint,
+        interface: Ipv4Addr,
+    ) -> Result<UdpSocket> {
+        let group = match endpoint.address {
+            SocketAddr::V4(addr) if addr.ip().is_multicast() => addr,
+            _ => {
+                return Err(SomeIpError::io(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Endpoint {} is not an IPv4 multicast address", endpoint.address),
+                )));
+            }
+        };
+
+        let socket = bind_reusable(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, group.port()).into())
+            .map_err(SomeIpError::io)?;
+        socket
+            .join_multicast_v4(group.ip(), &interface)
+            .map_err(SomeIpError::io)?;
+        Ok(socket)
+    }
+
+    /// Open a socket for receiving events on an IPv6 multicast endpoint.
+    ///
+    /// Like [`join_event_multicast`](Self::join_event_multicast), but joins
+    /// the group on the interface with index `interface` (0 lets the system
+    /// choose).
+    pub fn join_event_multicast_v6(
+        &self,
+        endpoint: &Endpoint,
+        interface: u32,
+    ) -> Result<UdpSocket> {
+        let group = match endpoint.address {
+            SocketAddr::V6(addr) if addr.ip().is_multicast() => addr,
+            _ => {
+                return Err(SomeIpError::io(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Endpoint {} is not an IPv6 multicast address", endpoint.address),
+                )));
+            }
+        };
+
+        let any = SocketAddrV6::new(Ipv6Addr::UNSPECIFIED, group.port(), 0, 0);
+        let socket = bind_reusable(any.into()).map_err(SomeIpError::io)?;
+        socket
+            .join_multicast_v6(group.ip(), interface)
+            .map_err(SomeIpError::io)?;
+        Ok(socket)
+    }
+
     /// Set the local endpoint to use for subscriptions.
     pub fn set_local_endpoint(&mut self, endpoint: Endpoint) {
         self.local_endpoint = Some(endpoint);
     }
 
+    /// Replace the local endpoint, returning the previous one.
+    pub(crate) fn replace_local_endpoint(
+        &mut self,
+        endpoint: Option<Endpoint>,
+    ) -> Option<Endpoint> {
+        std::mem::replace(&mut self.local_endpoint, endpoint)
+    }
+
+    /// Request initial event data on subsequent subscribes.
+    ///
+    /// Sets the explicit initial data flag on subscribe messages.
+    pub fn set_request_initial_data(&mut self, request: bool) {
+        self.request_initial_data = request;
+    }
+
+    /// Check if subscribes request initial event data.
+    pub fn request_initial_data(&self) -> bool {
+        self.request_initial_data
+    }
+
     /// Get the local address of the socket.
     pub fn local_addr(&self) -> Result<SocketAddr> {
         self.socket.local_addr().map_err(SomeIpError::io)
@@ -170,6 +357,8 @@ impl SdClient {
     }
 
     /// Send a FindService message for a specific service version.
+    ///
+    /// If `max_finds_per_second` is set, blocks until the rate allows sending.
     pub fn find_service_version(
         &mut self,
         service_id: ServiceId,
@@ -177,8 +366,48 @@ impl SdClient {
         major_version: u8,
         minor_version: u32,
     ) -> Result<()> {
+        self.wait_for_find_slot();
+
         let msg = SdMessage::find_service(service_id, instance_id, major_version, minor_version);
-        self.send_message(&msg)
+        self.send_message(&msg)?;
+        self.pending_finds.insert((service_id, instance_id));
+        Ok(())
+    }
+
+    /// Send a FindService message directly to a unicast SD endpoint.
+    ///
+    /// The unicast flag is set so the offer is answered to this client.
+    pub fn find_service_unicast(
+        &mut self,
+        sd_addr: SocketAddr,
+        service_id: ServiceId,
+        instance_id: InstanceId,
+    ) -> Result<()> {
+        self.wait_for_find_slot();
+
+        let mut msg = SdMessage::find_service(service_id, instance_id, 0xFF, 0xFFFFFFFF);
+        msg.flags.unicast = true;
+        self.socket
+            .send_to(&msg.to_datagram_bytes(), sd_addr)
+            .map_err(SomeIpError::io)?;
+        self.pending_finds.insert((service_id, instance_id));
+        Ok(())
+    }
+
+    /// Block until the find rate limit allows sending.
+    fn wait_for_find_slot(&mut self) {
+        if let Some(limiter) = &mut self.find_limiter {
+            while !limiter.try_acquire() {
+                std::thread::sleep(limiter.time_until_available());
+            }
+        }
+    }
+
+    /// Get services that were searched for but not offered yet.
+    ///
+    /// Use this to decide which finds to repeat.
+    pub fn pending_finds(&self) -> Vec<(ServiceId, InstanceId)> {
+        self.pending_finds.iter().copied().collect()
     }
 
     /// Subscribe to an eventgroup.
@@ -188,12 +417,38 @@ impl SdClient {
         instance_id: InstanceId,
         eventgroup_id: EventgroupId,
         major_version: u8,
+    ) -> Result<()> {
+        let config = Vec::new();
+        self.subscribe_with_config(service_id, instance_id, eventgroup_id, major_version, config)
+    }
+
+    /// Subscribe to an eventgroup, attaching key/value configuration.
+    ///
+    /// The configuration option is referenced by the entry's second option
+    /// run, after the local endpoint in the first run. An empty `config` adds
+    /// no option. Each `key=value` item is limited to 127 bytes, see
+    /// [`ConfigurationOption::from_pairs`].
+    pub fn subscribe_with_config(
+        &mut self,
+        service_id: ServiceId,
+        instance_id: InstanceId,
+        eventgroup_id: EventgroupId,
+        major_version: u8,
+        config: Vec<(String, String)>,
     ) -> Result<()> {
         let endpoint = self.local_endpoint.clone().ok_or_else(|| {
-            SomeIpError::invalid_header("Local endpoint not set for subscription")
+            SomeIpError::io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Local endpoint not set for subscription",
+            ))
         })?;
+        let config = if config.is_empty() {
+            None
+        } else {
+            Some(ConfigurationOption::from_pairs(&config)?)
+        };
 
-        let msg = SdMessage::subscribe_eventgroup(
+        let mut msg = SdMessage::subscribe_eventgroup(
             service_id,
             instance_id,
             major_version,
@@ -201,9 +456,90 @@ impl SdClient {
             self.subscribe_ttl,
             endpoint,
         );
+        if let Some(config) = config {
+            if let Some(SdEntry::Eventgroup(entry)) = msg.entries.first_mut() {
+                entry.index_second_option = msg.options.len() as u8;
+                entry.num_options_2 = 1;
+            }
+            msg.options.push(SdOption::Configuration(config));
+        }
+        msg.flags.explicit_initial_data = self.request_initial_data;
         self.send_message(&msg)
     }
 
+    /// Subscribe to an eventgroup and wait at most `timeout` for the ACK.
+    ///
+    /// Returns the multicast endpoint assigned by the server, if any. A NACK
+    /// is retried according to [`SdClientConfig::nack_retry`] before failing
+    /// with [`SomeIpError::SubscriptionRejected`]. The timeout covers all
+    /// attempts. Unrelated events received meanwhile are kept for
+    /// [`poll`](Self::poll).
+    pub fn subscribe_and_wait(
+        &mut self,
+        service_id: ServiceId,
+        instance_id: InstanceId,
+        eventgroup_id: EventgroupId,
+        major_version: u8,
+        timeout: Duration,
+    ) -> Result<Option<Endpoint>> {
+        let deadline = self.clock.now() + timeout;
+        let mut attempt = 0;
+        self.subscribe(service_id, instance_id, eventgroup_id, major_version)?;
+
+        loop {
+            match self.poll_socket()? {
+                Some(SdEvent::SubscriptionAck {
+                    service_id: acked_service,
+                    instance_id: acked_instance,
+                    eventgroup_id: acked,
+                    multicast_endpoint,
+                }) if acked_service == service_id
+                    && acked_instance == instance_id
+                    && acked == eventgroup_id =>
+                {
+                    return Ok(multicast_endpoint);
+                }
+                Some(SdEvent::SubscriptionNack {
+                    service_id: rejected_service,
+                    instance_id: rejected_instance,
+                    eventgroup_id: rejected,
+                }) if rejected_service == service_id
+                    && rejected_instance == instance_id
+                    && rejected == eventgroup_id =>
+                {
+                    let retry = self.nack_retry.as_ref().filter(|p| p.should_retry(attempt));
+                    let Some(policy) = retry else {
+                        return Err(SomeIpError::SubscriptionRejected {
+                            service_id,
+                            instance_id,
+                            eventgroup_id,
+                        });
+                    };
+
+                    let delay = policy.delay_for_attempt(attempt);
+                    if self.clock.now() + delay >= deadline {
+                        return Err(SomeIpError::Timeout);
+                    }
+                    std::thread::sleep(delay);
+                    attempt += 1;
+                    self.subscribe(service_id, instance_id, eventgroup_id, major_version)?;
+                    continue;
+                }
+                Some(event) => {
+                    self.pending_events.push_back(event);
+                    continue;
+                }
+                None => {}
+            }
+
+            if self.clock.now() >= deadline {
+                return Err(SomeIpError::Timeout);
+            }
+            // Small sleep to avoid busy waiting
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
     /// Unsubscribe from an eventgroup.
     pub fn unsubscribe(
         &mut self,
@@ -223,13 +559,8 @@ impl SdClient {
 
     /// Send an SD message.
     fn send_message(&self, msg: &SdMessage) -> Result<()> {
-        let someip_msg = msg.to_someip_message();
-        let mut buf = Vec::with_capacity(16 + someip_msg.payload.len());
-        buf.extend_from_slice(&someip_msg.header.to_bytes());
-        buf.extend_from_slice(&someip_msg.payload);
-
         self.socket
-            .send_to(&buf, self.multicast_addr)
+            .send_to(&msg.to_datagram_bytes(), self.multicast_addr)
             .map_err(SomeIpError::io)?;
 
         Ok(())
@@ -237,6 +568,14 @@ impl SdClient {
 
     /// Poll for incoming SD messages (non-blocking).
     pub fn poll(&mut self) -> Result<Option<SdEvent>> {
+        if let Some(event) = self.pending_events.pop_front() {
+            return Ok(Some(event));
+        }
+        self.poll_socket()
+    }
+
+    /// Receive and process one SD message from the socket, if available.
+    fn poll_socket(&mut self) -> Result<Option<SdEvent>> {
         match self.socket.recv_from(&mut self.recv_buffer) {
             Ok((size, src_addr)) => {
                 // Copy data to avoid borrow issues
@@ -248,6 +587,28 @@ impl SdClient {
         }
     }
 
+    /// Process all pending SD messages without blocking.
+    ///
+    /// Keeps the service cache current by applying any received offers.
+    /// Returns the number of service entries that were added or refreshed.
+    pub fn refresh(&mut self) -> Result<usize> {
+        let mut refreshed = 0;
+        loop {
+            match self.socket.recv_from(&mut self.recv_buffer) {
+                Ok((size, src_addr)) => {
+                    let data = self.recv_buffer[..size].to_vec();
+                    if let Some(SdEvent::ServiceAvailable(_)) =
+                        self.process_message(&data, src_addr)?
+                    {
+                        refreshed += 1;
+                    }
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(refreshed),
+                Err(e) => return Err(SomeIpError::io(e)),
+            }
+        }
+    }
+
     /// Wait for a specific service to become available.
     pub fn wait_for_service(
         &mut self,
@@ -259,7 +620,7 @@ impl SdClient {
 
         // Check if already known
         if let Some(info) = self.get_service(service_id, instance_id) {
-            if !info.is_expired() {
+            if !info.is_expired_at(self.clock.now()) {
                 return Ok(Some(info.clone()));
             }
         }
@@ -269,13 +630,11 @@ impl SdClient {
 
         // Poll until found or timeout
         while Instant::now() < deadline {
-            if let Some(event) = self.poll()? {
-                if let SdEvent::ServiceAvailable(info) = event {
-                    if info.service_id == service_id
-                        && (instance_id.is_any() || info.instance_id == instance_id)
-                    {
-                        return Ok(Some(info));
-                    }
+            if let Some(SdEvent::ServiceAvailable(info)) = self.poll()? {
+                if info.service_id == service_id
+                    && (instance_id.is_any() || info.instance_id == instance_id)
+                {
+                    return Ok(Some(info));
                 }
             }
 
@@ -291,6 +650,17 @@ impl SdClient {
         self.services.get(&(service_id, instance_id))
     }
 
+    /// Add a pre-known service without network discovery.
+    ///
+    /// The service never expires and is kept by [`cleanup_expired`](Self::cleanup_expired).
+    pub fn add_static_service(&mut self, mut info: ServiceInfo) {
+        info.expires_at = self.clock.now() + Duration::from_secs(u32::MAX as u64);
+        let key = (info.service_id, info.instance_id);
+        self.pending_finds.remove(&key);
+        self.static_services.insert(key);
+        self.services.insert(key, info);
+    }
+
     /// Get all known services.
     pub fn services(&self) -> impl Iterator<Item = &ServiceInfo> {
         self.services.values()
@@ -298,10 +668,13 @@ impl SdClient {
 
     /// Remove expired services.
     pub fn cleanup_expired(&mut self) -> Vec<(ServiceId, InstanceId)> {
+        let now = self.clock.now();
         let expired: Vec<_> = self
             .services
             .iter()
-            .filter(|(_, info)| info.is_expired())
+            .filter(|(key, info)| {
+                !self.static_services.contains(*key) && info.is_expired_at(now)
+            })
             .map(|(key, _)| *key)
             .collect();
 
@@ -314,13 +687,7 @@ impl SdClient {
 
     /// Process a received message.
     fn process_message(&mut self, data: &[u8], src_addr: SocketAddr) -> Result<Option<SdEvent>> {
-        // Skip SOME/IP header (16 bytes)
-        if data.len() < 16 {
-            return Ok(None);
-        }
-
-        let sd_payload = &data[16..];
-        let sd_msg = match SdMessage::from_bytes(sd_payload) {
+        let sd_msg = match SdMessage::from_datagram_bytes(data) {
             Ok(msg) => msg,
             Err(_) => return Ok(None),
         };
@@ -341,19 +708,32 @@ impl SdClient {
                                 }));
                             } else {
                                 // New or updated offer
-                                let endpoints = sd_msg.get_endpoints_for_entry(entry);
+                                let mut endpoints = sd_msg.get_endpoints_for_entry(entry);
+                                apply_link_local_scope(&mut endpoints, src_addr);
+                                let load_balancing = sd_msg
+                                    .get_options_for_entry(entry)
+                                    .into_iter()
+                                    .find_map(|opt| match opt {
+                                        SdOption::LoadBalancing(lb) => Some(lb.clone()),
+                                        _ => None,
+                                    });
                                 let info = ServiceInfo {
                                     service_id: service_entry.service_id,
                                     instance_id: service_entry.instance_id,
                                     major_version: service_entry.major_version,
                                     minor_version: service_entry.minor_version,
                                     endpoints,
-                                    expires_at: Instant::now()
+                                    expires_at: self.clock.now()
                                         + Duration::from_secs(service_entry.ttl as u64),
                                     source_addr: src_addr,
+                                    load_balancing,
                                 };
                                 let key = (service_entry.service_id, service_entry.instance_id);
                                 self.services.insert(key, info.clone());
+                                self.pending_finds.retain(|(service_id, instance_id)| {
+                                    *service_id != key.0
+                                        || !(instance_id.is_any() || *instance_id == key.1)
+                                });
                                 return Ok(Some(SdEvent::ServiceAvailable(info)));
                             }
                         }
@@ -385,6 +765,7 @@ impl SdClient {
                         }
                     }
                 }
+                SdEntry::Raw { .. } => {}
             }
         }
 
@@ -392,9 +773,32 @@ impl SdClient {
     }
 }
 
+/// Bind a UDP socket with `SO_REUSEADDR`, so multicast receivers can share a port.
+fn bind_reusable(addr: SocketAddr) -> io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::for_address(addr), Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    socket.bind(&addr.into())?;
+    Ok(socket.into())
+}
+
+/// Scope link-local IPv6 endpoints to the interface the offer arrived on.
+fn apply_link_local_scope(endpoints: &mut [Endpoint], src_addr: SocketAddr) {
+    let SocketAddr::V6(src) = src_addr else {
+        return;
+    };
+    for endpoint in endpoints {
+        if let SocketAddr::V6(addr) = &mut endpoint.address {
+            if addr.ip().is_unicast_link_local() && addr.scope_id() == 0 {
+                addr.set_scope_id(src.scope_id());
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sd::test_util::{localhost_client_config, unicast_client_config};
 
     #[test]
     fn test_service_info_expiry() {
@@ -406,10 +810,428 @@ mod tests {
             endpoints: vec![],
             expires_at: Instant::now() + Duration::from_secs(10),
             source_addr: "192.168.1.1:30490".parse().unwrap(),
+            load_balancing: None,
+        };
+
+        let now = Instant::now();
+        assert!(!info.is_expired_at(now));
+        assert!(info.remaining_ttl_at(now) > 0);
+        assert!(info.is_expired_at(now + Duration::from_secs(10)));
+        assert_eq!(info.remaining_ttl_at(now + Duration::from_secs(10)), 0);
+    }
+
+    #[test]
+    fn test_service_target_connect() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let info = ServiceInfo {
+            service_id: ServiceId(0x1234),
+            instance_id: InstanceId(0x0001),
+            major_version: 1,
+            minor_version: 0,
+            endpoints: vec![Endpoint::tcp(listener.local_addr().unwrap())],
+            expires_at: Instant::now(),
+            source_addr: "127.0.0.1:30490".parse().unwrap(),
+            load_balancing: None,
+        };
+
+        let target = info.to_target();
+        drop(info);
+        assert_eq!(target.service_id, ServiceId(0x1234));
+
+        let client = target.connect_tcp().unwrap();
+        assert_eq!(client.connection().peer_addr(), listener.local_addr().unwrap());
+        listener.accept().unwrap();
+
+        assert!(matches!(
+            target.connect_udp(),
+            Err(SomeIpError::Io(e)) if e.kind() == io::ErrorKind::InvalidInput
+        ));
+    }
+
+    #[test]
+    fn test_sd_client_multicast_interface() {
+        let client = SdClient::with_config(localhost_client_config()).unwrap();
+
+        let interface = SockRef::from(&client.socket).multicast_if_v4().unwrap();
+        assert_eq!(interface, Ipv4Addr::LOCALHOST);
+    }
+
+    #[test]
+    fn test_sd_client_request_initial_data() {
+        let (receiver, config) = unicast_client_config();
+        let mut client = SdClient::with_config(config).unwrap();
+        client.set_local_endpoint(Endpoint::udp("127.0.0.1:40000".parse().unwrap()));
+
+        let mut buf = [0u8; 1500];
+        for request in [false, true] {
+            client.set_request_initial_data(request);
+            client
+                .subscribe(ServiceId(0x1234), InstanceId(0x0001), EventgroupId(0x0001), 1)
+                .unwrap();
+
+            let (len, _) = receiver.recv_from(&mut buf).unwrap();
+            let msg = SdMessage::from_datagram_bytes(&buf[..len]).unwrap();
+            assert_eq!(msg.flags.explicit_initial_data, request);
+        }
+    }
+
+    #[test]
+    fn test_sd_client_subscribe_with_config() {
+        let (receiver, config) = unicast_client_config();
+        let mut client = SdClient::with_config(config).unwrap();
+        let endpoint = Endpoint::udp("127.0.0.1:40000".parse().unwrap());
+        client.set_local_endpoint(endpoint.clone());
+
+        let pairs = vec![("client".to_string(), "ecu1".to_string())];
+        client
+            .subscribe_with_config(
+                ServiceId(0x1234),
+                InstanceId(0x0001),
+                EventgroupId(0x0001),
+                1,
+                pairs.clone(),
+            )
+            .unwrap();
+
+        let mut buf = [0u8; 1500];
+        let (len, _) = receiver.recv_from(&mut buf).unwrap();
+        let msg = SdMessage::from_datagram_bytes(&buf[..len]).unwrap();
+        assert_eq!(msg.entries[0].option_runs(), (0, 1, 1, 1));
+        assert_eq!(msg.get_endpoints_for_entry(&msg.entries[0]), vec![endpoint]);
+        match &msg.options[1] {
+            SdOption::Configuration(opt) => assert_eq!(opt.pairs(), pairs),
+            other => panic!("unexpected option: {:?}", other),
+        }
+
+        // A plain subscribe carries only the endpoint
+        client
+            .subscribe(ServiceId(0x1234), InstanceId(0x0001), EventgroupId(0x0001), 1)
+            .unwrap();
+        let (len, _) = receiver.recv_from(&mut buf).unwrap();
+        let msg = SdMessage::from_datagram_bytes(&buf[..len]).unwrap();
+        assert_eq!(msg.entries[0].option_runs(), (0, 1, 0, 0));
+        assert_eq!(msg.options.len(), 1);
+    }
+
+    #[test]
+    fn test_sd_client_refresh() {
+        let mut client = SdClient::with_config(localhost_client_config()).unwrap();
+        let client_addr = client.local_addr().unwrap();
+
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let offer = SdMessage::offer_service(
+            ServiceId(0x1234),
+            InstanceId(0x0001),
+            1,
+            0,
+            1,
+            Endpoint::udp("127.0.0.1:30501".parse().unwrap()),
+        )
+        .to_someip_message()
+        .to_bytes();
+
+        let offer_and_refresh = |client: &mut SdClient| {
+            server.send_to(&offer, client_addr).unwrap();
+            std::thread::sleep(Duration::from_millis(50));
+            client.refresh().unwrap()
         };
 
-        assert!(!info.is_expired());
-        assert!(info.remaining_ttl() > 0);
+        assert_eq!(offer_and_refresh(&mut client), 1);
+        std::thread::sleep(Duration::from_millis(600));
+        assert_eq!(offer_and_refresh(&mut client), 1);
+        std::thread::sleep(Duration::from_millis(600));
+
+        // Past the original 1s TTL, but kept alive by the second offer
+        let info = client.get_service(ServiceId(0x1234), InstanceId(0x0001)).unwrap();
+        assert!(!info.is_expired_at(client.now()));
+        assert_eq!(client.refresh().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_sd_client_pending_finds() {
+        let mut client = SdClient::with_config(localhost_client_config()).unwrap();
+        assert!(client.pending_finds().is_empty());
+
+        client.find_service(ServiceId(0x1234), InstanceId::ANY).unwrap();
+        client.find_service(ServiceId(0x5678), InstanceId(0x0001)).unwrap();
+        let mut pending = client.pending_finds();
+        pending.sort_by_key(|(service_id, _)| service_id.0);
+        assert_eq!(
+            pending,
+            vec![
+                (ServiceId(0x1234), InstanceId::ANY),
+                (ServiceId(0x5678), InstanceId(0x0001)),
+            ]
+        );
+
+        let offer = SdMessage::offer_service(
+            ServiceId(0x1234),
+            InstanceId(0x0002),
+            1,
+            0,
+            5,
+            Endpoint::udp("127.0.0.1:30501".parse().unwrap()),
+        );
+        client
+            .process_message(&offer.to_datagram_bytes(), "127.0.0.1:30490".parse().unwrap())
+            .unwrap();
+
+        // 0x5678 is never offered and stays pending
+        assert_eq!(
+            client.pending_finds(),
+            vec![(ServiceId(0x5678), InstanceId(0x0001))]
+        );
+    }
+
+    #[test]
+    fn test_sd_client_static_service() {
+        use crate::clock::MockClock;
+
+        let mut client = SdClient::with_config(localhost_client_config()).unwrap();
+        let clock = MockClock::new();
+        client.set_clock(Arc::new(clock.clone()));
+
+        client.add_static_service(ServiceInfo {
+            service_id: ServiceId(0x1234),
+            instance_id: InstanceId(0x0001),
+            major_version: 1,
+            minor_version: 0,
+            endpoints: vec![Endpoint::tcp("127.0.0.1:30509".parse().unwrap())],
+            expires_at: clock.now(),
+            source_addr: "127.0.0.1:30490".parse().unwrap(),
+            load_balancing: None,
+        });
+
+        let info = client
+            .wait_for_service(ServiceId(0x1234), InstanceId(0x0001), Duration::ZERO)
+            .unwrap()
+            .unwrap();
+        assert_eq!(info.endpoints.len(), 1);
+
+        clock.advance(Duration::from_secs(3600));
+        assert!(client.cleanup_expired().is_empty());
+        assert!(client.get_service(ServiceId(0x1234), InstanceId(0x0001)).is_some());
+    }
+
+    #[test]
+    fn test_sd_client_find_service_unicast() {
+        let mut client = SdClient::with_config(localhost_client_config()).unwrap();
+
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        client
+            .find_service_unicast(server.local_addr().unwrap(), ServiceId(0x1234), InstanceId::ANY)
+            .unwrap();
+
+        let mut buf = [0u8; 1500];
+        let (size, client_addr) = server.recv_from(&mut buf).unwrap();
+        let find = SdMessage::from_datagram_bytes(&buf[..size]).unwrap();
+        assert!(find.flags.unicast);
+        match &find.entries[0] {
+            SdEntry::Service(entry) => {
+                assert_eq!(entry.entry_type, EntryType::FindService);
+                assert_eq!(entry.service_id, ServiceId(0x1234));
+            }
+            other => panic!("unexpected entry: {:?}", other),
+        }
+
+        let offer = SdMessage::offer_service(
+            ServiceId(0x1234),
+            InstanceId(0x0001),
+            1,
+            0,
+            5,
+            Endpoint::udp("127.0.0.1:30501".parse().unwrap()),
+        );
+        server.send_to(&offer.to_datagram_bytes(), client_addr).unwrap();
+
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(client.refresh().unwrap(), 1);
+        assert!(client.get_service(ServiceId(0x1234), InstanceId(0x0001)).is_some());
+        assert!(client.pending_finds().is_empty());
+    }
+
+    #[test]
+    fn test_sd_client_subscribe_nack_retry() {
+        let (server, config) = unicast_client_config();
+        let config = SdClientConfig {
+            nack_retry: Some(RetryPolicy::fixed(3, Duration::from_millis(20))),
+            ..config
+        };
+        let mut client = SdClient::with_config(config).unwrap();
+        client.set_local_endpoint(Endpoint::udp("[::1]:30501".parse().unwrap()));
+
+        // NACK twice, then ACK, then NACK once more
+        let server_handle = std::thread::spawn(move || {
+            let mut buf = [0u8; 1500];
+            for attempt in 0..4 {
+                let (_, client_addr) = server.recv_from(&mut buf).unwrap();
+                let id = (ServiceId(0x1234), InstanceId(0x0001));
+                let reply = if attempt == 2 {
+                    // An unrelated offer arrives while waiting for the ACK
+                    let endpoint = Endpoint::udp("[::1]:30502".parse().unwrap());
+                    let other = ServiceId(0x5678);
+                    let offer = SdMessage::offer_service(other, id.1, 1, 0, 5, endpoint);
+                    server.send_to(&offer.to_datagram_bytes(), client_addr).unwrap();
+                    SdMessage::subscribe_eventgroup_ack(id.0, id.1, 1, EventgroupId(1), 5, 0, None)
+                } else {
+                    SdMessage::subscribe_eventgroup_nack(id.0, id.1, 1, EventgroupId(1), 0)
+                };
+                server.send_to(&reply.to_datagram_bytes(), client_addr).unwrap();
+            }
+        });
+
+        let result = client.subscribe_and_wait(
+            ServiceId(0x1234),
+            InstanceId(0x0001),
+            EventgroupId(1),
+            1,
+            Duration::from_secs(2),
+        );
+        assert_eq!(result.unwrap(), None);
+        match client.poll().unwrap() {
+            Some(SdEvent::ServiceAvailable(info)) => assert_eq!(info.service_id, ServiceId(0x5678)),
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        // Without a retry policy the NACK is final
+        client.nack_retry = None;
+        let result = client.subscribe_and_wait(
+            ServiceId(0x1234),
+            InstanceId(0x0001),
+            EventgroupId(1),
+            1,
+            Duration::from_secs(2),
+        );
+        assert!(matches!(
+            result,
+            Err(SomeIpError::SubscriptionRejected {
+                service_id: ServiceId(0x1234),
+                instance_id: InstanceId(0x0001),
+                eventgroup_id: EventgroupId(1),
+            })
+        ));
+
+        server_handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_apply_link_local_scope() {
+        let mut endpoints = vec![
+            Endpoint::udp("[fe80::1]:30501".parse().unwrap()),
+            Endpoint::udp("[2001:db8::1]:30501".parse().unwrap()),
+        ];
+        apply_link_local_scope(&mut endpoints, "[fe80::2%4]:30490".parse().unwrap());
+
+        assert_eq!(endpoints[0].address, "[fe80::1%4]:30501".parse().unwrap());
+        assert_eq!(endpoints[1].address, "[2001:db8::1]:30501".parse().unwrap());
+    }
+
+    #[test]
+    fn test_sd_client_join_event_multicast() {
+        let client = SdClient::with_config(localhost_client_config()).unwrap();
+
+        let group = Ipv4Addr::new(239, 192, 0, 42);
+        let endpoint = Endpoint::udp(SocketAddr::V4(SocketAddrV4::new(group, 0)));
+        let socket = client
+            .join_event_multicast(&endpoint, Ipv4Addr::LOCALHOST)
+            .unwrap();
+        assert!(SockRef::from(&socket).reuse_address().unwrap());
+
+        // Joining the same group again on the socket fails because it is already a member
+        #[cfg(target_os = "linux")]
+        {
+            let err = socket
+                .join_multicast_v4(&group, &Ipv4Addr::LOCALHOST)
+                .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+        }
+
+        // Another receiver can share the port
+        let port = socket.local_addr().unwrap().port();
+        let shared = Endpoint::udp(SocketAddr::V4(SocketAddrV4::new(group, port)));
+        client
+            .join_event_multicast(&shared, Ipv4Addr::LOCALHOST)
+            .unwrap();
+
+        let unicast = Endpoint::udp("127.0.0.1:30501".parse().unwrap());
+        assert!(matches!(
+            client.join_event_multicast(&unicast, Ipv4Addr::LOCALHOST),
+            Err(SomeIpError::Io(e)) if e.kind() == io::ErrorKind::InvalidInput
+        ));
+    }
+
+    #[test]
+    fn test_sd_client_join_event_multicast_v6() {
+        let client = SdClient::with_config(localhost_client_config()).unwrap();
+
+        let ipv4_group = Endpoint::udp("239.192.0.42:30501".parse().unwrap());
+        assert!(client.join_event_multicast_v6(&ipv4_group, 0).is_err());
+        let unicast = Endpoint::udp("[::1]:30501".parse().unwrap());
+        assert!(client.join_event_multicast_v6(&unicast, 0).is_err());
+
+        let group = Endpoint::udp("[ff12::1234]:0".parse().unwrap());
+        client.join_event_multicast_v6(&group, 1).unwrap();
+    }
+
+    #[test]
+    fn test_sd_client_mock_clock_expiry() {
+        use crate::clock::MockClock;
+
+        let mut client = SdClient::with_config(localhost_client_config()).unwrap();
+        let clock = MockClock::new();
+        client.set_clock(Arc::new(clock.clone()));
+
+        let offer = SdMessage::offer_service(
+            ServiceId(0x1234),
+            InstanceId(0x0001),
+            1,
+            0,
+            5,
+            Endpoint::udp("127.0.0.1:30501".parse().unwrap()),
+        );
+        client
+            .process_message(&offer.to_datagram_bytes(), "127.0.0.1:30490".parse().unwrap())
+            .unwrap();
+
+        clock.advance(Duration::from_secs(4));
+        assert!(client.cleanup_expired().is_empty());
+        let info = client.get_service(ServiceId(0x1234), InstanceId(0x0001)).unwrap();
+        assert_eq!(info.remaining_ttl_at(client.now()), 1);
+
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(
+            client.cleanup_expired(),
+            vec![(ServiceId(0x1234), InstanceId(0x0001))]
+        );
+        assert!(client.get_service(ServiceId(0x1234), InstanceId(0x0001)).is_none());
+    }
+
+    #[test]
+    fn test_sd_client_load_balancing_option() {
+        use crate::sd::entry::ServiceEntry;
+
+        let mut client = SdClient::with_config(localhost_client_config()).unwrap();
+
+        let endpoint = Endpoint::udp("127.0.0.1:30501".parse().unwrap());
+        let offer = SdMessage::builder()
+            .add_service_entry(
+                ServiceEntry::offer_service(ServiceId(0x1234), InstanceId(0x0001), 1, 0, 5),
+                &[
+                    endpoint.to_option(),
+                    SdOption::LoadBalancing(LoadBalancingOption::new(1, 100)),
+                ],
+            )
+            .unwrap()
+            .build();
+        let bytes = offer.to_datagram_bytes();
+        assert_eq!(SdMessage::from_datagram_bytes(&bytes).unwrap(), offer);
+
+        client.process_message(&bytes, "127.0.0.1:30490".parse().unwrap()).unwrap();
+        let info = client.get_service(ServiceId(0x1234), InstanceId(0x0001)).unwrap();
+        assert_eq!(info.endpoints, vec![endpoint]);
+        let load_balancing = info.load_balancing.as_ref().unwrap();
+        assert_eq!((load_balancing.priority, load_balancing.weight), (1, 100));
     }
 
     #[test]
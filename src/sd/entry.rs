@@ -92,17 +92,21 @@ impl ServiceEntry {
     /// Parse a service entry from bytes.
     pub fn from_bytes(data: &[u8]) -> Result<Self> {
         if data.len() < SD_ENTRY_SIZE {
-            return Err(SomeIpError::MessageTooShort {
-                expected: SD_ENTRY_SIZE,
-                actual: data.len(),
-            });
+            return Err(SomeIpError::sd_parse(
+                format!("entry too short: expected {} bytes, got {}", SD_ENTRY_SIZE, data.len()),
+                0,
+            ));
         }
 
-        let entry_type = EntryType::from_u8(data[0])
-            .ok_or_else(|| SomeIpError::invalid_header(format!("Unknown entry type: 0x{:02X}", data[0])))?;
+        let entry_type = EntryType::from_u8(data[0]).ok_or_else(|| {
+            SomeIpError::sd_parse(format!("unknown entry type 0x{:02X}", data[0]), 0)
+        })?;
 
         if !entry_type.is_service_entry() {
-            return Err(SomeIpError::invalid_header("Expected service entry type"));
+            return Err(SomeIpError::sd_parse(
+                format!("expected service entry type, got {:?}", entry_type),
+                0,
+            ));
         }
 
         let index_first_option = data[1];
@@ -255,17 +259,21 @@ impl EventgroupEntry {
     /// Parse an eventgroup entry from bytes.
     pub fn from_bytes(data: &[u8]) -> Result<Self> {
         if data.len() < SD_ENTRY_SIZE {
-            return Err(SomeIpError::MessageTooShort {
-                expected: SD_ENTRY_SIZE,
-                actual: data.len(),
-            });
+            return Err(SomeIpError::sd_parse(
+                format!("entry too short: expected {} bytes, got {}", SD_ENTRY_SIZE, data.len()),
+                0,
+            ));
         }
 
-        let entry_type = EntryType::from_u8(data[0])
-            .ok_or_else(|| SomeIpError::invalid_header(format!("Unknown entry type: 0x{:02X}", data[0])))?;
+        let entry_type = EntryType::from_u8(data[0]).ok_or_else(|| {
+            SomeIpError::sd_parse(format!("unknown entry type 0x{:02X}", data[0]), 0)
+        })?;
 
         if !entry_type.is_eventgroup_entry() {
-            return Err(SomeIpError::invalid_header("Expected eventgroup entry type"));
+            return Err(SomeIpError::sd_parse(
+                format!("expected eventgroup entry type, got {:?}", entry_type),
+                0,
+            ));
         }
 
         let index_first_option = data[1];
@@ -327,16 +335,20 @@ pub enum SdEntry {
     Service(ServiceEntry),
     /// Eventgroup entry (Subscribe/Ack).
     Eventgroup(EventgroupEntry),
+    /// Entry with an unrecognized type, preserved verbatim.
+    Raw {
+        /// Raw entry type byte.
+        entry_type: u8,
+        /// Complete entry bytes, including the type byte.
+        bytes: [u8; SD_ENTRY_SIZE],
+    },
 }
 
 impl SdEntry {
     /// Parse an entry from bytes.
     pub fn from_bytes(data: &[u8]) -> Result<Self> {
         if data.is_empty() {
-            return Err(SomeIpError::MessageTooShort {
-                expected: 1,
-                actual: 0,
-            });
+            return Err(SomeIpError::sd_parse("empty entry", 0));
         }
 
         let entry_type = EntryType::from_u8(data[0]);
@@ -346,18 +358,55 @@ impl SdEntry {
             Some(t) if t.is_eventgroup_entry() => {
                 Ok(SdEntry::Eventgroup(EventgroupEntry::from_bytes(data)?))
             }
-            _ => Err(SomeIpError::invalid_header(format!(
-                "Unknown entry type: 0x{:02X}",
-                data[0]
-            ))),
+            _ => Err(SomeIpError::sd_parse(
+                format!("unknown entry type 0x{:02X}", data[0]),
+                0,
+            )),
         }
     }
 
+    /// Keep an entry verbatim without interpreting its type.
+    pub fn raw(data: &[u8]) -> Result<Self> {
+        if data.len() < SD_ENTRY_SIZE {
+            return Err(SomeIpError::sd_parse(
+                format!("entry too short: expected {} bytes, got {}", SD_ENTRY_SIZE, data.len()),
+                0,
+            ));
+        }
+
+        let mut bytes = [0u8; SD_ENTRY_SIZE];
+        bytes.copy_from_slice(&data[..SD_ENTRY_SIZE]);
+        Ok(SdEntry::Raw {
+            entry_type: bytes[0],
+            bytes,
+        })
+    }
+
     /// Serialize the entry to bytes.
     pub fn to_bytes(&self) -> [u8; SD_ENTRY_SIZE] {
         match self {
             SdEntry::Service(e) => e.to_bytes(),
             SdEntry::Eventgroup(e) => e.to_bytes(),
+            SdEntry::Raw { bytes, .. } => *bytes,
+        }
+    }
+
+    /// Get the option run indices and counts as `(index1, num1, index2, num2)`.
+    pub fn option_runs(&self) -> (u8, u8, u8, u8) {
+        match self {
+            SdEntry::Service(e) => (
+                e.index_first_option,
+                e.num_options_1,
+                e.index_second_option,
+                e.num_options_2,
+            ),
+            SdEntry::Eventgroup(e) => (
+                e.index_first_option,
+                e.num_options_1,
+                e.index_second_option,
+                e.num_options_2,
+            ),
+            SdEntry::Raw { bytes, .. } => (bytes[1], bytes[3] >> 4, bytes[2], bytes[3] & 0x0F),
         }
     }
 
@@ -366,6 +415,7 @@ impl SdEntry {
         match self {
             SdEntry::Service(e) => e.service_id,
             SdEntry::Eventgroup(e) => e.service_id,
+            SdEntry::Raw { bytes, .. } => ServiceId(u16::from_be_bytes([bytes[4], bytes[5]])),
         }
     }
 
@@ -374,6 +424,7 @@ impl SdEntry {
         match self {
             SdEntry::Service(e) => e.instance_id,
             SdEntry::Eventgroup(e) => e.instance_id,
+            SdEntry::Raw { bytes, .. } => InstanceId(u16::from_be_bytes([bytes[6], bytes[7]])),
         }
     }
 
@@ -382,6 +433,7 @@ impl SdEntry {
         match self {
             SdEntry::Service(e) => e.ttl,
             SdEntry::Eventgroup(e) => e.ttl,
+            SdEntry::Raw { bytes, .. } => u32::from_be_bytes([0, bytes[9], bytes[10], bytes[11]]),
         }
     }
 }
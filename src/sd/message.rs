@@ -1,5 +1,7 @@
 //! SOME/IP-SD message handling.
 
+use std::io;
+
 use bytes::Bytes;
 
 use crate::error::{Result, SomeIpError};
@@ -8,7 +10,10 @@ use crate::message::SomeIpMessage;
 
 use super::entry::{EventgroupEntry, SdEntry, ServiceEntry};
 use super::option::{Endpoint, SdOption};
-use super::types::{EventgroupId, InstanceId, SD_ENTRY_SIZE, SD_METHOD_ID, SD_SERVICE_ID};
+use super::types::{
+    EntryType, EventgroupId, InstanceId, SD_ENTRY_SIZE, SD_MAX_OPTIONS_PER_RUN, SD_METHOD_ID,
+    SD_SERVICE_ID,
+};
 
 /// SD message flags.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -47,6 +52,29 @@ impl SdFlags {
     }
 }
 
+/// The option run through which an entry references an option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OptionRun {
+    /// Referenced by the first option run.
+    First,
+    /// Referenced by the second option run.
+    Second,
+}
+
+/// Options controlling how SD messages are parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SdParseOptions {
+    /// Skip entries with unrecognized types instead of failing.
+    pub skip_unknown_entries: bool,
+    /// Keep entries with unrecognized types as [`SdEntry::Raw`].
+    ///
+    /// Takes precedence over `skip_unknown_entries`.
+    pub retain_unknown_entries: bool,
+    /// Accept messages that end after the entries array, without the
+    /// options length field, and parse them with no options.
+    pub allow_missing_options_length: bool,
+}
+
 /// A SOME/IP-SD message.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SdMessage {
@@ -59,6 +87,11 @@ pub struct SdMessage {
 }
 
 impl SdMessage {
+    /// Create a builder for a message with several entries.
+    pub fn builder() -> SdMessageBuilder {
+        SdMessageBuilder::new()
+    }
+
     /// Create a new empty SD message.
     pub fn new() -> Self {
         Self {
@@ -104,6 +137,59 @@ impl SdMessage {
         }
     }
 
+    /// Create an OfferService message referencing several endpoints.
+    ///
+    /// All endpoint options share the entry's first option run, as done for
+    /// services reachable over both TCP and UDP. Fails if more than
+    /// [`SD_MAX_OPTIONS_PER_RUN`] endpoints are given.
+    pub fn offer_service_endpoints(
+        service_id: ServiceId,
+        instance_id: InstanceId,
+        major_version: u8,
+        minor_version: u32,
+        ttl: u32,
+        endpoints: &[Endpoint],
+    ) -> Result<Self> {
+        check_option_run(endpoints.len())?;
+        let mut entry =
+            ServiceEntry::offer_service(service_id, instance_id, major_version, minor_version, ttl);
+        entry.index_first_option = 0;
+        entry.num_options_1 = endpoints.len() as u8;
+
+        Ok(Self {
+            flags: SdFlags::default(),
+            entries: vec![SdEntry::Service(entry)],
+            options: endpoints.iter().map(Endpoint::to_option).collect(),
+        })
+    }
+
+    /// Create an OfferService message with a unicast and a multicast endpoint.
+    ///
+    /// The unicast endpoint is referenced by the first option run and the
+    /// multicast endpoint, as a multicast option, by the second.
+    pub fn offer_service_dual_endpoint(
+        service_id: ServiceId,
+        instance_id: InstanceId,
+        major_version: u8,
+        minor_version: u32,
+        ttl: u32,
+        unicast: Endpoint,
+        multicast: Endpoint,
+    ) -> Self {
+        let mut entry =
+            ServiceEntry::offer_service(service_id, instance_id, major_version, minor_version, ttl);
+        entry.index_first_option = 0;
+        entry.num_options_1 = 1;
+        entry.index_second_option = 1;
+        entry.num_options_2 = 1;
+
+        Self {
+            flags: SdFlags::default(),
+            entries: vec![SdEntry::Service(entry)],
+            options: vec![unicast.to_option(), multicast.to_multicast_option()],
+        }
+    }
+
     /// Create a StopOfferService message.
     pub fn stop_offer_service(
         service_id: ServiceId,
@@ -166,6 +252,29 @@ impl SdMessage {
         }
     }
 
+    /// Create a teardown message for a service.
+    ///
+    /// Packs a StopOffer entry and a StopSubscribe entry per eventgroup.
+    pub fn teardown(
+        service_id: ServiceId,
+        instance_id: InstanceId,
+        major_version: u8,
+        minor_version: u32,
+        eventgroups: &[EventgroupId],
+    ) -> Self {
+        let mut msg =
+            Self::stop_offer_service(service_id, instance_id, major_version, minor_version);
+        msg.entries.extend(eventgroups.iter().map(|&eventgroup_id| {
+            SdEntry::Eventgroup(EventgroupEntry::unsubscribe(
+                service_id,
+                instance_id,
+                major_version,
+                eventgroup_id,
+            ))
+        }));
+        msg
+    }
+
     /// Create a SubscribeEventgroupAck message.
     pub fn subscribe_eventgroup_ack(
         service_id: ServiceId,
@@ -224,23 +333,39 @@ impl SdMessage {
 
     /// Parse an SD message from bytes (SD payload only, not including SOME/IP header).
     pub fn from_bytes(data: &[u8]) -> Result<Self> {
-        if data.len() < 12 {
-            return Err(SomeIpError::MessageTooShort {
-                expected: 12,
-                actual: data.len(),
-            });
+        Self::from_bytes_with_options(data, SdParseOptions::default())
+    }
+
+    /// Parse an SD message from bytes with custom parse options.
+    pub fn from_bytes_with_options(data: &[u8], parse_options: SdParseOptions) -> Result<Self> {
+        let min_length = if parse_options.allow_missing_options_length { 8 } else { 12 };
+        if data.len() < min_length {
+            return Err(SomeIpError::sd_parse(
+                format!(
+                    "message too short: expected at least {} bytes, got {}",
+                    min_length,
+                    data.len()
+                ),
+                0,
+            ));
         }
 
         let flags = SdFlags::from_u8(data[0]);
         // data[1..4] is reserved
 
         let entries_length = u32::from_be_bytes([data[4], data[5], data[6], data[7]]) as usize;
-
-        if data.len() < 8 + entries_length + 4 {
-            return Err(SomeIpError::MessageTooShort {
-                expected: 8 + entries_length + 4,
-                actual: data.len(),
-            });
+        let options_length_missing =
+            parse_options.allow_missing_options_length && data.len() == 8 + entries_length;
+
+        if data.len() < 8 + entries_length + 4 && !options_length_missing {
+            return Err(SomeIpError::sd_parse(
+                format!(
+                    "entries length {} exceeds message of {} bytes",
+                    entries_length,
+                    data.len()
+                ),
+                4,
+            ));
         }
 
         // Parse entries
@@ -248,28 +373,58 @@ impl SdMessage {
         let mut entries = Vec::new();
         let mut offset = 0;
         while offset + SD_ENTRY_SIZE <= entries_data.len() {
-            let entry = SdEntry::from_bytes(&entries_data[offset..])?;
-            entries.push(entry);
+            let entry_data = &entries_data[offset..];
+            let entry_offset = 8 + offset;
             offset += SD_ENTRY_SIZE;
+
+            if EntryType::from_u8(entry_data[0]).is_none() {
+                if parse_options.retain_unknown_entries {
+                    entries.push(SdEntry::raw(entry_data)?);
+                    continue;
+                }
+                if parse_options.skip_unknown_entries {
+                    continue;
+                }
+            }
+            let entry =
+                SdEntry::from_bytes(entry_data).map_err(|e| e.shift_sd_offset(entry_offset))?;
+            entries.push(entry);
+        }
+
+        if options_length_missing {
+            return Ok(Self {
+                flags,
+                entries,
+                options: Vec::new(),
+            });
         }
 
         // Parse options
         let options_offset = 8 + entries_length;
-        let options_length =
-            u32::from_be_bytes([data[options_offset], data[options_offset + 1], data[options_offset + 2], data[options_offset + 3]]) as usize;
+        let options_length = u32::from_be_bytes([
+            data[options_offset],
+            data[options_offset + 1],
+            data[options_offset + 2],
+            data[options_offset + 3],
+        ]) as usize;
 
         let options_data = &data[options_offset + 4..];
         if options_data.len() < options_length {
-            return Err(SomeIpError::MessageTooShort {
-                expected: options_length,
-                actual: options_data.len(),
-            });
+            return Err(SomeIpError::sd_parse(
+                format!(
+                    "options length {} exceeds remaining {} bytes",
+                    options_length,
+                    options_data.len()
+                ),
+                options_offset,
+            ));
         }
 
         let mut options = Vec::new();
         let mut opt_offset = 0;
         while opt_offset < options_length {
-            let (option, size) = SdOption::from_bytes(&options_data[opt_offset..])?;
+            let (option, size) = SdOption::from_bytes(&options_data[opt_offset..])
+                .map_err(|e| e.shift_sd_offset(options_offset + 4 + opt_offset))?;
             options.push(option);
             opt_offset += size;
         }
@@ -331,6 +486,14 @@ impl SdMessage {
         buf
     }
 
+    /// Check if every option ends on a 4-byte boundary when serialized.
+    ///
+    /// Entries are always 16 bytes, so only options can break alignment,
+    /// e.g. [`SdOption::Unknown`] data of arbitrary length.
+    pub fn is_wire_aligned(&self) -> bool {
+        self.options.iter().all(|o| o.to_bytes().len() % 4 == 0)
+    }
+
     /// Convert to a SOME/IP message.
     pub fn to_someip_message(&self) -> SomeIpMessage {
         let payload = self.to_bytes();
@@ -339,6 +502,16 @@ impl SdMessage {
             .build()
     }
 
+    /// Serialize to a complete datagram (SOME/IP header + SD payload).
+    pub fn to_datagram_bytes(&self) -> Vec<u8> {
+        self.to_someip_message().to_bytes()
+    }
+
+    /// Parse a complete datagram, validating the SOME/IP header.
+    pub fn from_datagram_bytes(data: &[u8]) -> Result<Self> {
+        Self::from_someip_message(&SomeIpMessage::from_bytes(data)?)
+    }
+
     /// Check if this is a FindService message.
     pub fn is_find_service(&self) -> bool {
         self.entries.iter().any(|e| {
@@ -371,20 +544,9 @@ impl SdMessage {
 
     /// Get the options for an entry by index.
     pub fn get_options_for_entry(&self, entry: &SdEntry) -> Vec<&SdOption> {
-        let (index1, num1, index2, num2) = match entry {
-            SdEntry::Service(e) => (
-                e.index_first_option as usize,
-                e.num_options_1 as usize,
-                e.index_second_option as usize,
-                e.num_options_2 as usize,
-            ),
-            SdEntry::Eventgroup(e) => (
-                e.index_first_option as usize,
-                e.num_options_1 as usize,
-                e.index_second_option as usize,
-                e.num_options_2 as usize,
-            ),
-        };
+        let (index1, num1, index2, num2) = entry.option_runs();
+        let (index1, num1) = (index1 as usize, num1 as usize);
+        let (index2, num2) = (index2 as usize, num2 as usize);
 
         let mut options = Vec::new();
 
@@ -405,6 +567,28 @@ impl SdMessage {
         options
     }
 
+    /// Get the options for an entry, labeled with the run referencing them.
+    ///
+    /// Options are returned in run order; indices beyond the option array are
+    /// skipped.
+    pub fn describe_entry_options(&self, entry: &SdEntry) -> Vec<(OptionRun, &SdOption)> {
+        let (index1, num1, index2, num2) = entry.option_runs();
+        let runs = [
+            (OptionRun::First, index1 as usize, num1 as usize),
+            (OptionRun::Second, index2 as usize, num2 as usize),
+        ];
+
+        runs.into_iter()
+            .flat_map(|(run, index, num)| {
+                self.options
+                    .iter()
+                    .skip(index)
+                    .take(num)
+                    .map(move |opt| (run, opt))
+            })
+            .collect()
+    }
+
     /// Get endpoints from options for an entry.
     pub fn get_endpoints_for_entry(&self, entry: &SdEntry) -> Vec<Endpoint> {
         self.get_options_for_entry(entry)
@@ -420,10 +604,301 @@ impl Default for SdMessage {
     }
 }
 
+/// Builder for SD messages with several entries.
+///
+/// Identical options are stored once and shared by all entries referencing
+/// them, which keeps multi-service announcements small.
+#[derive(Debug, Clone, Default)]
+pub struct SdMessageBuilder {
+    message: SdMessage,
+}
+
+impl SdMessageBuilder {
+    /// Create an empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the message flags.
+    pub fn flags(mut self, flags: SdFlags) -> Self {
+        self.message.flags = flags;
+        self
+    }
+
+    /// Add an OfferService entry for a service reachable at `endpoint`.
+    ///
+    /// Fails like [`add_service_entry`](Self::add_service_entry).
+    pub fn add_offer(
+        self,
+        service_id: ServiceId,
+        instance_id: InstanceId,
+        major_version: u8,
+        minor_version: u32,
+        ttl: u32,
+        endpoint: Endpoint,
+    ) -> Result<Self> {
+        let entry =
+            ServiceEntry::offer_service(service_id, instance_id, major_version, minor_version, ttl);
+        self.add_service_entry(entry, &[endpoint.to_option()])
+    }
+
+    /// Add a service entry referencing `options` through its first option run.
+    ///
+    /// The run reuses an identical sequence of options already in the message
+    /// if there is one. Fails if `options` has more than
+    /// [`SD_MAX_OPTIONS_PER_RUN`] entries or the run would start past option
+    /// index 255.
+    pub fn add_service_entry(
+        mut self,
+        mut entry: ServiceEntry,
+        options: &[SdOption],
+    ) -> Result<Self> {
+        let (index, num) = self.intern_options(options)?;
+        entry.index_first_option = index;
+        entry.num_options_1 = num;
+        self.message.entries.push(SdEntry::Service(entry));
+        Ok(self)
+    }
+
+    /// Build the message.
+    pub fn build(self) -> SdMessage {
+        self.message
+    }
+
+    /// Find or append a run of options, returning its index and length.
+    fn intern_options(&mut self, options: &[SdOption]) -> Result<(u8, u8)> {
+        if options.is_empty() {
+            return Ok((0, 0));
+        }
+        check_option_run(options.len())?;
+
+        let existing = &self.message.options;
+        let found = existing.windows(options.len()).position(|window| window == options);
+        let index = found.unwrap_or(existing.len());
+        let index = u8::try_from(index).map_err(|_| {
+            SomeIpError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Option index {} exceeds 255", index),
+            ))
+        })?;
+        if found.is_none() {
+            self.message.options.extend_from_slice(options);
+        }
+        Ok((index, options.len() as u8))
+    }
+}
+
+/// Check that `len` options fit into a single option run of an entry.
+pub(crate) fn check_option_run(len: usize) -> Result<()> {
+    if len > SD_MAX_OPTIONS_PER_RUN {
+        return Err(SomeIpError::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} options exceed the {} options of an option run",
+                len, SD_MAX_OPTIONS_PER_RUN
+            ),
+        )));
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sd::option::ConfigurationOption;
+    use crate::sd::types::{OptionType, TransportProtocol};
     use crate::types::MessageType;
+    use std::net::SocketAddr;
+
+    #[test]
+    fn test_sd_datagram_roundtrip() {
+        let msg = SdMessage::offer_service(
+            ServiceId(0x1234),
+            InstanceId(0x0001),
+            1,
+            0,
+            3600,
+            Endpoint::udp("192.168.1.100:30501".parse().unwrap()),
+        );
+
+        let datagram = msg.to_datagram_bytes();
+        assert_eq!(&datagram[0..4], &[0xFF, 0xFF, 0x81, 0x00]);
+        assert_eq!(SdMessage::from_datagram_bytes(&datagram).unwrap(), msg);
+
+        // Non-SD messages are rejected
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .payload(msg.to_bytes())
+            .build();
+        assert!(SdMessage::from_datagram_bytes(&request.to_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_sd_raw_entry_roundtrip() {
+        let find = ServiceEntry::find_service(ServiceId(0x1234), InstanceId::ANY, 0xFF, 0xFFFFFFFF);
+        let offer = ServiceEntry::offer_service(ServiceId(0x5678), InstanceId(0x0001), 1, 0, 3600);
+        let mut raw_bytes = [0xA5u8; SD_ENTRY_SIZE];
+        raw_bytes[0] = 0x03;
+        raw_bytes[1..4].copy_from_slice(&[0, 0, 0]);
+
+        let msg = SdMessage {
+            flags: SdFlags::default(),
+            entries: vec![
+                SdEntry::Service(find),
+                SdEntry::raw(&raw_bytes).unwrap(),
+                SdEntry::Service(offer),
+            ],
+            options: Vec::new(),
+        };
+        let bytes = msg.to_bytes();
+
+        let options = SdParseOptions {
+            retain_unknown_entries: true,
+            ..Default::default()
+        };
+        let parsed = SdMessage::from_bytes_with_options(&bytes, options).unwrap();
+        assert_eq!(parsed, msg);
+        assert!(matches!(
+            parsed.entries[1],
+            SdEntry::Raw { entry_type: 0x03, .. }
+        ));
+        assert_eq!(parsed.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_sd_skip_unknown_entries() {
+        let mut msg = SdMessage::find_service(ServiceId(0x1234), InstanceId::ANY, 0xFF, 0xFFFFFFFF);
+        msg.entries.push(msg.entries[0].clone());
+        let mut bytes = msg.to_bytes();
+
+        // Turn the second entry into an unknown vendor entry type
+        bytes[8 + SD_ENTRY_SIZE] = 0x03;
+
+        assert!(SdMessage::from_bytes(&bytes).is_err());
+
+        let options = SdParseOptions {
+            skip_unknown_entries: true,
+            ..Default::default()
+        };
+        let parsed = SdMessage::from_bytes_with_options(&bytes, options).unwrap();
+        assert_eq!(parsed.entries.len(), 1);
+        assert!(parsed.is_find_service());
+    }
+
+    #[test]
+    fn test_sd_missing_options_length() {
+        let msg = SdMessage::find_service(ServiceId(0x1234), InstanceId::ANY, 0xFF, 0xFFFFFFFF);
+        let mut bytes = msg.to_bytes();
+        bytes.truncate(bytes.len() - 4);
+
+        assert!(SdMessage::from_bytes(&bytes).is_err());
+
+        let options = SdParseOptions {
+            allow_missing_options_length: true,
+            ..Default::default()
+        };
+        let parsed = SdMessage::from_bytes_with_options(&bytes, options).unwrap();
+        assert_eq!(parsed, msg);
+
+        // A truncated options length field is still rejected
+        bytes.push(0);
+        assert!(SdMessage::from_bytes_with_options(&bytes, options).is_err());
+    }
+
+    #[test]
+    fn test_sd_message_builder_shares_options() {
+        let shared = Endpoint::tcp("192.168.1.10:30501".parse().unwrap());
+        let other = Endpoint::udp("192.168.1.20:30502".parse().unwrap());
+
+        let mut builder = SdMessage::builder();
+        for id in 0..10u16 {
+            builder = builder.add_offer(
+                ServiceId(0x1000 + id),
+                InstanceId(0x0001),
+                1,
+                0,
+                3600,
+                shared.clone(),
+            )
+            .unwrap();
+        }
+        let msg = builder
+            .add_offer(ServiceId(0x2000), InstanceId(0x0001), 1, 0, 3600, other.clone())
+            .unwrap()
+            .build();
+
+        assert_eq!(msg.entries.len(), 11);
+        assert_eq!(msg.options.len(), 2);
+
+        let parsed = SdMessage::from_bytes(&msg.to_bytes()).unwrap();
+        assert_eq!(parsed, msg);
+        for entry in &parsed.entries[..10] {
+            assert_eq!(parsed.get_endpoints_for_entry(entry), vec![shared.clone()]);
+        }
+        assert_eq!(parsed.get_endpoints_for_entry(&parsed.entries[10]), vec![other]);
+
+        // Runs are limited to 15 options and must start at index 255 or below
+        let options: Vec<_> = (0..16u16)
+            .map(|port| Endpoint::udp(SocketAddr::from(([192, 168, 1, 1], port))).to_option())
+            .collect();
+        let entry = ServiceEntry::offer_service(ServiceId(0x3000), InstanceId(0x0001), 1, 0, 3600);
+        let result = SdMessage::builder().add_service_entry(entry.clone(), &options);
+        let err = result.unwrap_err();
+        assert!(matches!(err, SomeIpError::Io(e) if e.kind() == io::ErrorKind::InvalidInput));
+
+        let endpoints: Vec<_> = (0..16u16)
+            .map(|port| Endpoint::udp(SocketAddr::from(([192, 168, 1, 1], port))))
+            .collect();
+        let offer = |endpoints: &[Endpoint]| {
+            let (service_id, instance_id) = (ServiceId(0x3000), InstanceId(0x0001));
+            SdMessage::offer_service_endpoints(service_id, instance_id, 1, 0, 3600, endpoints)
+        };
+        let result = offer(&endpoints);
+        let err = result.unwrap_err();
+        assert!(matches!(err, SomeIpError::Io(e) if e.kind() == io::ErrorKind::InvalidInput));
+        assert_eq!(offer(&endpoints[..15]).unwrap().options.len(), 15);
+
+        let mut builder = SdMessage::builder();
+        for chunk in (0..256u16).collect::<Vec<_>>().chunks(8) {
+            let options: Vec<_> = chunk
+                .iter()
+                .map(|&port| Endpoint::udp(SocketAddr::from(([10, 0, 0, 1], port))).to_option())
+                .collect();
+            builder = builder.add_service_entry(entry.clone(), &options).unwrap();
+        }
+        let overflow = Endpoint::udp("10.0.0.2:1".parse().unwrap()).to_option();
+        let result = builder.add_service_entry(entry, &[overflow]);
+        let err = result.unwrap_err();
+        assert!(matches!(err, SomeIpError::Io(e) if e.kind() == io::ErrorKind::InvalidInput));
+    }
+
+    #[test]
+    fn test_sd_teardown() {
+        let eventgroups = [EventgroupId(0x0001), EventgroupId(0x0002)];
+        let msg = SdMessage::teardown(ServiceId(0x1234), InstanceId(0x0001), 1, 0, &eventgroups);
+
+        let parsed = SdMessage::from_bytes(&msg.to_bytes()).unwrap();
+        assert_eq!(parsed, msg);
+        assert_eq!(parsed.entries.len(), 3);
+        assert!(parsed.is_stop_offer_service());
+
+        match &parsed.entries[0] {
+            SdEntry::Service(entry) => {
+                assert_eq!(entry.entry_type, EntryType::OfferService);
+                assert_eq!(entry.ttl, 0);
+            }
+            other => panic!("unexpected entry: {:?}", other),
+        }
+        for (entry, eventgroup) in parsed.entries[1..].iter().zip(eventgroups) {
+            match entry {
+                SdEntry::Eventgroup(entry) => {
+                    assert_eq!(entry.entry_type, EntryType::SubscribeEventgroup);
+                    assert_eq!(entry.eventgroup_id, eventgroup);
+                    assert_eq!(entry.ttl, 0);
+                }
+                other => panic!("unexpected entry: {:?}", other),
+            }
+        }
+    }
 
     #[test]
     fn test_sd_flags_roundtrip() {
@@ -490,6 +965,61 @@ mod tests {
         assert_eq!(original.options.len(), parsed.options.len());
     }
 
+    #[test]
+    fn test_sd_parse_error_offset() {
+        let endpoint = Endpoint::tcp("192.168.1.100:30490".parse().unwrap());
+        let msg =
+            SdMessage::offer_service(ServiceId(0x1234), InstanceId(0x0001), 1, 0, 3600, endpoint);
+        let mut bytes = msg.to_bytes();
+
+        // Flags and entries length (8), one entry (16), options length (4),
+        // option header (4), then the protocol byte at offset 5
+        let protocol_offset = 8 + SD_ENTRY_SIZE + 4 + 4 + 5;
+        assert_eq!(bytes[protocol_offset], TransportProtocol::Tcp as u8);
+        bytes[protocol_offset] = 0x99;
+
+        match SdMessage::from_bytes(&bytes) {
+            Err(SomeIpError::SdParse { reason, offset }) => {
+                assert_eq!(offset, protocol_offset);
+                assert!(reason.contains("0x99"));
+            }
+            other => panic!("expected SdParse error, got {:?}", other),
+        }
+
+        assert!(matches!(
+            SdMessage::from_bytes(&bytes[..10]),
+            Err(SomeIpError::SdParse { offset: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_sd_option_alignment() {
+        let endpoint = Endpoint::udp("192.168.1.100:30501".parse().unwrap());
+        let mut msg =
+            SdMessage::offer_service(ServiceId(0x1234), InstanceId(0x0001), 1, 0, 3600, endpoint);
+        // Odd length: one item of 5 bytes, its length prefix and the terminator
+        msg.options.push(SdOption::Configuration(ConfigurationOption::new("\x05abc=d\0")));
+        assert!(msg.is_wire_aligned());
+
+        // 12-byte endpoint option, then 4-byte header + 7 bytes + 1 padding
+        let bytes = msg.to_bytes();
+        let options = &bytes[8 + SD_ENTRY_SIZE + 4..];
+        assert_eq!(options.len(), 12 + 12);
+        assert_eq!(&options[0..3], &[0x00, 0x09, OptionType::IPv4Endpoint as u8]);
+        assert_eq!(&options[12..15], &[0x00, 0x09, OptionType::Configuration as u8]);
+        assert_eq!(&options[16..24], b"\x05abc=d\0\0");
+
+        let parsed = SdMessage::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, msg);
+
+        msg.options.push(SdOption::Unknown {
+            option_type: 0x77,
+            discardable: true,
+            data: vec![1, 2],
+        });
+        assert!(!msg.is_wire_aligned());
+    }
+
     #[test]
     fn test_to_someip_message() {
         let msg = SdMessage::find_service(
@@ -524,4 +1054,68 @@ mod tests {
         assert_eq!(endpoints.len(), 1);
         assert_eq!(endpoints[0], endpoint);
     }
+
+    #[test]
+    fn test_describe_entry_options() {
+        let udp = Endpoint::udp("192.168.1.100:30501".parse().unwrap());
+        let tcp = Endpoint::tcp("192.168.1.100:30502".parse().unwrap());
+        let mut entry = ServiceEntry::offer_service(ServiceId(0x1234), InstanceId(0x0001), 1, 0, 3);
+        entry.index_first_option = 1;
+        entry.num_options_1 = 1;
+        entry.index_second_option = 0;
+        entry.num_options_2 = 1;
+
+        let mut msg = SdMessage::new();
+        msg.entries.push(SdEntry::Service(entry.clone()));
+        msg.options.push(udp.to_option());
+        msg.options.push(tcp.to_option());
+
+        let entry = SdEntry::Service(entry);
+        let described = msg.describe_entry_options(&entry);
+        assert_eq!(
+            described,
+            vec![
+                (OptionRun::First, &msg.options[1]),
+                (OptionRun::Second, &msg.options[0]),
+            ]
+        );
+
+        // Runs pointing past the option array are truncated
+        let mut out_of_range = SdMessage::new();
+        out_of_range.options.push(udp.to_option());
+        assert_eq!(
+            out_of_range.describe_entry_options(&entry),
+            vec![(OptionRun::Second, &out_of_range.options[0])]
+        );
+    }
+
+    #[test]
+    fn test_offer_service_dual_endpoint_roundtrip() {
+        let unicast = Endpoint::udp("192.168.1.100:30501".parse().unwrap());
+        let multicast = Endpoint::udp("239.1.1.1:30502".parse().unwrap());
+        let msg = SdMessage::offer_service_dual_endpoint(
+            ServiceId(0x1234),
+            InstanceId(0x0001),
+            1,
+            0,
+            3600,
+            unicast.clone(),
+            multicast.clone(),
+        );
+
+        let bytes = msg.to_bytes();
+        let options = &bytes[8 + SD_ENTRY_SIZE + 4..];
+        assert_eq!(options[2], OptionType::IPv4Endpoint as u8);
+        assert_eq!(options[12 + 2], OptionType::IPv4Multicast as u8);
+
+        let parsed = SdMessage::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, msg);
+
+        let entry = &parsed.entries[0];
+        assert_eq!(entry.option_runs(), (0, 1, 1, 1));
+        assert_eq!(
+            parsed.get_options_for_entry(entry),
+            vec![&unicast.to_option(), &multicast.to_multicast_option()]
+        );
+    }
 }
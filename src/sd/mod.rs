@@ -26,15 +26,23 @@ mod client;
 mod entry;
 mod message;
 mod option;
+mod rate_limit;
 mod server;
+mod subscription;
+#[cfg(test)]
+mod test_util;
 mod types;
 
-pub use client::{SdClient, SdClientConfig, SdEvent, ServiceInfo};
+pub use client::{SdClient, SdClientConfig, SdEvent, ServiceInfo, ServiceTarget};
 pub use entry::{EventgroupEntry, SdEntry, ServiceEntry};
-pub use message::{SdFlags, SdMessage};
-pub use option::{ConfigurationOption, Endpoint, IPv4EndpointOption, IPv6EndpointOption, SdOption};
-pub use server::{OfferedService, SdRequest, SdServer};
+pub use message::{OptionRun, SdFlags, SdMessage, SdMessageBuilder, SdParseOptions};
+pub use option::{
+    ConfigurationOption, Endpoint, IPv4EndpointOption, IPv6EndpointOption, LoadBalancingOption,
+    SdOption,
+};
+pub use server::{OfferedService, SdRequest, SdServer, ServiceStatus};
+pub use subscription::EventSubscription;
 pub use types::{
     EntryType, EventgroupId, InstanceId, OptionType, TransportProtocol, SD_DEFAULT_PORT,
-    SD_ENTRY_SIZE, SD_METHOD_ID, SD_MULTICAST_ADDR, SD_SERVICE_ID,
+    SD_ENTRY_SIZE, SD_MAX_OPTIONS_PER_RUN, SD_METHOD_ID, SD_MULTICAST_ADDR, SD_SERVICE_ID,
 };
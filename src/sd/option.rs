@@ -1,11 +1,15 @@
 //! SOME/IP-SD option types.
 
+use std::io;
 use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
 
 use crate::error::{Result, SomeIpError};
 
 use super::types::{OptionType, TransportProtocol, SD_OPTION_HEADER_SIZE};
 
+/// Discardable flag bit in the option header's reserved byte.
+const DISCARDABLE_FLAG: u8 = 0x80;
+
 /// IPv4 endpoint option.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct IPv4EndpointOption {
@@ -15,11 +19,16 @@ pub struct IPv4EndpointOption {
     pub protocol: TransportProtocol,
     /// Port number.
     pub port: u16,
+    /// Discardable flag from the option header.
+    pub discardable: bool,
 }
 
 impl IPv4EndpointOption {
     /// Size of an IPv4 endpoint option (excluding header).
-    pub const DATA_SIZE: usize = 9;
+    ///
+    /// The option's length field is `DATA_SIZE + 1`, as it also counts the
+    /// reserved byte after the type.
+    pub const DATA_SIZE: usize = 8;
 
     /// Create a new IPv4 endpoint option.
     pub fn new(address: Ipv4Addr, protocol: TransportProtocol, port: u16) -> Self {
@@ -27,6 +36,7 @@ impl IPv4EndpointOption {
             address,
             protocol,
             port,
+            discardable: false,
         }
     }
 
@@ -36,6 +46,7 @@ impl IPv4EndpointOption {
             address: *addr.ip(),
             protocol,
             port: addr.port(),
+            discardable: false,
         }
     }
 
@@ -44,25 +55,36 @@ impl IPv4EndpointOption {
         SocketAddrV4::new(self.address, self.port)
     }
 
+    /// Convert to an endpoint.
+    pub fn to_endpoint(&self) -> Endpoint {
+        Endpoint::new(SocketAddr::V4(self.to_socket_addr()), self.protocol)
+    }
+
     /// Parse from bytes (excluding the option header).
     pub fn from_bytes(data: &[u8]) -> Result<Self> {
         if data.len() < Self::DATA_SIZE {
-            return Err(SomeIpError::MessageTooShort {
-                expected: Self::DATA_SIZE,
-                actual: data.len(),
-            });
+            return Err(SomeIpError::sd_parse(
+                format!(
+                    "endpoint too short: expected {} bytes, got {}",
+                    Self::DATA_SIZE,
+                    data.len()
+                ),
+                0,
+            ));
         }
 
         let address = Ipv4Addr::new(data[0], data[1], data[2], data[3]);
         // data[4] is reserved
-        let protocol = TransportProtocol::from_u8(data[5])
-            .ok_or_else(|| SomeIpError::invalid_header(format!("Unknown protocol: 0x{:02X}", data[5])))?;
+        let protocol = TransportProtocol::from_u8(data[5]).ok_or_else(|| {
+            SomeIpError::sd_parse(format!("unknown protocol 0x{:02X}", data[5]), 5)
+        })?;
         let port = u16::from_be_bytes([data[6], data[7]]);
 
         Ok(Self {
             address,
             protocol,
             port,
+            discardable: false,
         })
     }
 
@@ -77,7 +99,6 @@ impl IPv4EndpointOption {
         buf[4] = 0; // Reserved
         buf[5] = self.protocol as u8;
         buf[6..8].copy_from_slice(&self.port.to_be_bytes());
-        buf[8] = 0; // Padding for alignment
         buf
     }
 }
@@ -91,11 +112,20 @@ pub struct IPv6EndpointOption {
     pub protocol: TransportProtocol,
     /// Port number.
     pub port: u16,
+    /// Discardable flag from the option header.
+    pub discardable: bool,
+    /// Interface scope ID for link-local addresses.
+    ///
+    /// Not part of the wire format; parsed options have 0.
+    pub scope_id: u32,
 }
 
 impl IPv6EndpointOption {
     /// Size of an IPv6 endpoint option (excluding header).
-    pub const DATA_SIZE: usize = 21;
+    ///
+    /// The option's length field is `DATA_SIZE + 1`, as it also counts the
+    /// reserved byte after the type.
+    pub const DATA_SIZE: usize = 20;
 
     /// Create a new IPv6 endpoint option.
     pub fn new(address: Ipv6Addr, protocol: TransportProtocol, port: u16) -> Self {
@@ -103,44 +133,66 @@ impl IPv6EndpointOption {
             address,
             protocol,
             port,
+            discardable: false,
+            scope_id: 0,
         }
     }
 
+    /// Set the interface scope ID.
+    pub fn with_scope_id(mut self, scope_id: u32) -> Self {
+        self.scope_id = scope_id;
+        self
+    }
+
     /// Create from a socket address.
     pub fn from_socket_addr(addr: SocketAddrV6, protocol: TransportProtocol) -> Self {
         Self {
             address: *addr.ip(),
             protocol,
             port: addr.port(),
+            discardable: false,
+            scope_id: addr.scope_id(),
         }
     }
 
     /// Convert to a socket address.
     pub fn to_socket_addr(&self) -> SocketAddrV6 {
-        SocketAddrV6::new(self.address, self.port, 0, 0)
+        SocketAddrV6::new(self.address, self.port, 0, self.scope_id)
+    }
+
+    /// Convert to an endpoint.
+    pub fn to_endpoint(&self) -> Endpoint {
+        Endpoint::new(SocketAddr::V6(self.to_socket_addr()), self.protocol)
     }
 
     /// Parse from bytes (excluding the option header).
     pub fn from_bytes(data: &[u8]) -> Result<Self> {
         if data.len() < Self::DATA_SIZE {
-            return Err(SomeIpError::MessageTooShort {
-                expected: Self::DATA_SIZE,
-                actual: data.len(),
-            });
+            return Err(SomeIpError::sd_parse(
+                format!(
+                    "endpoint too short: expected {} bytes, got {}",
+                    Self::DATA_SIZE,
+                    data.len()
+                ),
+                0,
+            ));
         }
 
         let mut addr_bytes = [0u8; 16];
         addr_bytes.copy_from_slice(&data[0..16]);
         let address = Ipv6Addr::from(addr_bytes);
         // data[16] is reserved
-        let protocol = TransportProtocol::from_u8(data[17])
-            .ok_or_else(|| SomeIpError::invalid_header(format!("Unknown protocol: 0x{:02X}", data[17])))?;
+        let protocol = TransportProtocol::from_u8(data[17]).ok_or_else(|| {
+            SomeIpError::sd_parse(format!("unknown protocol 0x{:02X}", data[17]), 17)
+        })?;
         let port = u16::from_be_bytes([data[18], data[19]]);
 
         Ok(Self {
             address,
             protocol,
             port,
+            discardable: false,
+            scope_id: 0,
         })
     }
 
@@ -151,7 +203,6 @@ impl IPv6EndpointOption {
         buf[16] = 0; // Reserved
         buf[17] = self.protocol as u8;
         buf[18..20].copy_from_slice(&self.port.to_be_bytes());
-        buf[20] = 0; // Padding
         buf
     }
 }
@@ -161,6 +212,8 @@ impl IPv6EndpointOption {
 pub struct ConfigurationOption {
     /// Configuration string.
     pub config_string: String,
+    /// Discardable flag from the option header.
+    pub discardable: bool,
 }
 
 impl ConfigurationOption {
@@ -168,19 +221,148 @@ impl ConfigurationOption {
     pub fn new(config_string: impl Into<String>) -> Self {
         Self {
             config_string: config_string.into(),
+            discardable: false,
+        }
+    }
+
+    /// Create a configuration option from key/value pairs.
+    ///
+    /// Each `key=value` item is prefixed with its length and the list ends
+    /// with a zero byte. Although the length byte could count up to 255, items
+    /// are limited to 127 bytes: `config_string` is a `String`, and a length
+    /// byte of 128 or more is not valid UTF-8 on its own. Longer items return
+    /// an [`io::ErrorKind::InvalidInput`] error.
+    pub fn from_pairs(pairs: &[(String, String)]) -> Result<Self> {
+        let mut config_string = String::new();
+        for (key, value) in pairs {
+            let item = format!("{}={}", key, value);
+            if item.len() > 0x7F {
+                return Err(SomeIpError::io(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Configuration item too long: {} bytes", item.len()),
+                )));
+            }
+            config_string.push(char::from(item.len() as u8));
+            config_string.push_str(&item);
+        }
+        config_string.push('\0');
+        Ok(Self::new(config_string))
+    }
+
+    /// Decode the key/value pairs of a length-prefixed configuration string.
+    ///
+    /// Items without `=` get an empty value; decoding stops at a zero length.
+    pub fn pairs(&self) -> Vec<(String, String)> {
+        let bytes = self.config_string.as_bytes();
+        let mut pairs = Vec::new();
+        let mut pos = 0;
+
+        while let Some(&len) = bytes.get(pos) {
+            let len = len as usize;
+            let Some(item) = bytes.get(pos + 1..pos + 1 + len).filter(|_| len > 0) else {
+                break;
+            };
+            let item = String::from_utf8_lossy(item);
+            let (key, value) = item.split_once('=').unwrap_or((&item, ""));
+            pairs.push((key.to_string(), value.to_string()));
+            pos += 1 + len;
         }
+
+        pairs
     }
 
     /// Parse from bytes (excluding the option header).
+    ///
+    /// Alignment padding after the terminating zero byte is stripped; the
+    /// terminator itself is kept. Strings that do not follow the
+    /// length-prefixed layout lose all trailing zero bytes instead.
     pub fn from_bytes(data: &[u8]) -> Result<Self> {
-        let config_string = String::from_utf8(data.to_vec())
-            .map_err(|_| SomeIpError::invalid_header("Invalid UTF-8 in configuration string"))?;
-        Ok(Self { config_string })
+        let data = &data[..Self::unpadded_len(data)];
+        let config_string = String::from_utf8(data.to_vec()).map_err(|e| {
+            SomeIpError::sd_parse(
+                "invalid UTF-8 in configuration string",
+                e.utf8_error().valid_up_to(),
+            )
+        })?;
+        Ok(Self {
+            config_string,
+            discardable: false,
+        })
     }
 
     /// Serialize to bytes (excluding the option header).
+    ///
+    /// Zero bytes are appended so the whole option ends on a 4-byte boundary.
     pub fn to_bytes(&self) -> Vec<u8> {
-        self.config_string.as_bytes().to_vec()
+        let mut data = self.config_string.as_bytes().to_vec();
+        let padding = (4 - (SD_OPTION_HEADER_SIZE + data.len()) % 4) % 4;
+        data.resize(data.len() + padding, 0);
+        data
+    }
+
+    /// Length of the configuration string in `data`, without padding.
+    fn unpadded_len(data: &[u8]) -> usize {
+        let mut pos = 0;
+        while let Some(&len) = data.get(pos) {
+            if len == 0 {
+                return pos + 1;
+            }
+            pos += 1 + len as usize;
+        }
+        data.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1)
+    }
+}
+
+/// Load balancing option, used to choose between service instances.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadBalancingOption {
+    /// Priority; lower values are preferred.
+    pub priority: u16,
+    /// Weight for choosing among instances of equal priority.
+    pub weight: u16,
+    /// Discardable flag from the option header.
+    pub discardable: bool,
+}
+
+impl LoadBalancingOption {
+    /// Size of a load balancing option (excluding header).
+    pub const DATA_SIZE: usize = 4;
+
+    /// Create a new load balancing option.
+    pub fn new(priority: u16, weight: u16) -> Self {
+        Self {
+            priority,
+            weight,
+            discardable: false,
+        }
+    }
+
+    /// Parse from bytes (excluding the option header).
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < Self::DATA_SIZE {
+            return Err(SomeIpError::sd_parse(
+                format!(
+                    "load balancing option too short: expected {} bytes, got {}",
+                    Self::DATA_SIZE,
+                    data.len()
+                ),
+                0,
+            ));
+        }
+
+        Ok(Self {
+            priority: u16::from_be_bytes([data[0], data[1]]),
+            weight: u16::from_be_bytes([data[2], data[3]]),
+            discardable: false,
+        })
+    }
+
+    /// Serialize to bytes (excluding the option header).
+    pub fn to_bytes(&self) -> [u8; Self::DATA_SIZE] {
+        let mut buf = [0u8; Self::DATA_SIZE];
+        buf[0..2].copy_from_slice(&self.priority.to_be_bytes());
+        buf[2..4].copy_from_slice(&self.weight.to_be_bytes());
+        buf
     }
 }
 
@@ -197,55 +379,100 @@ pub enum SdOption {
     IPv6Multicast(IPv6EndpointOption),
     /// Configuration string option.
     Configuration(ConfigurationOption),
+    /// Load balancing option.
+    LoadBalancing(LoadBalancingOption),
     /// Unknown option (preserved for round-tripping).
-    Unknown { option_type: u8, data: Vec<u8> },
+    Unknown {
+        option_type: u8,
+        discardable: bool,
+        data: Vec<u8>,
+    },
 }
 
 impl SdOption {
     /// Parse an option from bytes (including the header).
     pub fn from_bytes(data: &[u8]) -> Result<(Self, usize)> {
         if data.len() < SD_OPTION_HEADER_SIZE {
-            return Err(SomeIpError::MessageTooShort {
-                expected: SD_OPTION_HEADER_SIZE,
-                actual: data.len(),
-            });
+            return Err(SomeIpError::sd_parse(
+                format!(
+                    "option header too short: expected {} bytes, got {}",
+                    SD_OPTION_HEADER_SIZE,
+                    data.len()
+                ),
+                0,
+            ));
         }
 
+        // The length covers everything after the type field, including data[3]
         let length = u16::from_be_bytes([data[0], data[1]]) as usize;
         let option_type_byte = data[2];
-        // data[3] is reserved
+        // data[3]: discardable flag (bit 7) + reserved
+        let discardable = data[3] & DISCARDABLE_FLAG != 0;
 
-        let total_size = SD_OPTION_HEADER_SIZE + length;
+        if length == 0 {
+            return Err(SomeIpError::sd_parse("option length 0 omits the reserved byte", 0));
+        }
+        let total_size = SD_OPTION_HEADER_SIZE - 1 + length;
         if data.len() < total_size {
-            return Err(SomeIpError::MessageTooShort {
-                expected: total_size,
-                actual: data.len(),
-            });
+            return Err(SomeIpError::sd_parse(
+                format!("option length {} exceeds remaining {} bytes", length, data.len()),
+                0,
+            ));
         }
 
         let option_data = &data[SD_OPTION_HEADER_SIZE..total_size];
 
-        let option = match OptionType::from_u8(option_type_byte) {
+        // Typed endpoint options have a fixed size
+        let expected_length = match OptionType::from_u8(option_type_byte) {
+            Some(OptionType::IPv4Endpoint | OptionType::IPv4Multicast) => {
+                Some(IPv4EndpointOption::DATA_SIZE + 1)
+            }
+            Some(OptionType::IPv6Endpoint | OptionType::IPv6Multicast) => {
+                Some(IPv6EndpointOption::DATA_SIZE + 1)
+            }
+            Some(OptionType::LoadBalancing) => Some(LoadBalancingOption::DATA_SIZE + 1),
+            _ => None,
+        };
+        if let Some(expected) = expected_length {
+            if length != expected {
+                return Err(SomeIpError::sd_parse(
+                    format!(
+                        "option type 0x{:02X} has length {}, expected {}",
+                        option_type_byte, length, expected
+                    ),
+                    0,
+                ));
+            }
+        }
+
+        let parsed = match OptionType::from_u8(option_type_byte) {
             Some(OptionType::IPv4Endpoint) => {
-                SdOption::IPv4Endpoint(IPv4EndpointOption::from_bytes(option_data)?)
+                IPv4EndpointOption::from_bytes(option_data).map(SdOption::IPv4Endpoint)
             }
             Some(OptionType::IPv6Endpoint) => {
-                SdOption::IPv6Endpoint(IPv6EndpointOption::from_bytes(option_data)?)
+                IPv6EndpointOption::from_bytes(option_data).map(SdOption::IPv6Endpoint)
             }
             Some(OptionType::IPv4Multicast) => {
-                SdOption::IPv4Multicast(IPv4EndpointOption::from_bytes(option_data)?)
+                IPv4EndpointOption::from_bytes(option_data).map(SdOption::IPv4Multicast)
             }
             Some(OptionType::IPv6Multicast) => {
-                SdOption::IPv6Multicast(IPv6EndpointOption::from_bytes(option_data)?)
+                IPv6EndpointOption::from_bytes(option_data).map(SdOption::IPv6Multicast)
             }
             Some(OptionType::Configuration) => {
-                SdOption::Configuration(ConfigurationOption::from_bytes(option_data)?)
+                ConfigurationOption::from_bytes(option_data).map(SdOption::Configuration)
             }
-            _ => SdOption::Unknown {
+            Some(OptionType::LoadBalancing) => {
+                LoadBalancingOption::from_bytes(option_data).map(SdOption::LoadBalancing)
+            }
+            _ => Ok(SdOption::Unknown {
                 option_type: option_type_byte,
+                discardable,
                 data: option_data.to_vec(),
-            },
+            }),
         };
+        // Offsets of the option data are relative to the end of the header
+        let mut option = parsed.map_err(|e| e.shift_sd_offset(SD_OPTION_HEADER_SIZE))?;
+        option.set_discardable(discardable);
 
         Ok((option, total_size))
     }
@@ -258,14 +485,19 @@ impl SdOption {
             SdOption::IPv4Multicast(opt) => (OptionType::IPv4Multicast as u8, opt.to_bytes().to_vec()),
             SdOption::IPv6Multicast(opt) => (OptionType::IPv6Multicast as u8, opt.to_bytes().to_vec()),
             SdOption::Configuration(opt) => (OptionType::Configuration as u8, opt.to_bytes()),
-            SdOption::Unknown { option_type, data } => (*option_type, data.clone()),
+            SdOption::LoadBalancing(opt) => (OptionType::LoadBalancing as u8, opt.to_bytes().to_vec()),
+            SdOption::Unknown { option_type, data, .. } => (*option_type, data.clone()),
         };
 
-        let length = data.len() as u16;
+        let length = (data.len() + 1) as u16;
         let mut buf = Vec::with_capacity(SD_OPTION_HEADER_SIZE + data.len());
         buf.extend_from_slice(&length.to_be_bytes());
         buf.push(option_type);
-        buf.push(0); // Reserved
+        buf.push(if self.is_discardable() {
+            DISCARDABLE_FLAG
+        } else {
+            0
+        });
         buf.extend_from_slice(&data);
 
         buf
@@ -279,9 +511,35 @@ impl SdOption {
             SdOption::IPv4Multicast(_) => Some(OptionType::IPv4Multicast),
             SdOption::IPv6Multicast(_) => Some(OptionType::IPv6Multicast),
             SdOption::Configuration(_) => Some(OptionType::Configuration),
+            SdOption::LoadBalancing(_) => Some(OptionType::LoadBalancing),
             SdOption::Unknown { .. } => None,
         }
     }
+
+    /// Check if the discardable flag is set.
+    ///
+    /// A receiver that does not understand a discardable option may ignore it
+    /// instead of rejecting the entry it is attached to.
+    pub fn is_discardable(&self) -> bool {
+        match self {
+            SdOption::IPv4Endpoint(opt) | SdOption::IPv4Multicast(opt) => opt.discardable,
+            SdOption::IPv6Endpoint(opt) | SdOption::IPv6Multicast(opt) => opt.discardable,
+            SdOption::Configuration(opt) => opt.discardable,
+            SdOption::LoadBalancing(opt) => opt.discardable,
+            SdOption::Unknown { discardable, .. } => *discardable,
+        }
+    }
+
+    /// Set the discardable flag.
+    pub fn set_discardable(&mut self, value: bool) {
+        match self {
+            SdOption::IPv4Endpoint(opt) | SdOption::IPv4Multicast(opt) => opt.discardable = value,
+            SdOption::IPv6Endpoint(opt) | SdOption::IPv6Multicast(opt) => opt.discardable = value,
+            SdOption::Configuration(opt) => opt.discardable = value,
+            SdOption::LoadBalancing(opt) => opt.discardable = value,
+            SdOption::Unknown { discardable, .. } => *discardable = value,
+        }
+    }
 }
 
 /// A network endpoint (address + port + protocol).
@@ -299,6 +557,11 @@ impl Endpoint {
         Self { address, protocol }
     }
 
+    /// Create from a socket address and transport protocol.
+    pub fn from_socket_addr(address: SocketAddr, protocol: TransportProtocol) -> Self {
+        Self::new(address, protocol)
+    }
+
     /// Create a TCP endpoint.
     pub fn tcp(address: SocketAddr) -> Self {
         Self::new(address, TransportProtocol::Tcp)
@@ -313,7 +576,12 @@ impl Endpoint {
     pub fn from_str_tcp(addr: &str) -> Result<Self> {
         let socket_addr: SocketAddr = addr
             .parse()
-            .map_err(|_| SomeIpError::invalid_header(format!("Invalid address: {}", addr)))?;
+            .map_err(|_| {
+                SomeIpError::io(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Invalid address: {}", addr),
+                ))
+            })?;
         Ok(Self::tcp(socket_addr))
     }
 
@@ -321,7 +589,12 @@ impl Endpoint {
     pub fn from_str_udp(addr: &str) -> Result<Self> {
         let socket_addr: SocketAddr = addr
             .parse()
-            .map_err(|_| SomeIpError::invalid_header(format!("Invalid address: {}", addr)))?;
+            .map_err(|_| {
+                SomeIpError::io(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Invalid address: {}", addr),
+                ))
+            })?;
         Ok(Self::udp(socket_addr))
     }
 
@@ -339,17 +612,25 @@ impl Endpoint {
         }
     }
 
+    /// Convert to an SD multicast option (type 0x14 or 0x16).
+    pub fn to_multicast_option(&self) -> SdOption {
+        match self.address {
+            SocketAddr::V4(addr) => SdOption::IPv4Multicast(IPv4EndpointOption::from_socket_addr(
+                addr,
+                self.protocol,
+            )),
+            SocketAddr::V6(addr) => SdOption::IPv6Multicast(IPv6EndpointOption::from_socket_addr(
+                addr,
+                self.protocol,
+            )),
+        }
+    }
+
     /// Create from an SD option.
     pub fn from_option(option: &SdOption) -> Option<Self> {
         match option {
-            SdOption::IPv4Endpoint(opt) => Some(Self {
-                address: SocketAddr::V4(opt.to_socket_addr()),
-                protocol: opt.protocol,
-            }),
-            SdOption::IPv6Endpoint(opt) => Some(Self {
-                address: SocketAddr::V6(opt.to_socket_addr()),
-                protocol: opt.protocol,
-            }),
+            SdOption::IPv4Endpoint(opt) => Some(opt.to_endpoint()),
+            SdOption::IPv6Endpoint(opt) => Some(opt.to_endpoint()),
             _ => None,
         }
     }
@@ -397,6 +678,21 @@ mod tests {
         assert_eq!(opt, parsed);
     }
 
+    #[test]
+    fn test_ipv6_endpoint_scope_id() {
+        let address = Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+        let opt = IPv6EndpointOption::new(address, TransportProtocol::Udp, 30490).with_scope_id(3);
+
+        let addr = opt.to_socket_addr();
+        assert_eq!(addr.scope_id(), 3);
+        assert_eq!(*addr.ip(), address);
+        assert_eq!(IPv6EndpointOption::from_socket_addr(addr, TransportProtocol::Udp), opt);
+
+        // The scope ID is not on the wire
+        let parsed = IPv6EndpointOption::from_bytes(&opt.to_bytes()).unwrap();
+        assert_eq!(parsed.scope_id, 0);
+    }
+
     #[test]
     fn test_sd_option_roundtrip() {
         let opt = SdOption::IPv4Endpoint(IPv4EndpointOption::new(
@@ -412,6 +708,68 @@ mod tests {
         assert_eq!(size, bytes.len());
     }
 
+    #[test]
+    fn test_sd_option_wrong_endpoint_length() {
+        let opt = SdOption::IPv4Endpoint(IPv4EndpointOption::new(
+            Ipv4Addr::new(192, 168, 1, 100),
+            TransportProtocol::Tcp,
+            30490,
+        ));
+
+        // Declare a length of 12 and append 3 trailing bytes
+        let mut bytes = opt.to_bytes();
+        bytes[0..2].copy_from_slice(&12u16.to_be_bytes());
+        bytes.extend_from_slice(&[0, 0, 0]);
+        assert!(matches!(
+            SdOption::from_bytes(&bytes),
+            Err(SomeIpError::SdParse { offset: 0, .. })
+        ));
+
+        // Unknown option types stay lenient
+        let unknown = SdOption::Unknown {
+            option_type: 0x77,
+            discardable: true,
+            data: vec![1, 2, 3],
+        };
+        let (parsed, _) = SdOption::from_bytes(&unknown.to_bytes()).unwrap();
+        assert_eq!(parsed, unknown);
+    }
+
+    #[test]
+    fn test_sd_option_discardable_roundtrip() {
+        let mut opt = SdOption::IPv4Endpoint(IPv4EndpointOption::new(
+            Ipv4Addr::new(192, 168, 1, 100),
+            TransportProtocol::Udp,
+            30501,
+        ));
+        opt.set_discardable(true);
+
+        let bytes = opt.to_bytes();
+        assert_eq!(bytes[3], 0x80);
+
+        let (parsed, _) = SdOption::from_bytes(&bytes).unwrap();
+        assert!(parsed.is_discardable());
+        assert_eq!(opt, parsed);
+        assert_eq!(parsed.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_endpoint_option_to_endpoint() {
+        let opt = IPv4EndpointOption::new(
+            Ipv4Addr::new(192, 168, 1, 100),
+            TransportProtocol::Udp,
+            30501,
+        );
+        let endpoint = opt.to_endpoint();
+        assert_eq!(endpoint, Endpoint::udp("192.168.1.100:30501".parse().unwrap()));
+        assert_eq!(endpoint.to_option(), SdOption::IPv4Endpoint(opt));
+
+        let opt = IPv6EndpointOption::new(Ipv6Addr::LOCALHOST, TransportProtocol::Tcp, 30490);
+        let addr = "[::1]:30490".parse().unwrap();
+        let endpoint = Endpoint::from_socket_addr(addr, TransportProtocol::Tcp);
+        assert_eq!(opt.to_endpoint(), endpoint);
+    }
+
     #[test]
     fn test_endpoint_display() {
         let endpoint = Endpoint::tcp("192.168.1.100:30490".parse().unwrap());
@@ -428,4 +786,23 @@ mod tests {
         let parsed = ConfigurationOption::from_bytes(&bytes).unwrap();
         assert_eq!(opt, parsed);
     }
+
+    #[test]
+    fn test_configuration_option_pairs() {
+        let pairs = vec![
+            ("client".to_string(), "ecu1".to_string()),
+            ("flag".to_string(), String::new()),
+        ];
+        let opt = ConfigurationOption::from_pairs(&pairs).unwrap();
+        assert_eq!(opt.config_string.as_bytes(), b"\x0bclient=ecu1\x05flag=\0");
+        assert_eq!(opt.pairs(), pairs);
+
+        let parsed = ConfigurationOption::from_bytes(&opt.to_bytes()).unwrap();
+        assert_eq!(parsed, opt);
+        assert_eq!(parsed.pairs(), pairs);
+
+        let long = vec![("k".to_string(), "v".repeat(200))];
+        let err = ConfigurationOption::from_pairs(&long).unwrap_err();
+        assert!(matches!(err, SomeIpError::Io(e) if e.kind() == io::ErrorKind::InvalidInput));
+    }
 }
@@ -0,0 +1,82 @@
+//! Token-bucket rate limiting for SD messages.
+
+use std::time::{Duration, Instant};
+
+/// A token bucket allowing bursts of up to one second's worth of tokens.
+#[derive(Debug, Clone)]
+pub(crate) struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Create a full bucket refilling at `per_second` tokens per second.
+    pub(crate) fn new(per_second: u32) -> Self {
+        let rate = per_second.max(1) as f64;
+        Self {
+            rate,
+            tokens: rate,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Get the tokens available now, including those accrued since the last refill.
+    fn available(&self) -> f64 {
+        let elapsed = self.last_refill.elapsed().as_secs_f64();
+        (self.tokens + elapsed * self.rate).min(self.rate)
+    }
+
+    /// Add tokens for the time elapsed since the last refill.
+    fn refill(&mut self) {
+        self.tokens = self.available();
+        self.last_refill = Instant::now();
+    }
+
+    /// Check if a token is available without consuming it.
+    pub(crate) fn has_token(&self) -> bool {
+        self.available() >= 1.0
+    }
+
+    /// Take a token if one is available.
+    pub(crate) fn try_acquire(&mut self) -> bool {
+        self.refill();
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Get the time until the next token becomes available.
+    pub(crate) fn time_until_available(&mut self) -> Duration {
+        self.refill();
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) / self.rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_limits_burst() {
+        let mut bucket = TokenBucket::new(5);
+        let acquired = (0..100).filter(|_| bucket.try_acquire()).count();
+        assert_eq!(acquired, 5);
+        assert!(bucket.time_until_available() > Duration::ZERO);
+    }
+
+    #[test]
+    fn test_token_bucket_refills() {
+        let mut bucket = TokenBucket::new(100);
+        while bucket.try_acquire() {}
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(bucket.try_acquire());
+    }
+}
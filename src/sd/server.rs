@@ -1,20 +1,37 @@
 //! SOME/IP-SD server for offering services.
 
+use std::collections::hash_map::RandomState;
 use std::collections::HashMap;
+use std::hash::BuildHasher;
 use std::io;
 use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use socket2::SockRef;
+
+use crate::clock::{self, Clock};
 use crate::error::{Result, SomeIpError};
 use crate::header::ServiceId;
 
-use super::entry::SdEntry;
-use super::message::SdMessage;
-use super::option::Endpoint;
+use super::entry::{EventgroupEntry, SdEntry, ServiceEntry};
+use super::message::{check_option_run, SdMessage};
+use super::option::{Endpoint, SdOption};
+use super::rate_limit::TokenBucket;
 use super::types::{
     EntryType, EventgroupId, InstanceId, SD_DEFAULT_PORT, SD_MULTICAST_ADDR,
 };
 
+/// Maximum size of a datagram coalescing offers in [`SdServer::offer_services`].
+///
+/// Leaves room for IPv6 and UDP headers within a typical 1500-byte MTU.
+const MAX_OFFER_DATAGRAM_SIZE: usize = 1400;
+
+/// Maximum FindService answers waiting for their delay.
+///
+/// Further finds are still reported by [`SdServer::poll`] but not answered.
+const MAX_PENDING_RESPONSES: usize = 64;
+
 /// An offered service.
 #[derive(Debug, Clone)]
 pub struct OfferedService {
@@ -26,10 +43,74 @@ pub struct OfferedService {
     pub major_version: u8,
     /// Minor version.
     pub minor_version: u32,
-    /// Endpoint where the service is available.
-    pub endpoint: Endpoint,
+    /// Endpoints where the service is available, e.g. one TCP and one UDP.
+    pub endpoints: Vec<Endpoint>,
+    /// TTL in seconds for offer announcements.
+    pub ttl: u32,
+}
+
+impl OfferedService {
+    /// Create an offered service from a discovered service entry.
+    pub fn from_entry(entry: &ServiceEntry, endpoints: Vec<Endpoint>) -> Self {
+        Self {
+            service_id: entry.service_id,
+            instance_id: entry.instance_id,
+            major_version: entry.major_version,
+            minor_version: entry.minor_version,
+            endpoints,
+            ttl: entry.ttl,
+        }
+    }
+
+    /// Create an OfferService entry for this service, without option references.
+    pub fn to_entry(&self) -> ServiceEntry {
+        ServiceEntry::offer_service(
+            self.service_id,
+            self.instance_id,
+            self.major_version,
+            self.minor_version,
+            self.ttl,
+        )
+    }
+
+    /// Create an OfferService message announcing all endpoints.
+    fn offer_message(&self) -> Result<SdMessage> {
+        SdMessage::offer_service_endpoints(
+            self.service_id,
+            self.instance_id,
+            self.major_version,
+            self.minor_version,
+            self.ttl,
+            &self.endpoints,
+        )
+    }
+
+    /// Get the endpoint options referenced by the offer entry.
+    fn endpoint_options(&self) -> Vec<SdOption> {
+        self.endpoints.iter().map(Endpoint::to_option).collect()
+    }
+
+    /// Check that all endpoints fit into the offer entry's option run.
+    fn check_endpoints(&self) -> Result<()> {
+        check_option_run(self.endpoints.len())
+    }
+}
+
+/// Snapshot of an offered service and its subscribers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceStatus {
+    /// Service ID.
+    pub service_id: ServiceId,
+    /// Instance ID.
+    pub instance_id: InstanceId,
+    /// Major version.
+    pub major_version: u8,
+    /// Minor version.
+    pub minor_version: u32,
     /// TTL in seconds for offer announcements.
     pub ttl: u32,
+    /// Number of active subscribers per eventgroup.
+    pub subscriber_count_by_eventgroup: HashMap<EventgroupId, usize>,
 }
 
 /// A subscription from a client.
@@ -101,10 +182,19 @@ pub struct SdServerConfig {
     pub bind_addr: SocketAddr,
     /// Multicast address for SD.
     pub multicast_addr: SocketAddr,
-    /// Interface address for multicast (None = any).
+    /// Interface address for multicast join and send (None = any).
     pub multicast_interface: Option<Ipv4Addr>,
     /// Interval for cyclic offer announcements.
     pub offer_interval: Duration,
+    /// Maximum cyclic offer messages per second (None = unlimited).
+    pub max_offers_per_second: Option<u32>,
+    /// Range `(min, max)` of the random delay before answering a FindService.
+    pub response_delay: (Duration, Duration),
+    /// Send StopOffer for all offered services when the server is dropped.
+    ///
+    /// Best effort only: send errors are ignored and UDP gives no delivery
+    /// guarantee, so subscribers may still have to wait for TTL expiry.
+    pub stop_offer_on_drop: bool,
 }
 
 impl Default for SdServerConfig {
@@ -114,13 +204,33 @@ impl Default for SdServerConfig {
             multicast_addr: SocketAddr::V4(SocketAddrV4::new(SD_MULTICAST_ADDR, SD_DEFAULT_PORT)),
             multicast_interface: None,
             offer_interval: Duration::from_secs(1),
+            max_offers_per_second: None,
+            response_delay: (Duration::ZERO, Duration::ZERO),
+            stop_offer_on_drop: false,
         }
     }
 }
 
+/// Pick a random delay in `[min, max]`.
+fn random_delay((min, max): (Duration, Duration)) -> Duration {
+    let span = max.saturating_sub(min).as_nanos() as u64;
+    if span == 0 {
+        return min;
+    }
+    let random = RandomState::new().hash_one(Instant::now());
+    min + Duration::from_nanos(random % (span + 1))
+}
+
 /// Key for identifying a subscription.
 type SubscriptionKey = (ServiceId, InstanceId, EventgroupId, SocketAddr);
 
+/// A FindService answer waiting for its delay: due time, answered service,
+/// offer message and destination.
+type PendingResponse = (Instant, (ServiceId, InstanceId), SdMessage, SocketAddr);
+
+/// Callback providing services on demand for finds of unknown services.
+type FindHandler = Box<dyn FnMut(ServiceId, InstanceId) -> Option<OfferedService> + Send>;
+
 /// SOME/IP-SD server for offering services and handling subscriptions.
 pub struct SdServer {
     socket: UdpSocket,
@@ -129,7 +239,15 @@ pub struct SdServer {
     subscriptions: HashMap<SubscriptionKey, Subscription>,
     recv_buffer: Vec<u8>,
     last_offer_time: Option<Instant>,
+    /// Services not yet announced in the current cyclic offer round.
+    deferred_offers: Vec<(ServiceId, InstanceId)>,
     offer_interval: Duration,
+    offer_limiter: Option<TokenBucket>,
+    response_delay: (Duration, Duration),
+    pending_responses: Vec<PendingResponse>,
+    find_handler: Option<FindHandler>,
+    stop_offer_on_drop: bool,
+    clock: Arc<dyn Clock>,
 }
 
 impl SdServer {
@@ -150,6 +268,13 @@ impl SdServer {
                 .map_err(SomeIpError::io)?;
         }
 
+        // Send multicast through the configured interface
+        if let Some(interface) = config.multicast_interface {
+            SockRef::from(&socket)
+                .set_multicast_if_v4(&interface)
+                .map_err(SomeIpError::io)?;
+        }
+
         // Enable sending to multicast
         socket.set_multicast_loop_v4(true).ok();
 
@@ -163,10 +288,23 @@ impl SdServer {
             subscriptions: HashMap::new(),
             recv_buffer: vec![0u8; 65535],
             last_offer_time: None,
+            deferred_offers: Vec::new(),
             offer_interval: config.offer_interval,
+            offer_limiter: config.max_offers_per_second.map(TokenBucket::new),
+            response_delay: config.response_delay,
+            pending_responses: Vec::new(),
+            find_handler: None,
+            stop_offer_on_drop: config.stop_offer_on_drop,
+            clock: clock::system(),
         })
     }
 
+    /// Set the clock used for offer timing, subscription expiry and delayed
+    /// FindService answers.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
     /// Get the local address of the socket.
     pub fn local_addr(&self) -> Result<SocketAddr> {
         self.socket.local_addr().map_err(SomeIpError::io)
@@ -174,19 +312,60 @@ impl SdServer {
 
     /// Start offering a service.
     pub fn offer_service(&mut self, service: OfferedService) -> Result<()> {
+        service.check_endpoints()?;
         let key = (service.service_id, service.instance_id);
         self.offered_services.insert(key, service.clone());
 
         // Send initial offer
-        let msg = SdMessage::offer_service(
-            service.service_id,
-            service.instance_id,
-            service.major_version,
-            service.minor_version,
-            service.ttl,
-            service.endpoint,
-        );
-        self.send_multicast(&msg)
+        self.send_multicast(&service.offer_message()?)
+    }
+
+    /// Offer several services, announcing them in as few datagrams as possible.
+    ///
+    /// Offers are coalesced into SD messages of at most 1400 bytes. All
+    /// services are registered before the first message is sent, so if a send
+    /// fails they still count as offered and the next cyclic round announces
+    /// them.
+    pub fn offer_services(&mut self, services: Vec<OfferedService>) -> Result<()> {
+        let mut messages = Vec::new();
+        let mut builder = SdMessage::builder();
+        let mut entries = 0;
+        for service in &services {
+            service.check_endpoints()?;
+            let (entry, options) = (service.to_entry(), service.endpoint_options());
+            let grown = builder.clone().add_service_entry(entry.clone(), &options)?;
+            let size = grown.clone().build().to_datagram_bytes().len();
+            if entries > 0 && size > MAX_OFFER_DATAGRAM_SIZE {
+                messages.push(builder.build());
+                builder = SdMessage::builder().add_service_entry(entry, &options)?;
+                entries = 1;
+            } else {
+                builder = grown;
+                entries += 1;
+            }
+        }
+        if entries > 0 {
+            messages.push(builder.build());
+        }
+
+        for service in services {
+            self.offered_services.insert((service.service_id, service.instance_id), service);
+        }
+        for msg in &messages {
+            self.send_multicast(msg)?;
+        }
+        Ok(())
+    }
+
+    /// Set a handler for finds of services that are not offered.
+    ///
+    /// If the handler returns a service, it is registered as offered and the
+    /// find is answered, which allows creating services lazily.
+    pub fn set_find_handler(
+        &mut self,
+        handler: impl FnMut(ServiceId, InstanceId) -> Option<OfferedService> + Send + 'static,
+    ) {
+        self.find_handler = Some(Box::new(handler));
     }
 
     /// Stop offering a service.
@@ -214,32 +393,77 @@ impl SdServer {
         self.offered_services.values()
     }
 
+    /// Get a snapshot of offered services and their active subscriber counts.
+    pub fn status(&self) -> Vec<ServiceStatus> {
+        let now = self.clock.now();
+        self.offered_services
+            .values()
+            .map(|service| {
+                let mut subscriber_count_by_eventgroup = HashMap::new();
+                for ((sid, iid, egid, _), sub) in &self.subscriptions {
+                    if *sid == service.service_id
+                        && *iid == service.instance_id
+                        && now < sub.expires_at
+                    {
+                        *subscriber_count_by_eventgroup.entry(*egid).or_insert(0) += 1;
+                    }
+                }
+
+                ServiceStatus {
+                    service_id: service.service_id,
+                    instance_id: service.instance_id,
+                    major_version: service.major_version,
+                    minor_version: service.minor_version,
+                    ttl: service.ttl,
+                    subscriber_count_by_eventgroup,
+                }
+            })
+            .collect()
+    }
+
     /// Send cyclic offer announcements for all services.
+    ///
+    /// If `max_offers_per_second` is set, offers exceeding the rate are
+    /// deferred and the round is completed by a later call.
     pub fn send_offers(&mut self) -> Result<()> {
-        for service in self.offered_services.values() {
-            let msg = SdMessage::offer_service(
-                service.service_id,
-                service.instance_id,
-                service.major_version,
-                service.minor_version,
-                service.ttl,
-                service.endpoint.clone(),
-            );
-            self.send_multicast(&msg)?;
+        if self.deferred_offers.is_empty() {
+            let mut keys: Vec<_> = self.offered_services.keys().copied().collect();
+            // Announce in ascending order by popping from the end
+            keys.sort_by_key(|(service_id, instance_id)| (service_id.0, instance_id.0));
+            keys.reverse();
+            self.deferred_offers = keys;
         }
-        self.last_offer_time = Some(Instant::now());
+
+        while let Some(&key) = self.deferred_offers.last() {
+            let Some(service) = self.offered_services.get(&key) else {
+                // Stopped offering since the round started
+                self.deferred_offers.pop();
+                continue;
+            };
+            if let Some(limiter) = &mut self.offer_limiter {
+                if !limiter.try_acquire() {
+                    return Ok(());
+                }
+            }
+
+            self.send_multicast(&service.offer_message()?)?;
+            self.deferred_offers.pop();
+        }
+        self.last_offer_time = Some(self.clock.now());
         Ok(())
     }
 
     /// Check if it's time to send cyclic offers.
     pub fn should_send_offers(&self) -> bool {
-        match self.last_offer_time {
-            Some(last) => Instant::now().duration_since(last) >= self.offer_interval,
+        let due = match self.last_offer_time {
+            Some(last) => self.clock.now().duration_since(last) >= self.offer_interval,
             None => true,
-        }
+        };
+        due && self.offer_limiter.as_ref().is_none_or(|limiter| limiter.has_token())
     }
 
     /// Accept a subscription request.
+    #[allow(clippy::too_many_arguments)]
     pub fn accept_subscription(
         &mut self,
         service_id: ServiceId,
@@ -251,30 +475,21 @@ impl SdServer {
         ttl: u32,
         multicast_endpoint: Option<Endpoint>,
     ) -> Result<()> {
-        // Store subscription
-        let key = (service_id, instance_id, eventgroup_id, client_addr);
-        self.subscriptions.insert(
-            key,
-            Subscription {
-                client_addr,
-                client_endpoint,
-                counter,
-                expires_at: Instant::now() + Duration::from_secs(ttl as u64),
-            },
+        self.record_subscription(
+            service_id,
+            instance_id,
+            eventgroup_id,
+            counter,
+            client_addr,
+            client_endpoint,
+            ttl,
         );
 
-        // Get major version from offered service
-        let major_version = self
-            .offered_services
-            .get(&(service_id, instance_id))
-            .map(|s| s.major_version)
-            .unwrap_or(0xFF);
-
         // Send ACK
         let msg = SdMessage::subscribe_eventgroup_ack(
             service_id,
             instance_id,
-            major_version,
+            self.offered_major_version(service_id, instance_id),
             eventgroup_id,
             ttl,
             counter,
@@ -283,6 +498,69 @@ impl SdServer {
         self.send_to(&msg, client_addr)
     }
 
+    /// Accept several subscriptions from one client with a single ACK message.
+    ///
+    /// Each entry is `(service, instance, eventgroup, counter, client endpoint, ttl)`.
+    pub fn accept_subscriptions(
+        &mut self,
+        acks: &[(ServiceId, InstanceId, EventgroupId, u8, Endpoint, u32)],
+        client_addr: SocketAddr,
+    ) -> Result<()> {
+        let mut msg = SdMessage::new();
+        for (service_id, instance_id, eventgroup_id, counter, client_endpoint, ttl) in acks {
+            self.record_subscription(
+                *service_id,
+                *instance_id,
+                *eventgroup_id,
+                *counter,
+                client_addr,
+                client_endpoint.clone(),
+                *ttl,
+            );
+            msg.entries.push(SdEntry::Eventgroup(EventgroupEntry::subscribe_ack(
+                *service_id,
+                *instance_id,
+                self.offered_major_version(*service_id, *instance_id),
+                *eventgroup_id,
+                *ttl,
+                *counter,
+            )));
+        }
+        self.send_to(&msg, client_addr)
+    }
+
+    /// Store a subscription.
+    #[allow(clippy::too_many_arguments)]
+    fn record_subscription(
+        &mut self,
+        service_id: ServiceId,
+        instance_id: InstanceId,
+        eventgroup_id: EventgroupId,
+        counter: u8,
+        client_addr: SocketAddr,
+        client_endpoint: Endpoint,
+        ttl: u32,
+    ) {
+        let key = (service_id, instance_id, eventgroup_id, client_addr);
+        self.subscriptions.insert(
+            key,
+            Subscription {
+                client_addr,
+                client_endpoint,
+                counter,
+                expires_at: self.clock.now() + Duration::from_secs(ttl as u64),
+            },
+        );
+    }
+
+    /// Get the major version of an offered service, or 0xFF if not offered.
+    fn offered_major_version(&self, service_id: ServiceId, instance_id: InstanceId) -> u8 {
+        self.offered_services
+            .get(&(service_id, instance_id))
+            .map(|s| s.major_version)
+            .unwrap_or(0xFF)
+    }
+
     /// Reject a subscription request.
     pub fn reject_subscription(
         &mut self,
@@ -317,13 +595,14 @@ impl SdServer {
         instance_id: InstanceId,
         eventgroup_id: EventgroupId,
     ) -> Vec<&Endpoint> {
+        let now = self.clock.now();
         self.subscriptions
             .iter()
             .filter(|((sid, iid, egid, _), sub)| {
                 *sid == service_id
                     && *iid == instance_id
                     && *egid == eventgroup_id
-                    && Instant::now() < sub.expires_at
+                    && now < sub.expires_at
             })
             .map(|(_, sub)| &sub.client_endpoint)
             .collect()
@@ -331,10 +610,11 @@ impl SdServer {
 
     /// Remove expired subscriptions.
     pub fn cleanup_expired(&mut self) -> Vec<SubscriptionKey> {
+        let now = self.clock.now();
         let expired: Vec<_> = self
             .subscriptions
             .iter()
-            .filter(|(_, sub)| Instant::now() >= sub.expires_at)
+            .filter(|(_, sub)| now >= sub.expires_at)
             .map(|(key, _)| *key)
             .collect();
 
@@ -346,16 +626,50 @@ impl SdServer {
     }
 
     /// Poll for incoming SD requests (non-blocking).
+    ///
+    /// Also sends queued FindService answers whose delay has elapsed, so call
+    /// this regularly.
     pub fn poll(&mut self) -> Result<Option<SdRequest>> {
-        match self.socket.recv_from(&mut self.recv_buffer) {
+        let request = match self.socket.recv_from(&mut self.recv_buffer) {
             Ok((size, src_addr)) => {
                 // Copy data to avoid borrow issues
                 let data = self.recv_buffer[..size].to_vec();
-                self.process_message(&data, src_addr)
+                self.process_message(&data, src_addr)?
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => None,
+            Err(e) => return Err(SomeIpError::io(e)),
+        };
+        self.send_due_responses()?;
+        Ok(request)
+    }
+
+    /// Send queued FindService answers whose delay has elapsed.
+    ///
+    /// Returns the number of answers sent. Every due answer is attempted and
+    /// removed from the queue; if any send fails, the first error is returned
+    /// once all were tried.
+    pub fn send_due_responses(&mut self) -> Result<usize> {
+        let now = self.clock.now();
+        let (due, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending_responses)
+            .into_iter()
+            .partition(|(due_at, _, _, _)| *due_at <= now);
+        self.pending_responses = pending;
+
+        let mut first_error = None;
+        for (_, _, msg, addr) in &due {
+            if let Err(e) = self.send_to(msg, *addr) {
+                first_error.get_or_insert(e);
             }
-            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Ok(None),
-            Err(e) => Err(SomeIpError::io(e)),
         }
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(due.len()),
+        }
+    }
+
+    /// Get the number of FindService answers waiting for their delay.
+    pub fn pending_responses(&self) -> usize {
+        self.pending_responses.len()
     }
 
     /// Send a message to the multicast address.
@@ -365,25 +679,16 @@ impl SdServer {
 
     /// Send a message to a specific address.
     fn send_to(&self, msg: &SdMessage, addr: SocketAddr) -> Result<()> {
-        let someip_msg = msg.to_someip_message();
-        let mut buf = Vec::with_capacity(16 + someip_msg.payload.len());
-        buf.extend_from_slice(&someip_msg.header.to_bytes());
-        buf.extend_from_slice(&someip_msg.payload);
-
-        self.socket.send_to(&buf, addr).map_err(SomeIpError::io)?;
+        self.socket
+            .send_to(&msg.to_datagram_bytes(), addr)
+            .map_err(SomeIpError::io)?;
 
         Ok(())
     }
 
     /// Process a received message.
     fn process_message(&mut self, data: &[u8], src_addr: SocketAddr) -> Result<Option<SdRequest>> {
-        // Skip SOME/IP header (16 bytes)
-        if data.len() < 16 {
-            return Ok(None);
-        }
-
-        let sd_payload = &data[16..];
-        let sd_msg = match SdMessage::from_bytes(sd_payload) {
+        let sd_msg = match SdMessage::from_datagram_bytes(data) {
             Ok(msg) => msg,
             Err(_) => return Ok(None),
         };
@@ -393,19 +698,28 @@ impl SdServer {
             match entry {
                 SdEntry::Service(service_entry) => {
                     if service_entry.entry_type == EntryType::FindService {
-                        // Check if we offer this service
-                        let key = (service_entry.service_id, service_entry.instance_id);
-                        if let Some(offered) = self.offered_services.get(&key) {
-                            // Send unicast offer response
-                            let msg = SdMessage::offer_service(
-                                offered.service_id,
-                                offered.instance_id,
-                                offered.major_version,
-                                offered.minor_version,
-                                offered.ttl,
-                                offered.endpoint.clone(),
-                            );
-                            self.send_to(&msg, src_addr)?;
+                        // Check if we offer this service, or can offer it on demand
+                        let mut key = (service_entry.service_id, service_entry.instance_id);
+                        if !self.offered_services.contains_key(&key) {
+                            if let Some(handler) = &mut self.find_handler {
+                                if let Some(service) = handler(key.0, key.1) {
+                                    key = (service.service_id, service.instance_id);
+                                    self.offered_services.insert(key, service);
+                                }
+                            }
+                        }
+                        // Queue unicast offer response after a random delay, once
+                        // per service and client while the queue has room
+                        let queued = self
+                            .pending_responses
+                            .iter()
+                            .any(|(_, k, _, addr)| *k == key && *addr == src_addr);
+                        let full = self.pending_responses.len() >= MAX_PENDING_RESPONSES;
+                        let offered = self.offered_services.get(&key);
+                        if let Some(offered) = offered.filter(|_| !queued && !full) {
+                            let msg = offered.offer_message()?;
+                            let due_at = self.clock.now() + random_delay(self.response_delay);
+                            self.pending_responses.push((due_at, key, msg, src_addr));
                         }
 
                         return Ok(Some(SdRequest::FindService {
@@ -453,6 +767,7 @@ impl SdServer {
                         }
                     }
                 }
+                SdEntry::Raw { .. } => {}
             }
         }
 
@@ -460,9 +775,29 @@ impl SdServer {
     }
 }
 
+impl Drop for SdServer {
+    fn drop(&mut self) {
+        if !self.stop_offer_on_drop {
+            return;
+        }
+        for service in self.offered_services.values() {
+            let msg = SdMessage::stop_offer_service(
+                service.service_id,
+                service.instance_id,
+                service.major_version,
+                service.minor_version,
+            );
+            let _ = self.send_multicast(&msg);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sd::test_util::{localhost_server_config, unicast_server_config};
+    use crate::sd::SD_MAX_OPTIONS_PER_RUN;
+    use std::net::Ipv6Addr;
 
     #[test]
     fn test_offered_service() {
@@ -471,7 +806,7 @@ mod tests {
             instance_id: InstanceId(0x0001),
             major_version: 1,
             minor_version: 0,
-            endpoint: Endpoint::tcp("192.168.1.100:30490".parse().unwrap()),
+            endpoints: vec![Endpoint::tcp("192.168.1.100:30490".parse().unwrap())],
             ttl: 3600,
         };
 
@@ -479,6 +814,411 @@ mod tests {
         assert_eq!(service.ttl, 3600);
     }
 
+    #[test]
+    fn test_offered_service_entry_roundtrip() {
+        let service = OfferedService {
+            service_id: ServiceId(0x1234),
+            instance_id: InstanceId(0x0001),
+            major_version: 2,
+            minor_version: 7,
+            endpoints: vec![Endpoint::udp("192.168.1.100:30501".parse().unwrap())],
+            ttl: 3600,
+        };
+
+        let entry = service.to_entry();
+        assert_eq!(entry.entry_type, EntryType::OfferService);
+        assert_eq!(entry.num_options_1, 0);
+
+        let restored = OfferedService::from_entry(&entry, service.endpoints.clone());
+        assert_eq!(restored.service_id, service.service_id);
+        assert_eq!(restored.instance_id, service.instance_id);
+        assert_eq!(restored.major_version, service.major_version);
+        assert_eq!(restored.minor_version, service.minor_version);
+        assert_eq!(restored.endpoints, service.endpoints);
+        assert_eq!(restored.ttl, service.ttl);
+    }
+
+    #[test]
+    fn test_sd_server_status() {
+        let mut server = SdServer::with_config(localhost_server_config()).unwrap();
+
+        let service = OfferedService {
+            service_id: ServiceId(0x1234),
+            instance_id: InstanceId(0x0001),
+            major_version: 1,
+            minor_version: 0,
+            endpoints: vec![Endpoint::udp("127.0.0.1:30501".parse().unwrap())],
+            ttl: 3600,
+        };
+        server
+            .offered_services
+            .insert((service.service_id, service.instance_id), service);
+
+        let client_a = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let client_b = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for client in [&client_a, &client_b] {
+            let addr = client.local_addr().unwrap();
+            server
+                .accept_subscription(
+                    ServiceId(0x1234),
+                    InstanceId(0x0001),
+                    EventgroupId(0x0001),
+                    0,
+                    addr,
+                    Endpoint::udp(addr),
+                    3600,
+                    None,
+                )
+                .unwrap();
+        }
+
+        let status = server.status();
+        assert_eq!(status.len(), 1);
+        assert_eq!(status[0].service_id, ServiceId(0x1234));
+        assert_eq!(status[0].major_version, 1);
+        assert_eq!(status[0].ttl, 3600);
+        assert_eq!(
+            status[0].subscriber_count_by_eventgroup.get(&EventgroupId(0x0001)),
+            Some(&2)
+        );
+    }
+
+    #[test]
+    fn test_sd_server_accept_subscriptions() {
+        let mut server = SdServer::with_config(localhost_server_config()).unwrap();
+
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let addr = client.local_addr().unwrap();
+
+        let acks: Vec<_> = [EventgroupId(0x0001), EventgroupId(0x0002)]
+            .into_iter()
+            .map(|eventgroup| {
+                let endpoint = Endpoint::udp(addr);
+                (ServiceId(0x1234), InstanceId(0x0001), eventgroup, 0, endpoint, 3600)
+            })
+            .collect();
+        server.accept_subscriptions(&acks, addr).unwrap();
+
+        let mut buf = [0u8; 1500];
+        let (len, _) = client.recv_from(&mut buf).unwrap();
+        let ack = SdMessage::from_datagram_bytes(&buf[..len]).unwrap();
+        assert_eq!(ack.entries.len(), 2);
+        for (entry, eventgroup) in ack.entries.iter().zip([0x0001, 0x0002]) {
+            match entry {
+                SdEntry::Eventgroup(entry) => {
+                    assert_eq!(entry.entry_type, EntryType::SubscribeEventgroupAck);
+                    assert_eq!(entry.eventgroup_id, EventgroupId(eventgroup));
+                }
+                other => panic!("unexpected entry: {:?}", other),
+            }
+        }
+        assert_eq!(server.subscriptions.len(), 2);
+    }
+
+    #[test]
+    fn test_sd_server_offer_rate_limit() {
+        let (receiver, config) = unicast_server_config();
+        receiver.set_nonblocking(true).unwrap();
+        let config = SdServerConfig {
+            max_offers_per_second: Some(5),
+            ..config
+        };
+        let mut server = SdServer::with_config(config).unwrap();
+        server
+            .offer_service(OfferedService {
+                service_id: ServiceId(0x1234),
+                instance_id: InstanceId(0x0001),
+                major_version: 1,
+                minor_version: 0,
+                endpoints: vec![Endpoint::udp("127.0.0.1:30501".parse().unwrap())],
+                ttl: 3600,
+            })
+            .unwrap();
+
+        for _ in 0..100 {
+            server.send_offers().unwrap();
+        }
+        assert!(!server.should_send_offers());
+
+        std::thread::sleep(Duration::from_millis(50));
+        let mut buf = [0u8; 1500];
+        let mut received = 0;
+        while receiver.recv_from(&mut buf).is_ok() {
+            received += 1;
+        }
+
+        // Initial offer plus at most one second's worth of cyclic offers
+        assert_eq!(received, 1 + 5);
+    }
+
+    #[test]
+    fn test_sd_server_offer_rate_limit_resumes_round() {
+        use std::collections::HashSet;
+
+        let (receiver, config) = unicast_server_config();
+        receiver.set_nonblocking(true).unwrap();
+        let config = SdServerConfig {
+            max_offers_per_second: Some(10),
+            ..config
+        };
+        let mut server = SdServer::with_config(config).unwrap();
+        for id in 0..15u16 {
+            let service = OfferedService {
+                service_id: ServiceId(0x1000 + id),
+                instance_id: InstanceId(0x0001),
+                major_version: 1,
+                minor_version: 0,
+                endpoints: vec![Endpoint::udp("127.0.0.1:30501".parse().unwrap())],
+                ttl: 3600,
+            };
+            server
+                .offered_services
+                .insert((service.service_id, service.instance_id), service);
+        }
+
+        // The first call is limited to 10 offers, later calls finish the round
+        server.send_offers().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(3);
+        while !server.deferred_offers.is_empty() {
+            assert!(Instant::now() < deadline, "offer round never completed");
+            std::thread::sleep(Duration::from_millis(20));
+            server.send_offers().unwrap();
+        }
+
+        std::thread::sleep(Duration::from_millis(20));
+        let mut buf = [0u8; 1500];
+        let mut offered = HashSet::new();
+        while let Ok((len, _)) = receiver.recv_from(&mut buf) {
+            let offer = SdMessage::from_datagram_bytes(&buf[..len]).unwrap();
+            offered.insert(offer.entries[0].service_id());
+        }
+        assert_eq!(offered.len(), 15);
+    }
+
+    #[test]
+    fn test_sd_server_offer_services_coalesced() {
+        let (receiver, config) = unicast_server_config();
+        let mut server = SdServer::with_config(config).unwrap();
+        let services: Vec<_> = (1..=3u16)
+            .map(|id| OfferedService {
+                service_id: ServiceId(0x1230 + id),
+                instance_id: InstanceId(0x0001),
+                major_version: 1,
+                minor_version: 0,
+                endpoints: vec![Endpoint::udp(format!("127.0.0.1:3050{}", id).parse().unwrap())],
+                ttl: 3600,
+            })
+            .collect();
+        server.offer_services(services).unwrap();
+        assert_eq!(server.offered_services().count(), 3);
+
+        let mut buf = [0u8; 1500];
+        let (len, _) = receiver.recv_from(&mut buf).unwrap();
+        let offer = SdMessage::from_datagram_bytes(&buf[..len]).unwrap();
+        assert_eq!(offer.entries.len(), 3);
+        for (entry, id) in offer.entries.iter().zip(1..=3u16) {
+            let endpoints = offer.get_endpoints_for_entry(entry);
+            assert_eq!(endpoints[0].address.port(), 30500 + id);
+        }
+
+        // Services with many endpoints are split by size, not by count
+        let services: Vec<_> = (1..=5u16)
+            .map(|id| OfferedService {
+                service_id: ServiceId(0x1240 + id),
+                instance_id: InstanceId(0x0001),
+                major_version: 1,
+                minor_version: 0,
+                endpoints: (0..SD_MAX_OPTIONS_PER_RUN as u16)
+                    .map(|i| SocketAddr::from((Ipv6Addr::LOCALHOST, 30000 + id * 100 + i)))
+                    .map(Endpoint::udp)
+                    .collect(),
+                ttl: 3600,
+            })
+            .collect();
+        server.offer_services(services).unwrap();
+        assert_eq!(server.offered_services().count(), 8);
+
+        let mut entries = 0;
+        let mut datagrams = 0;
+        while entries < 5 {
+            let (len, _) = receiver.recv_from(&mut buf).unwrap();
+            assert!(len <= MAX_OFFER_DATAGRAM_SIZE);
+            entries += SdMessage::from_datagram_bytes(&buf[..len]).unwrap().entries.len();
+            datagrams += 1;
+        }
+        assert_eq!(entries, 5);
+        assert!(datagrams > 1);
+
+        // No further datagrams were sent
+        receiver.set_nonblocking(true).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(receiver.recv_from(&mut buf).is_err());
+    }
+
+    #[test]
+    fn test_sd_server_offer_tcp_and_udp_endpoints() {
+        let (receiver, config) = unicast_server_config();
+        let mut server = SdServer::with_config(config).unwrap();
+        let endpoints = vec![
+            Endpoint::tcp("192.168.1.10:30501".parse().unwrap()),
+            Endpoint::udp("192.168.1.10:30502".parse().unwrap()),
+        ];
+        server
+            .offer_service(OfferedService {
+                service_id: ServiceId(0x1234),
+                instance_id: InstanceId(0x0001),
+                major_version: 1,
+                minor_version: 0,
+                endpoints: endpoints.clone(),
+                ttl: 3600,
+            })
+            .unwrap();
+
+        let mut buf = [0u8; 1500];
+        let (len, _) = receiver.recv_from(&mut buf).unwrap();
+        let offer = SdMessage::from_datagram_bytes(&buf[..len]).unwrap();
+        assert_eq!(offer.entries.len(), 1);
+        assert_eq!(offer.options.len(), 2);
+        match &offer.entries[0] {
+            SdEntry::Service(entry) => assert_eq!(entry.num_options_1, 2),
+            other => panic!("unexpected entry: {:?}", other),
+        }
+        assert_eq!(offer.get_endpoints_for_entry(&offer.entries[0]), endpoints);
+    }
+
+    #[test]
+    fn test_sd_server_stop_offer_on_drop() {
+        let (receiver, config) = unicast_server_config();
+        let config = SdServerConfig {
+            stop_offer_on_drop: true,
+            ..config
+        };
+        let mut server = SdServer::with_config(config).unwrap();
+        server
+            .offer_service(OfferedService {
+                service_id: ServiceId(0x1234),
+                instance_id: InstanceId(0x0001),
+                major_version: 1,
+                minor_version: 0,
+                endpoints: vec![Endpoint::udp("127.0.0.1:30501".parse().unwrap())],
+                ttl: 3600,
+            })
+            .unwrap();
+
+        let mut buf = [0u8; 1500];
+        let (len, _) = receiver.recv_from(&mut buf).unwrap();
+        assert!(SdMessage::from_datagram_bytes(&buf[..len]).unwrap().is_offer_service());
+
+        drop(server);
+        let (len, _) = receiver.recv_from(&mut buf).unwrap();
+        let stop = SdMessage::from_datagram_bytes(&buf[..len]).unwrap();
+        assert!(stop.is_stop_offer_service());
+    }
+
+    #[test]
+    fn test_sd_server_find_response_delay() {
+        use crate::clock::MockClock;
+
+        let config = SdServerConfig {
+            response_delay: (Duration::from_millis(100), Duration::from_millis(150)),
+            ..localhost_server_config()
+        };
+        let mut server = SdServer::with_config(config).unwrap();
+        let service = OfferedService {
+            service_id: ServiceId(0x1234),
+            instance_id: InstanceId(0x0001),
+            major_version: 1,
+            minor_version: 0,
+            endpoints: vec![Endpoint::udp("127.0.0.1:30501".parse().unwrap())],
+            ttl: 3600,
+        };
+        server
+            .offered_services
+            .insert((service.service_id, service.instance_id), service);
+
+        let clock = MockClock::new();
+        server.set_clock(Arc::new(clock.clone()));
+
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let client_addr = client.local_addr().unwrap();
+        let find = SdMessage::find_service(ServiceId(0x1234), InstanceId(0x0001), 0xFF, 0xFFFFFFFF);
+        let find = find.to_datagram_bytes();
+
+        // An IPv4 socket cannot send to this address, so its answer fails
+        server.process_message(&find, "[::1]:30490".parse().unwrap()).unwrap();
+        // Repeated finds from the same client are answered once
+        server.process_message(&find, client_addr).unwrap();
+        server.process_message(&find, client_addr).unwrap();
+        assert_eq!(server.pending_responses(), 2);
+
+        clock.advance(Duration::from_millis(99));
+        assert_eq!(server.send_due_responses().unwrap(), 0);
+
+        // The failing answer does not keep the other one from being sent
+        clock.advance(Duration::from_millis(51));
+        assert!(server.send_due_responses().is_err());
+        assert_eq!(server.pending_responses(), 0);
+        let mut buf = [0u8; 1500];
+        let (len, _) = client.recv_from(&mut buf).unwrap();
+        let offer = SdMessage::from_datagram_bytes(&buf[..len]).unwrap();
+        assert!(offer.is_offer_service());
+
+        // The queue is bounded
+        for port in 1..=MAX_PENDING_RESPONSES as u16 + 1 {
+            let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+            server.process_message(&find, addr).unwrap();
+        }
+        assert_eq!(server.pending_responses(), MAX_PENDING_RESPONSES);
+    }
+
+    #[test]
+    fn test_sd_server_find_handler() {
+        let mut server = SdServer::with_config(localhost_server_config()).unwrap();
+        server.set_find_handler(|service_id, instance_id| {
+            (service_id == ServiceId(0x1234)).then(|| OfferedService {
+                service_id,
+                instance_id,
+                major_version: 1,
+                minor_version: 0,
+                endpoints: vec![Endpoint::udp("127.0.0.1:30501".parse().unwrap())],
+                ttl: 3600,
+            })
+        });
+
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        for service_id in [ServiceId(0x5678), ServiceId(0x1234)] {
+            let find = SdMessage::find_service(service_id, InstanceId(0x0001), 0xFF, 0xFFFFFFFF);
+            client.send_to(&find.to_datagram_bytes(), server_addr).unwrap();
+        }
+
+        std::thread::sleep(Duration::from_millis(50));
+        while server.poll().unwrap().is_some() {}
+
+        let mut buf = [0u8; 1500];
+        let (len, _) = client.recv_from(&mut buf).unwrap();
+        let offer = SdMessage::from_datagram_bytes(&buf[..len]).unwrap();
+        assert!(offer.is_offer_service());
+        match &offer.entries[0] {
+            SdEntry::Service(entry) => assert_eq!(entry.service_id, ServiceId(0x1234)),
+            other => panic!("unexpected entry: {:?}", other),
+        }
+        assert_eq!(server.offered_services().count(), 1);
+    }
+
+    #[test]
+    fn test_random_delay_range() {
+        let range = (Duration::from_millis(10), Duration::from_millis(50));
+        for _ in 0..100 {
+            let delay = random_delay(range);
+            assert!(delay >= range.0 && delay <= range.1);
+        }
+        assert_eq!(random_delay((Duration::ZERO, Duration::ZERO)), Duration::ZERO);
+    }
+
     #[test]
     fn test_sd_server_config_default() {
         let config = SdServerConfig::default();
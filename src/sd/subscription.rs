@@ -0,0 +1,213 @@
+//! Eventgroup subscriptions combining SD and event reception.
+
+use std::net::{SocketAddr, UdpSocket};
+use std::time::Duration;
+
+use crate::error::{Result, SomeIpError};
+use crate::message::SomeIpMessage;
+use crate::transport::map_io_timeout;
+
+use super::client::{SdClient, ServiceInfo};
+use super::option::Endpoint;
+use super::types::EventgroupId;
+
+/// An acknowledged eventgroup subscription with its event socket.
+///
+/// Created by [`EventSubscription::subscribe`], which performs the SD
+/// subscribe handshake. Events are read from the multicast socket if the
+/// server assigned a multicast endpoint, otherwise from the unicast socket.
+#[derive(Debug)]
+pub struct EventSubscription {
+    service: ServiceInfo,
+    eventgroup_id: EventgroupId,
+    unicast: UdpSocket,
+    multicast: Option<UdpSocket>,
+    recv_buffer: Vec<u8>,
+}
+
+impl EventSubscription {
+    /// Subscribe to an eventgroup and wait at most `timeout` for the ACK.
+    ///
+    /// A UDP event socket is bound to `local_addr` and announced as the
+    /// client's endpoint for this subscribe only; the endpoint set with
+    /// [`SdClient::set_local_endpoint`] is restored afterwards. For an IPv6
+    /// `local_addr`, a multicast group is joined on its scope ID interface.
+    /// See [`SdClient::subscribe_and_wait`] for how NACKs and timeouts are
+    /// reported.
+    pub fn subscribe(
+        client: &mut SdClient,
+        service: &ServiceInfo,
+        eventgroup_id: EventgroupId,
+        local_addr: SocketAddr,
+        timeout: Duration,
+    ) -> Result<Self> {
+        let unicast = UdpSocket::bind(local_addr).map_err(SomeIpError::io)?;
+        let endpoint = Endpoint::udp(unicast.local_addr().map_err(SomeIpError::io)?);
+        let previous_endpoint = client.replace_local_endpoint(Some(endpoint));
+        let result = client.subscribe_and_wait(
+            service.service_id,
+            service.instance_id,
+            eventgroup_id,
+            service.major_version,
+            timeout,
+        );
+        client.replace_local_endpoint(previous_endpoint);
+
+        let multicast = match (result?, local_addr) {
+            (Some(endpoint), SocketAddr::V4(addr)) => {
+                Some(client.join_event_multicast(&endpoint, *addr.ip())?)
+            }
+            (Some(endpoint), SocketAddr::V6(addr)) => {
+                Some(client.join_event_multicast_v6(&endpoint, addr.scope_id())?)
+            }
+            (None, _) => None,
+        };
+
+        Ok(Self {
+            service: service.clone(),
+            eventgroup_id,
+            unicast,
+            multicast,
+            recv_buffer: vec![0u8; 65535],
+        })
+    }
+
+    /// Get the subscribed service.
+    pub fn service(&self) -> &ServiceInfo {
+        &self.service
+    }
+
+    /// Get the subscribed eventgroup.
+    pub fn eventgroup_id(&self) -> EventgroupId {
+        self.eventgroup_id
+    }
+
+    /// Get the local address events are received on.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.event_socket().local_addr().map_err(SomeIpError::io)
+    }
+
+    /// Receive the next event, waiting at most `timeout`.
+    pub fn recv_event(&mut self, timeout: Duration) -> Result<SomeIpMessage> {
+        let mut buf = std::mem::take(&mut self.recv_buffer);
+        let socket = self.event_socket();
+        let result = socket
+            .set_read_timeout(Some(timeout))
+            .and_then(|()| socket.recv_from(&mut buf));
+
+        let message = match result {
+            Ok((len, _)) => SomeIpMessage::from_bytes(&buf[..len]),
+            Err(e) => Err(map_io_timeout(SomeIpError::io(e))),
+        };
+        self.recv_buffer = buf;
+        message
+    }
+
+    /// Stop the subscription.
+    pub fn unsubscribe(self, client: &mut SdClient) -> Result<()> {
+        client.unsubscribe(
+            self.service.service_id,
+            self.service.instance_id,
+            self.eventgroup_id,
+            self.service.major_version,
+        )
+    }
+
+    /// Get the socket events are read from.
+    fn event_socket(&self) -> &UdpSocket {
+        self.multicast.as_ref().unwrap_or(&self.unicast)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::{MethodId, ServiceId};
+    use crate::sd::test_util::{unicast_client_config, unicast_server_config};
+    use crate::sd::{InstanceId, OfferedService, SdClientConfig, SdRequest, SdServer};
+    use std::thread;
+    use std::time::Instant;
+
+    #[test]
+    fn test_event_subscription() {
+        let (_group, server_config) = unicast_server_config();
+        let mut server = SdServer::with_config(server_config).unwrap();
+        let server_addr = server.local_addr().unwrap();
+        server
+            .offer_service(OfferedService {
+                service_id: ServiceId(0x1234),
+                instance_id: InstanceId(0x0001),
+                major_version: 1,
+                minor_version: 0,
+                endpoints: vec![Endpoint::udp("[::1]:30501".parse().unwrap())],
+                ttl: 3600,
+            })
+            .unwrap();
+
+        let (_group, client_config) = unicast_client_config();
+        let client_config = SdClientConfig {
+            multicast_addr: server_addr,
+            ..client_config
+        };
+        let mut client = SdClient::with_config(client_config).unwrap();
+
+        // Answer the find, then accept the subscription
+        let server_handle = thread::spawn(move || {
+            let deadline = Instant::now() + Duration::from_secs(2);
+            let (endpoint, from) = loop {
+                assert!(Instant::now() < deadline, "no subscription received");
+                if let Some(SdRequest::Subscribe { endpoint, from, .. }) = server.poll().unwrap() {
+                    break (endpoint, from);
+                }
+                thread::sleep(Duration::from_millis(5));
+            };
+            server
+                .accept_subscription(
+                    ServiceId(0x1234),
+                    InstanceId(0x0001),
+                    EventgroupId(0x0001),
+                    0,
+                    from,
+                    endpoint.clone(),
+                    3600,
+                    None,
+                )
+                .unwrap();
+
+            // Publish one event to the subscriber
+            let event = SomeIpMessage::notification(ServiceId(0x1234), MethodId::event(0x0001))
+                .payload(b"event".as_slice())
+                .build();
+            let publisher = UdpSocket::bind("[::1]:0").unwrap();
+            publisher.send_to(&event.to_bytes(), endpoint.address).unwrap();
+        });
+
+        let service = client
+            .wait_for_service(ServiceId(0x1234), InstanceId(0x0001), Duration::from_secs(2))
+            .unwrap()
+            .unwrap();
+        let configured = Endpoint::udp("[::1]:30501".parse().unwrap());
+        client.set_local_endpoint(configured.clone());
+        let mut subscription = EventSubscription::subscribe(
+            &mut client,
+            &service,
+            EventgroupId(0x0001),
+            "[::1]:0".parse().unwrap(),
+            Duration::from_secs(2),
+        )
+        .unwrap();
+        // The client's own endpoint is restored after the subscribe
+        assert_eq!(client.replace_local_endpoint(None), Some(configured));
+
+        let event = subscription.recv_event(Duration::from_secs(2)).unwrap();
+        assert_eq!(event.header.method_id, MethodId::event(0x0001));
+        assert_eq!(event.payload.as_ref(), b"event");
+        assert!(matches!(
+            subscription.recv_event(Duration::from_millis(20)),
+            Err(SomeIpError::Timeout)
+        ));
+
+        server_handle.join().unwrap();
+        subscription.unsubscribe(&mut client).unwrap();
+    }
+}
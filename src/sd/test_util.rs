@@ -0,0 +1,57 @@
+//! Socket fixtures shared by the SD unit tests.
+
+use std::net::{Ipv4Addr, UdpSocket};
+use std::time::Duration;
+
+use super::client::SdClientConfig;
+use super::server::SdServerConfig;
+
+/// Bind a `[::1]` socket that stands in for the SD multicast group.
+///
+/// Unicast IPv6 "multicast" addresses need no group membership, so tests can
+/// observe everything a node sends to the group from this one socket.
+fn unicast_group() -> UdpSocket {
+    let receiver = UdpSocket::bind("[::1]:0").unwrap();
+    receiver.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+    receiver
+}
+
+/// A `[::1]` server config whose multicast address is the returned receiver.
+pub(crate) fn unicast_server_config() -> (UdpSocket, SdServerConfig) {
+    let receiver = unicast_group();
+    let config = SdServerConfig {
+        bind_addr: "[::1]:0".parse().unwrap(),
+        multicast_addr: receiver.local_addr().unwrap(),
+        ..Default::default()
+    };
+    (receiver, config)
+}
+
+/// A `[::1]` client config whose multicast address is the returned receiver.
+pub(crate) fn unicast_client_config() -> (UdpSocket, SdClientConfig) {
+    let receiver = unicast_group();
+    let config = SdClientConfig {
+        bind_addr: "[::1]:0".parse().unwrap(),
+        multicast_addr: receiver.local_addr().unwrap(),
+        ..Default::default()
+    };
+    (receiver, config)
+}
+
+/// A server config on an ephemeral IPv4 loopback port.
+pub(crate) fn localhost_server_config() -> SdServerConfig {
+    SdServerConfig {
+        bind_addr: "127.0.0.1:0".parse().unwrap(),
+        multicast_interface: Some(Ipv4Addr::LOCALHOST),
+        ..Default::default()
+    }
+}
+
+/// A client config on an ephemeral IPv4 loopback port.
+pub(crate) fn localhost_client_config() -> SdClientConfig {
+    SdClientConfig {
+        bind_addr: "127.0.0.1:0".parse().unwrap(),
+        multicast_interface: Some(Ipv4Addr::LOCALHOST),
+        ..Default::default()
+    }
+}
@@ -20,6 +20,11 @@ pub const SD_ENTRY_SIZE: usize = 16;
 /// Size of an SD option header in bytes.
 pub const SD_OPTION_HEADER_SIZE: usize = 4;
 
+/// Largest number of options one option run of an entry can reference.
+///
+/// The count is a 4-bit field; the run's start index is 8 bits.
+pub const SD_MAX_OPTIONS_PER_RUN: usize = 15;
+
 /// Instance ID for a service instance.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub struct InstanceId(pub u16);
@@ -143,12 +148,13 @@ impl OptionType {
 }
 
 /// Transport protocol used for endpoints.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 #[repr(u8)]
 pub enum TransportProtocol {
     /// TCP protocol.
     Tcp = 0x06,
     /// UDP protocol.
+    #[default]
     Udp = 0x11,
 }
 
@@ -161,11 +167,25 @@ impl TransportProtocol {
             _ => None,
         }
     }
-}
 
-impl Default for TransportProtocol {
-    fn default() -> Self {
-        Self::Udp
+    /// Create a TransportProtocol from an IANA IP protocol number.
+    ///
+    /// SOME/IP-SD uses the IANA numbers as its option protocol byte, so this
+    /// currently agrees with [`from_u8`](Self::from_u8).
+    pub fn from_ip_proto(proto: u8) -> Option<Self> {
+        match proto {
+            6 => Some(Self::Tcp),
+            17 => Some(Self::Udp),
+            _ => None,
+        }
+    }
+
+    /// Get the IANA IP protocol number.
+    pub fn to_ip_proto(&self) -> u8 {
+        match self {
+            Self::Tcp => 6,
+            Self::Udp => 17,
+        }
     }
 }
 
@@ -201,4 +221,13 @@ mod tests {
         assert_eq!(TransportProtocol::from_u8(0x11), Some(TransportProtocol::Udp));
         assert_eq!(TransportProtocol::from_u8(0xFF), None);
     }
+
+    #[test]
+    fn test_transport_protocol_ip_proto() {
+        assert_eq!(TransportProtocol::Tcp.to_ip_proto(), 6);
+        assert_eq!(TransportProtocol::Udp.to_ip_proto(), 17);
+        assert_eq!(TransportProtocol::from_ip_proto(6), Some(TransportProtocol::Tcp));
+        assert_eq!(TransportProtocol::from_ip_proto(17), Some(TransportProtocol::Udp));
+        assert_eq!(TransportProtocol::from_ip_proto(1), None);
+    }
 }
@@ -3,15 +3,17 @@
 use std::io;
 use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
 use std::sync::atomic::{AtomicU16, Ordering};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crate::error::Result;
-use crate::header::{ClientId, SessionId, HEADER_SIZE};
+use crate::header::{ClientId, ServiceId, SessionId, HEADER_SIZE};
 use crate::message::SomeIpMessage;
+use crate::transport::read_until;
 
-use super::header::TP_HEADER_SIZE;
-use super::reassembly::TpReassembler;
-use super::segment::{segment_message, TpSegment, DEFAULT_MAX_SEGMENT_PAYLOAD};
+use super::reassembly::{ReassemblyKey, TpReassembler};
+use super::segment::{
+    needs_segmentation, segment_message, TpSegment, DEFAULT_MAX_SEGMENT_PAYLOAD,
+};
 
 /// Maximum UDP datagram size for TP messages.
 const MAX_DATAGRAM_SIZE: usize = 1500;
@@ -107,37 +109,46 @@ impl TpUdpClient {
 
     /// Send a message, segmenting if necessary.
     fn send_message(&self, message: &SomeIpMessage) -> Result<()> {
-        let segments = segment_message(message, self.max_segment_payload);
-
-        if segments.is_empty() {
+        if !needs_segmentation(message, self.max_segment_payload) {
             // Small message, send directly
             let data = message.to_bytes();
             self.socket.send(&data)?;
-        } else {
-            // Large message, send as segments
-            for segment in segments {
-                let data = segment.to_bytes();
-                self.socket.send(&data)?;
-            }
+            return Ok(());
+        }
+
+        // Large message, send as segments
+        for segment in segment_message(message, self.max_segment_payload) {
+            let data = segment.to_bytes();
+            self.socket.send(&data)?;
         }
 
         Ok(())
     }
 
     /// Send a message to a specific address, segmenting if necessary.
-    fn send_message_to<A: ToSocketAddrs>(&self, addr: A, message: &SomeIpMessage) -> Result<()> {
-        let segments = segment_message(message, self.max_segment_payload);
-
-        if segments.is_empty() {
+    ///
+    /// `progress` is called with the payload bytes sent so far and the total
+    /// after each datagram.
+    fn send_message_to<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        message: &SomeIpMessage,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<()> {
+        let total = message.payload.len();
+        if !needs_segmentation(message, self.max_segment_payload) {
             // Small message, send directly
             let data = message.to_bytes();
             self.socket.send_to(&data, &addr)?;
-        } else {
-            // Large message, send as segments
-            for segment in segments {
-                let data = segment.to_bytes();
-                self.socket.send_to(&data, &addr)?;
-            }
+            progress(total, total);
+            return Ok(());
+        }
+
+        // Large message, send as segments
+        for segment in segment_message(message, self.max_segment_payload) {
+            let data = segment.to_bytes();
+            self.socket.send_to(&data, &addr)?;
+            progress(segment.tp_header.byte_offset() + segment.payload.len(), total);
         }
 
         Ok(())
@@ -151,9 +162,8 @@ impl TpUdpClient {
             let (len, addr) = self.socket.recv_from(&mut self.recv_buffer)?;
             let data = &self.recv_buffer[..len];
 
-            // Check if this is a TP message
-            if len >= HEADER_SIZE + TP_HEADER_SIZE {
-                // Parse header to check message type
+            // Check if this is a TP message, regardless of its size
+            if len >= HEADER_SIZE {
                 let header = crate::header::SomeIpHeader::from_bytes(&data[..HEADER_SIZE])?;
 
                 if header.message_type.is_tp() {
@@ -194,6 +204,40 @@ impl TpUdpClient {
         }
     }
 
+    /// Send a request to the connected address and wait at most `duration` for a response.
+    ///
+    /// The timeout covers reassembly of a segmented response. On expiry the
+    /// partial reassembly is discarded and
+    /// [`SomeIpError::Timeout`](crate::SomeIpError::Timeout) is returned.
+    pub fn call_timeout(
+        &mut self,
+        mut message: SomeIpMessage,
+        duration: Duration,
+    ) -> Result<SomeIpMessage> {
+        message.header.client_id = self.client_id;
+        message.header.session_id = self.next_session_id();
+
+        let request_id = message.header.request_id();
+        let key = ReassemblyKey::from_header(&message.header);
+
+        self.send_message(&message)?;
+
+        let deadline = Instant::now() + duration;
+        let previous_timeout = self.socket.read_timeout()?;
+
+        let result = read_until(deadline, |remaining| {
+            self.socket.set_read_timeout(Some(remaining))?;
+            let (response, _) = self.receive()?;
+            Ok((response.header.request_id() == request_id).then_some(response))
+        });
+
+        if result.is_err() {
+            self.reassembler.cancel(key);
+        }
+        self.socket.set_read_timeout(previous_timeout)?;
+        result
+    }
+
     /// Send a request to a specific address and wait for a response.
     pub fn call_to<A: ToSocketAddrs>(
         &mut self,
@@ -205,7 +249,7 @@ impl TpUdpClient {
 
         let request_id = message.header.request_id();
 
-        self.send_message_to(addr, &message)?;
+        self.send_message_to(addr, &message, |_, _| {})?;
 
         // Wait for matching response
         loop {
@@ -230,7 +274,23 @@ impl TpUdpClient {
         message.header.client_id = self.client_id;
         message.header.session_id = self.next_session_id();
 
-        self.send_message_to(addr, &message)
+        self.send_message_to(addr, &message, |_, _| {})
+    }
+
+    /// Send a fire-and-forget message to a specific address, reporting progress.
+    ///
+    /// `progress(sent, total)` is invoked after each segment goes out, with
+    /// payload byte counts. Useful for progress bars during large transfers.
+    pub fn send_with_progress<A: ToSocketAddrs>(
+        &mut self,
+        addr: A,
+        mut message: SomeIpMessage,
+        progress: impl FnMut(usize, usize),
+    ) -> Result<()> {
+        message.header.client_id = self.client_id;
+        message.header.session_id = self.next_session_id();
+
+        self.send_message_to(addr, &message, progress)
     }
 
     /// Clean up timed-out reassembly contexts.
@@ -245,6 +305,16 @@ impl TpUdpClient {
         self.reassembler.active_contexts()
     }
 
+    /// Cancel a single in-flight reassembly.
+    pub fn cancel_reassembly(&mut self, key: ReassemblyKey) -> bool {
+        self.reassembler.cancel(key)
+    }
+
+    /// Cancel all in-flight reassemblies for a service.
+    pub fn cancel_reassemblies_for_service(&mut self, service_id: ServiceId) -> usize {
+        self.reassembler.cancel_for_service(service_id)
+    }
+
     /// Get a reference to the underlying socket.
     pub fn socket(&self) -> &UdpSocket {
         &self.socket
@@ -254,6 +324,7 @@ impl TpUdpClient {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::SomeIpError;
 
     #[test]
     fn test_tp_client_new() {
@@ -271,4 +342,94 @@ mod tests {
         client.set_max_segment_payload(1000);
         client.set_reassembly_timeout(Duration::from_secs(10));
     }
+
+    #[test]
+    fn test_tp_client_call_timeout_incomplete_response() {
+        use crate::header::MethodId;
+        use std::thread;
+
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server_addr = server.local_addr().unwrap();
+
+        let server_handle = thread::spawn(move || {
+            let mut buf = [0u8; 1500];
+            let (len, client_addr) = server.recv_from(&mut buf).unwrap();
+            let request = SomeIpMessage::from_bytes(&buf[..len]).unwrap();
+
+            // Send only 2 of the 3 response segments
+            let response = request.create_response().payload(vec![0x5A; 48]).build();
+            let segments = segment_message(&response, 16);
+            assert_eq!(segments.len(), 3);
+            for segment in &segments[..2] {
+                server.send_to(&segment.to_bytes(), client_addr).unwrap();
+            }
+        });
+
+        let mut client = TpUdpClient::bind("127.0.0.1:0").unwrap();
+        client.connect(server_addr).unwrap();
+
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001)).build();
+        let started = Instant::now();
+        let result = client.call_timeout(request, Duration::from_millis(200));
+
+        assert!(matches!(result, Err(SomeIpError::Timeout)));
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert_eq!(client.active_reassemblies(), 0);
+        assert_eq!(client.socket().read_timeout().unwrap(), None);
+
+        server_handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_tp_client_send_with_progress() {
+        use crate::header::MethodId;
+
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut client = TpUdpClient::bind("127.0.0.1:0").unwrap();
+        client.set_max_segment_payload(16);
+
+        let message = SomeIpMessage::notification(ServiceId(0x1234), MethodId::event(0x0001))
+            .payload(vec![0xAB; 40])
+            .build();
+        let mut reports = Vec::new();
+        client
+            .send_with_progress(receiver.local_addr().unwrap(), message, |sent, total| {
+                reports.push((sent, total))
+            })
+            .unwrap();
+
+        assert_eq!(reports, vec![(16, 40), (32, 40), (40, 40)]);
+    }
+
+    #[test]
+    fn test_tp_client_small_message_skips_segmentation() {
+        use super::super::segment::SEGMENT_CALLS;
+        use crate::header::MethodId;
+
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let mut client = TpUdpClient::bind("127.0.0.1:0").unwrap();
+        client.set_max_segment_payload(16);
+        client.connect(receiver.local_addr().unwrap()).unwrap();
+
+        let calls_before = SEGMENT_CALLS.with(|calls| calls.get());
+        let small = SomeIpMessage::notification(ServiceId(0x1234), MethodId::event(0x0001))
+            .payload(vec![1; 16])
+            .build();
+        client.send(small.clone()).unwrap();
+        assert_eq!(SEGMENT_CALLS.with(|calls| calls.get()), calls_before);
+
+        let mut buf = [0u8; 1500];
+        let (len, _) = receiver.recv_from(&mut buf).unwrap();
+        let received = SomeIpMessage::from_bytes(&buf[..len]).unwrap();
+        assert_eq!(received.payload, small.payload);
+        assert!(!received.header.message_type.is_tp());
+
+        // Large messages still go through segmentation
+        let large = SomeIpMessage::notification(ServiceId(0x1234), MethodId::event(0x0001))
+            .payload(vec![2; 17])
+            .build();
+        client.send(large).unwrap();
+        assert_eq!(SEGMENT_CALLS.with(|calls| calls.get()), calls_before + 1);
+    }
 }
@@ -1,10 +1,17 @@
 //! SOME/IP-TP header definition.
 
 use crate::error::{Result, SomeIpError};
+use crate::header::HEADER_SIZE;
 
 /// Size of the TP header in bytes.
 pub const TP_HEADER_SIZE: usize = 4;
 
+/// Largest payload a reassembled message can carry.
+///
+/// Bounded by the 32-bit SOME/IP length field, which also covers the
+/// 8 bytes from request ID to return code.
+pub const MAX_TP_PAYLOAD: usize = u32::MAX as usize - 8;
+
 /// TP header (4 bytes after SOME/IP header for segmented messages).
 ///
 /// Format:
@@ -63,17 +70,45 @@ impl TpHeader {
         // More flag is bit 31 (1 bit)
         let value = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
 
-        let offset = value >> 4; // Upper 28 bits
-        let more = (value & 0x01) != 0; // Lowest bit
+        Ok(Self::from_u32(value))
+    }
+
+    /// Create a TP header from its packed 32-bit representation.
+    ///
+    /// The offset occupies the upper 28 bits and the more flag the lowest
+    /// bit; the 3 reserved bits are ignored.
+    pub fn from_u32(value: u32) -> Self {
+        Self {
+            offset: value >> 4,
+            more: (value & 0x01) != 0,
+        }
+    }
+
+    /// Get the packed 32-bit representation, with reserved bits set to 0.
+    pub fn to_u32(&self) -> u32 {
+        (self.offset << 4) | (if self.more { 0x01 } else { 0x00 })
+    }
+
+    /// Peek the TP header of a full datagram without parsing the segment.
+    ///
+    /// Reads the 4 bytes following the SOME/IP header. The message type is
+    /// not checked.
+    pub fn peek(data: &[u8]) -> Result<Self> {
+        let min_size = HEADER_SIZE + TP_HEADER_SIZE;
+        if data.len() < min_size {
+            return Err(SomeIpError::MessageTooShort {
+                expected: min_size,
+                actual: data.len(),
+            });
+        }
 
-        Ok(Self { offset, more })
+        Self::from_bytes(&data[HEADER_SIZE..min_size])
     }
 
     /// Serialize the TP header to bytes.
     pub fn to_bytes(&self) -> [u8; TP_HEADER_SIZE] {
         // Offset in upper 28 bits, reserved 3 bits, more flag in lowest bit
-        let value = (self.offset << 4) | (if self.more { 0x01 } else { 0x00 });
-        value.to_be_bytes()
+        self.to_u32().to_be_bytes()
     }
 }
 
@@ -120,12 +155,43 @@ mod tests {
         assert_eq!(original, parsed);
     }
 
+    #[test]
+    fn test_tp_header_bit_layout() {
+        let header = TpHeader::new(0x1234, true);
+        assert_eq!(header.to_u32(), 0x0001_2341);
+        assert_eq!(header.to_bytes(), [0x00, 0x01, 0x23, 0x41]);
+        assert_eq!(TpHeader::from_bytes(&[0x00, 0x01, 0x23, 0x41]).unwrap(), header);
+
+        // Reserved bits are ignored when parsing
+        assert_eq!(TpHeader::from_u32(0x0001_234F), header);
+    }
+
     #[test]
     fn test_tp_header_byte_offset() {
         let header = TpHeader::new(100, true);
         assert_eq!(header.byte_offset(), 1600); // 100 * 16
     }
 
+    #[test]
+    fn test_tp_header_peek() {
+        use crate::header::{MethodId, ServiceId};
+        use crate::message::SomeIpMessage;
+        use crate::tp::segment::segment_message;
+
+        let msg = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .payload(vec![0xAB; 100])
+            .build();
+        let segments = segment_message(&msg, 32);
+
+        let data = segments[1].to_bytes();
+        let header = TpHeader::peek(&data).unwrap();
+        assert_eq!(header, segments[1].tp_header);
+        assert_eq!(header.offset, 2);
+        assert!(header.more);
+
+        assert!(TpHeader::peek(&data[..HEADER_SIZE + 2]).is_err());
+    }
+
     #[test]
     fn test_tp_header_too_short() {
         let result = TpHeader::from_bytes(&[0, 1, 2]);
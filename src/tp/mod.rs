@@ -34,7 +34,7 @@ mod segment;
 mod server;
 
 pub use client::TpUdpClient;
-pub use header::{TpHeader, TP_HEADER_SIZE};
-pub use reassembly::{ReassemblyKey, TpReassembler};
+pub use header::{TpHeader, MAX_TP_PAYLOAD, TP_HEADER_SIZE};
+pub use reassembly::{ReassemblyKey, ReassemblyLimitPolicy, TpReassembler};
 pub use segment::{needs_segmentation, segment_message, TpSegment, DEFAULT_MAX_SEGMENT_PAYLOAD};
 pub use server::TpUdpServer;
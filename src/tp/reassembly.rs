@@ -1,14 +1,17 @@
 //! SOME/IP-TP message reassembly.
 
 use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use bytes::{BufMut, BytesMut};
 
+use crate::clock::{self, Clock};
 use crate::error::{Result, SomeIpError};
 use crate::header::{ClientId, MethodId, ServiceId, SessionId, SomeIpHeader};
 use crate::message::SomeIpMessage;
 
+use super::header::MAX_TP_PAYLOAD;
 use super::segment::TpSegment;
 
 /// Default timeout for reassembly contexts.
@@ -41,6 +44,17 @@ impl ReassemblyKey {
     }
 }
 
+/// What to do when a new segment would exceed the context or byte limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReassemblyLimitPolicy {
+    /// Reject the segment with [`SomeIpError::TooManyReassemblies`] or
+    /// [`SomeIpError::TpMemoryLimit`].
+    #[default]
+    Reject,
+    /// Drop the oldest active reassembly to make room.
+    EvictOldest,
+}
+
 /// State for reassembling a single message.
 #[derive(Debug)]
 struct ReassemblyContext {
@@ -50,24 +64,29 @@ struct ReassemblyContext {
     segments: BTreeMap<u32, bytes::Bytes>,
     /// Total payload length (known when last segment is received).
     total_length: Option<usize>,
+    /// Payload bytes held in `segments`.
+    buffered_bytes: usize,
     /// When this context was created.
     created_at: Instant,
 }
 
 impl ReassemblyContext {
-    fn new(header: SomeIpHeader) -> Self {
+    fn new(header: SomeIpHeader, now: Instant) -> Self {
         Self {
             base_header: header,
             segments: BTreeMap::new(),
             total_length: None,
-            created_at: Instant::now(),
+            buffered_bytes: 0,
+            created_at: now,
         }
     }
 
     /// Add a segment to this context.
     fn add_segment(&mut self, segment: &TpSegment) {
         let offset = segment.tp_header.offset;
-        self.segments.insert(offset, segment.payload.clone());
+        let replaced = self.segments.insert(offset, segment.payload.clone());
+        self.buffered_bytes -= replaced.map_or(0, |payload| payload.len());
+        self.buffered_bytes += segment.payload.len();
 
         // If this is the last segment, calculate total length
         if !segment.tp_header.more {
@@ -76,6 +95,28 @@ impl ReassemblyContext {
         }
     }
 
+    /// Get the byte ranges not yet covered by received segments.
+    fn missing_ranges(&self) -> Vec<(u32, u32)> {
+        let mut ranges = Vec::new();
+        let mut covered: u32 = 0;
+
+        for (&offset, payload) in &self.segments {
+            let start = offset * 16;
+            if start > covered {
+                ranges.push((covered, start));
+            }
+            covered = covered.max(start + payload.len() as u32);
+        }
+
+        if let Some(total) = self.total_length {
+            if (covered as usize) < total {
+                ranges.push((covered, total as u32));
+            }
+        }
+
+        ranges
+    }
+
     /// Check if reassembly is complete.
     fn is_complete(&self) -> bool {
         let total = match self.total_length {
@@ -108,7 +149,7 @@ impl ReassemblyContext {
 
         let mut payload = BytesMut::with_capacity(total);
 
-        for (_, segment_payload) in &self.segments {
+        for segment_payload in self.segments.values() {
             payload.put_slice(segment_payload);
         }
 
@@ -121,8 +162,8 @@ impl ReassemblyContext {
     }
 
     /// Check if this context has timed out.
-    fn is_timed_out(&self, timeout: Duration) -> bool {
-        self.created_at.elapsed() > timeout
+    fn is_timed_out(&self, timeout: Duration, now: Instant) -> bool {
+        now.saturating_duration_since(self.created_at) > timeout
     }
 }
 
@@ -135,6 +176,18 @@ pub struct TpReassembler {
     contexts: HashMap<ReassemblyKey, ReassemblyContext>,
     /// Timeout for reassembly.
     timeout: Duration,
+    /// Clock used for reassembly timeouts.
+    clock: Arc<dyn Clock>,
+    /// Maximum number of concurrent contexts (None = unlimited).
+    max_contexts: Option<usize>,
+    /// Policy applied when the context or byte limit is reached.
+    limit_policy: ReassemblyLimitPolicy,
+    /// Maximum payload bytes buffered across all contexts (None = unlimited).
+    global_byte_limit: Option<usize>,
+    /// Payload bytes buffered across all contexts.
+    buffered_bytes: usize,
+    /// When timed-out contexts were last swept.
+    last_cleanup: Instant,
 }
 
 impl TpReassembler {
@@ -145,54 +198,236 @@ impl TpReassembler {
 
     /// Create a new reassembler with custom timeout.
     pub fn with_timeout(timeout: Duration) -> Self {
+        let clock = clock::system();
         Self {
             contexts: HashMap::new(),
             timeout,
+            last_cleanup: clock.now(),
+            clock,
+            max_contexts: None,
+            limit_policy: ReassemblyLimitPolicy::default(),
+            global_byte_limit: None,
+            buffered_bytes: 0,
         }
     }
 
+    /// Limit the number of concurrent reassembly contexts.
+    pub fn with_max_contexts(mut self, max_contexts: usize) -> Self {
+        self.max_contexts = Some(max_contexts);
+        self
+    }
+
+    /// Limit the total payload bytes buffered across all reassembly contexts.
+    ///
+    /// Provides a hard memory ceiling for untrusted input. When a segment
+    /// would exceed the limit, the [`ReassemblyLimitPolicy`] decides whether
+    /// it is rejected or the oldest other reassemblies are evicted.
+    pub fn with_global_byte_limit(mut self, bytes: usize) -> Self {
+        self.global_byte_limit = Some(bytes);
+        self
+    }
+
+    /// Set the policy applied when the context or byte limit is reached.
+    pub fn with_limit_policy(mut self, policy: ReassemblyLimitPolicy) -> Self {
+        self.limit_policy = policy;
+        self
+    }
+
+    /// Set the clock used for reassembly timeouts.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.last_cleanup = clock.now();
+        self.clock = clock;
+    }
+
     /// Feed a TP segment to the reassembler.
     ///
     /// Returns `Some(message)` if reassembly is complete, `None` if more segments are needed.
     pub fn feed(&mut self, segment: TpSegment) -> Result<Option<SomeIpMessage>> {
+        let byte_offset = segment.tp_header.byte_offset();
+        if byte_offset.saturating_add(segment.payload.len()) > MAX_TP_PAYLOAD {
+            return Err(SomeIpError::TpOffsetOutOfRange {
+                byte_offset,
+                max: MAX_TP_PAYLOAD,
+            });
+        }
+
         let key = ReassemblyKey::from_header(&segment.header);
 
+        if let Some(max) = self.max_contexts {
+            if !self.contexts.contains_key(&key) && self.contexts.len() >= max {
+                match self.limit_policy {
+                    ReassemblyLimitPolicy::Reject => {
+                        return Err(SomeIpError::TooManyReassemblies { max });
+                    }
+                    ReassemblyLimitPolicy::EvictOldest => {
+                        let oldest = self
+                            .contexts
+                            .iter()
+                            .min_by_key(|(_, ctx)| ctx.created_at)
+                            .map(|(key, _)| *key);
+                        if let Some(oldest) = oldest {
+                            self.remove_context(oldest);
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(limit) = self.global_byte_limit {
+            self.enforce_byte_limit(key, &segment, limit)?;
+        }
+
         // Get or create context
+        let now = self.clock.now();
         let context = self.contexts.entry(key).or_insert_with(|| {
-            ReassemblyContext::new(segment.header.clone())
+            ReassemblyContext::new(segment.header.clone(), now)
         });
 
         // Add segment
+        self.buffered_bytes -= context.buffered_bytes;
         context.add_segment(&segment);
+        self.buffered_bytes += context.buffered_bytes;
 
         // Check if complete
         if context.is_complete() {
             let message = context.assemble()?;
-            self.contexts.remove(&key);
+            self.remove_context(key);
             return Ok(Some(message));
         }
 
         Ok(None)
     }
 
+    /// Make room for a segment under the global byte limit.
+    fn enforce_byte_limit(
+        &mut self,
+        key: ReassemblyKey,
+        segment: &TpSegment,
+        limit: usize,
+    ) -> Result<()> {
+        // A retransmitted segment replaces the one buffered at its offset
+        let replaced = self
+            .contexts
+            .get(&key)
+            .and_then(|ctx| ctx.segments.get(&segment.tp_header.offset))
+            .map_or(0, |payload| payload.len());
+
+        loop {
+            let needed = (self.buffered_bytes - replaced).saturating_add(segment.payload.len());
+            if needed <= limit {
+                return Ok(());
+            }
+            if self.limit_policy == ReassemblyLimitPolicy::Reject {
+                return Err(SomeIpError::TpMemoryLimit { limit });
+            }
+
+            let oldest = self
+                .contexts
+                .iter()
+                .filter(|(other, _)| **other != key)
+                .min_by_key(|(_, ctx)| ctx.created_at)
+                .map(|(other, _)| *other);
+            match oldest {
+                Some(oldest) => {
+                    self.remove_context(oldest);
+                }
+                None => return Err(SomeIpError::TpMemoryLimit { limit }),
+            }
+        }
+    }
+
+    /// Remove a context, releasing its bytes from the running total.
+    fn remove_context(&mut self, key: ReassemblyKey) -> bool {
+        match self.contexts.remove(&key) {
+            Some(context) => {
+                self.buffered_bytes -= context.buffered_bytes;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Get the total payload bytes buffered across all active contexts.
+    pub fn buffered_bytes(&self) -> usize {
+        self.buffered_bytes
+    }
+
     /// Clean up timed-out reassembly contexts.
     ///
     /// Returns the number of contexts removed.
     pub fn cleanup(&mut self) -> usize {
         let timeout = self.timeout;
+        let now = self.clock.now();
         let before = self.contexts.len();
-        self.contexts.retain(|_, ctx| !ctx.is_timed_out(timeout));
+        let buffered_bytes = &mut self.buffered_bytes;
+        self.contexts.retain(|_, ctx| {
+            let timed_out = ctx.is_timed_out(timeout, now);
+            if timed_out {
+                *buffered_bytes -= ctx.buffered_bytes;
+            }
+            !timed_out
+        });
+        self.last_cleanup = now;
         before - self.contexts.len()
     }
 
+    /// Clean up timed-out contexts if a timeout interval passed since the last sweep.
+    ///
+    /// Cheap enough to call before every receive. Returns the number of
+    /// contexts removed.
+    pub fn cleanup_if_due(&mut self) -> usize {
+        if self.clock.now().saturating_duration_since(self.last_cleanup) < self.timeout {
+            return 0;
+        }
+        self.cleanup()
+    }
+
     /// Get the number of active reassembly contexts.
     pub fn active_contexts(&self) -> usize {
         self.contexts.len()
     }
 
+    /// Get the byte ranges still missing for an active reassembly.
+    ///
+    /// Ranges are half-open `(start, end)` byte offsets into the payload.
+    /// Gaps after the last received segment are only reported once the final
+    /// segment has been seen. Returns `None` if there is no such reassembly.
+    pub fn missing_ranges(&self, key: ReassemblyKey) -> Option<Vec<(u32, u32)>> {
+        self.contexts.get(&key).map(ReassemblyContext::missing_ranges)
+    }
+
+    /// Get the keys of all active reassembly contexts.
+    pub fn active_keys(&self) -> Vec<ReassemblyKey> {
+        self.contexts.keys().copied().collect()
+    }
+
+    /// Cancel a single reassembly.
+    ///
+    /// Returns `true` if a context was removed.
+    pub fn cancel(&mut self, key: ReassemblyKey) -> bool {
+        self.remove_context(key)
+    }
+
+    /// Cancel all reassemblies for a service.
+    ///
+    /// Returns the number of contexts removed.
+    pub fn cancel_for_service(&mut self, service_id: ServiceId) -> usize {
+        let before = self.contexts.len();
+        let buffered_bytes = &mut self.buffered_bytes;
+        self.contexts.retain(|key, ctx| {
+            let cancelled = key.service_id == service_id;
+            if cancelled {
+                *buffered_bytes -= ctx.buffered_bytes;
+            }
+            !cancelled
+        });
+        before - self.contexts.len()
+    }
+
     /// Clear all reassembly contexts.
     pub fn clear(&mut self) {
         self.contexts.clear();
+        self.buffered_bytes = 0;
     }
 }
 
@@ -205,16 +440,20 @@ impl Default for TpReassembler {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::header::{MethodId, ServiceId};
+    use crate::header::{MethodId, ServiceId, SessionId};
+    use crate::tp::header::TpHeader;
     use crate::tp::segment::segment_message;
+    use crate::types::{MessageType, ReturnCode};
 
     #[test]
     fn test_reassembly_key() {
-        let mut header = SomeIpHeader::default();
-        header.service_id = ServiceId(0x1234);
-        header.method_id = MethodId(0x0001);
-        header.client_id = ClientId(0x0100);
-        header.session_id = SessionId(0x0001);
+        let header = SomeIpHeader {
+            service_id: ServiceId(0x1234),
+            method_id: MethodId(0x0001),
+            client_id: ClientId(0x0100),
+            session_id: SessionId(0x0001),
+            ..Default::default()
+        };
 
         let key = ReassemblyKey::from_header(&header);
 
@@ -274,6 +513,56 @@ mod tests {
         assert_eq!(reassembled.payload.as_ref(), expected_payload.as_slice());
     }
 
+    #[test]
+    fn test_reassemble_error_response_return_code() {
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .session_id(SessionId(0x0042))
+            .build();
+        let msg = request
+            .create_error_response(ReturnCode::NotReady)
+            .payload_vec(vec![0x5A; 3000])
+            .build();
+
+        let segments = segment_message(&msg, 1392);
+        for segment in &segments {
+            assert_eq!(segment.header.message_type, MessageType::TpError);
+            assert_eq!(segment.header.return_code, ReturnCode::NotReady);
+        }
+
+        // The last segment arrives first and provides the base header
+        let mut reassembler = TpReassembler::new();
+        assert!(reassembler.feed(segments[2].clone()).unwrap().is_none());
+        assert!(reassembler.feed(segments[1].clone()).unwrap().is_none());
+        let reassembled = reassembler.feed(segments[0].clone()).unwrap().unwrap();
+
+        assert_eq!(reassembled.header.message_type, MessageType::Error);
+        assert_eq!(reassembled.header.return_code, ReturnCode::NotReady);
+        assert_eq!(reassembled.header.session_id, SessionId(0x0042));
+        assert_eq!(reassembled, msg);
+    }
+
+    #[test]
+    fn test_missing_ranges() {
+        let msg = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .payload_vec(vec![0xAA; 3000])
+            .build();
+        let segments = segment_message(&msg, 1392);
+        let key = ReassemblyKey::from_header(&segments[0].header);
+
+        let mut reassembler = TpReassembler::new();
+        assert_eq!(reassembler.missing_ranges(key), None);
+
+        assert!(reassembler.feed(segments[0].clone()).unwrap().is_none());
+        assert_eq!(reassembler.missing_ranges(key), Some(vec![]));
+
+        // Segment 1 covers bytes 1392..2784
+        assert!(reassembler.feed(segments[2].clone()).unwrap().is_none());
+        assert_eq!(reassembler.missing_ranges(key), Some(vec![(1392, 2784)]));
+
+        assert!(reassembler.feed(segments[1].clone()).unwrap().is_some());
+        assert_eq!(reassembler.missing_ranges(key), None);
+    }
+
     #[test]
     fn test_multiple_concurrent_reassemblies() {
         let expected_payload1: Vec<u8> = vec![0xAAu8; 3000];
@@ -314,4 +603,192 @@ mod tests {
 
         assert_eq!(reassembler.active_contexts(), 0);
     }
+
+    #[test]
+    fn test_reassemble_preserves_type_and_return_code() {
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001)).build();
+        let error = request
+            .create_error_response(ReturnCode::NotReady)
+            .payload_vec(vec![0xEEu8; 3000])
+            .build();
+
+        let mut reassembler = TpReassembler::new();
+        let mut result = None;
+        for segment in segment_message(&error, 1392) {
+            assert!(segment.header.message_type.is_tp());
+            result = reassembler.feed(segment).unwrap();
+        }
+
+        let reassembled = result.unwrap();
+        assert_eq!(reassembled.header.message_type, MessageType::Error);
+        assert_eq!(reassembled.header.return_code, ReturnCode::NotReady);
+        assert_eq!(reassembled, error);
+    }
+
+    #[test]
+    fn test_reject_out_of_range_offset() {
+        let msg = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .payload_vec(vec![0u8; 3000])
+            .build();
+        let mut segment = segment_message(&msg, 1392).remove(1);
+        segment.tp_header = TpHeader::new(MAX_TP_PAYLOAD as u32 / 16, true);
+
+        let mut reassembler = TpReassembler::new();
+        let result = reassembler.feed(segment);
+        assert!(matches!(
+            result,
+            Err(SomeIpError::TpOffsetOutOfRange { max: MAX_TP_PAYLOAD, .. })
+        ));
+        assert_eq!(reassembler.active_contexts(), 0);
+    }
+
+    #[test]
+    fn test_max_contexts() {
+        use crate::clock::MockClock;
+
+        let segments: Vec<_> = (1..=3u16)
+            .map(|session| {
+                let msg = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+                    .session_id(SessionId(session))
+                    .payload_vec(vec![0u8; 3000])
+                    .build();
+                segment_message(&msg, 1392).remove(0)
+            })
+            .collect();
+
+        let mut reassembler = TpReassembler::new().with_max_contexts(2);
+        reassembler.feed(segments[0].clone()).unwrap();
+        reassembler.feed(segments[1].clone()).unwrap();
+        assert!(matches!(
+            reassembler.feed(segments[2].clone()),
+            Err(SomeIpError::TooManyReassemblies { max: 2 })
+        ));
+        // Segments for existing contexts are still accepted
+        reassembler.feed(segments[0].clone()).unwrap();
+        assert_eq!(reassembler.active_contexts(), 2);
+
+        let clock = MockClock::new();
+        let mut reassembler = TpReassembler::new()
+            .with_max_contexts(2)
+            .with_limit_policy(ReassemblyLimitPolicy::EvictOldest);
+        reassembler.set_clock(Arc::new(clock.clone()));
+        for segment in &segments {
+            reassembler.feed(segment.clone()).unwrap();
+            clock.advance(Duration::from_millis(10));
+        }
+
+        let mut sessions: Vec<_> = reassembler.active_keys().iter().map(|k| k.session_id).collect();
+        sessions.sort_by_key(|session| session.0);
+        assert_eq!(sessions, vec![SessionId(2), SessionId(3)]);
+    }
+
+    #[test]
+    fn test_global_byte_limit() {
+        use crate::clock::MockClock;
+
+        let segments: Vec<_> = (1..=2u16)
+            .map(|session| {
+                let msg = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+                    .session_id(SessionId(session))
+                    .payload_vec(vec![0u8; 3000])
+                    .build();
+                segment_message(&msg, 1392)
+            })
+            .collect();
+
+        // 1392 + 1392 bytes exceed the limit of 2000
+        let mut reassembler = TpReassembler::new().with_global_byte_limit(2000);
+        reassembler.feed(segments[0][0].clone()).unwrap();
+        assert_eq!(reassembler.buffered_bytes(), 1392);
+        assert!(matches!(
+            reassembler.feed(segments[1][0].clone()),
+            Err(SomeIpError::TpMemoryLimit { limit: 2000 })
+        ));
+        assert_eq!(reassembler.active_contexts(), 1);
+        // Retransmitting a buffered segment does not count twice
+        reassembler.feed(segments[0][0].clone()).unwrap();
+        assert_eq!(reassembler.buffered_bytes(), 1392);
+
+        let clock = MockClock::new();
+        let mut reassembler = TpReassembler::new()
+            .with_global_byte_limit(2000)
+            .with_limit_policy(ReassemblyLimitPolicy::EvictOldest);
+        reassembler.set_clock(Arc::new(clock.clone()));
+        reassembler.feed(segments[0][0].clone()).unwrap();
+        clock.advance(Duration::from_millis(10));
+        reassembler.feed(segments[1][0].clone()).unwrap();
+
+        let keys = reassembler.active_keys();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].session_id, SessionId(2));
+        assert_eq!(reassembler.buffered_bytes(), 1392);
+
+        // A segment that cannot fit even after evicting everything else
+        assert!(matches!(
+            reassembler.feed(segments[1][1].clone()),
+            Err(SomeIpError::TpMemoryLimit { limit: 2000 })
+        ));
+    }
+
+    #[test]
+    fn test_cancel_for_service() {
+        let msg1 = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .payload_vec(vec![0xAAu8; 3000])
+            .build();
+        let msg2 = SomeIpMessage::request(ServiceId(0x5678), MethodId(0x0001))
+            .payload_vec(vec![0xBBu8; 3000])
+            .build();
+
+        let segments1 = segment_message(&msg1, 1392);
+        let segments2 = segment_message(&msg2, 1392);
+
+        let mut reassembler = TpReassembler::new();
+        reassembler.feed(segments1[0].clone()).unwrap();
+        reassembler.feed(segments2[0].clone()).unwrap();
+        assert_eq!(reassembler.active_contexts(), 2);
+
+        assert_eq!(reassembler.buffered_bytes(), 2 * 1392);
+        assert_eq!(reassembler.cancel_for_service(ServiceId(0x1234)), 1);
+        assert_eq!(reassembler.cancel_for_service(ServiceId(0x1234)), 0);
+        assert_eq!(reassembler.buffered_bytes(), 1392);
+
+        let keys = reassembler.active_keys();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].service_id, ServiceId(0x5678));
+
+        // The surviving reassembly still completes
+        reassembler.feed(segments2[1].clone()).unwrap();
+        let result = reassembler.feed(segments2[2].clone()).unwrap();
+        assert_eq!(result.unwrap().payload.as_ref(), &[0xBBu8; 3000][..]);
+        assert_eq!(reassembler.buffered_bytes(), 0);
+
+        reassembler.feed(segments1[0].clone()).unwrap();
+        let key = ReassemblyKey::from_header(&segments1[0].header);
+        assert!(reassembler.cancel(key));
+        assert!(!reassembler.cancel(key));
+        assert_eq!(reassembler.active_contexts(), 0);
+        assert_eq!(reassembler.buffered_bytes(), 0);
+    }
+
+    #[test]
+    fn test_reassembly_timeout_with_mock_clock() {
+        use crate::clock::MockClock;
+
+        let msg = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .payload_vec(vec![0u8; 3000])
+            .build();
+        let segments = segment_message(&msg, 1392);
+
+        let clock = MockClock::new();
+        let mut reassembler = TpReassembler::with_timeout(Duration::from_secs(5));
+        reassembler.set_clock(Arc::new(clock.clone()));
+
+        reassembler.feed(segments[0].clone()).unwrap();
+        assert_eq!(reassembler.cleanup(), 0);
+
+        clock.advance(Duration::from_secs(6));
+        assert_eq!(reassembler.cleanup(), 1);
+        assert_eq!(reassembler.active_contexts(), 0);
+        assert_eq!(reassembler.buffered_bytes(), 0);
+    }
 }
@@ -11,11 +11,17 @@ use super::header::{TpHeader, TP_HEADER_SIZE};
 
 /// Default maximum segment payload size.
 ///
-/// This is calculated as: MTU (1500) - IP header (20) - UDP header (8)
-/// - SOME/IP header (16) - TP header (4) = 1452, rounded down to 1392
+/// This is calculated as: MTU (1500) - IP header (20) - UDP header (8) -
+/// SOME/IP header (16) - TP header (4) = 1452, rounded down to 1392
 /// for alignment to 16-byte boundaries.
 pub const DEFAULT_MAX_SEGMENT_PAYLOAD: usize = 1392;
 
+#[cfg(test)]
+thread_local! {
+    /// Number of `segment_message` calls on the current thread.
+    pub(crate) static SEGMENT_CALLS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
 /// A single TP segment.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TpSegment {
@@ -90,6 +96,9 @@ impl TpSegment {
 ///
 /// Returns an empty vector if the message doesn't need segmentation.
 pub fn segment_message(message: &SomeIpMessage, max_segment_payload: usize) -> Vec<TpSegment> {
+    #[cfg(test)]
+    SEGMENT_CALLS.with(|calls| calls.set(calls.get() + 1));
+
     let payload = &message.payload;
 
     // No segmentation needed for small messages
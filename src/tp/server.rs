@@ -5,13 +5,14 @@ use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
 use std::time::Duration;
 
 use crate::error::Result;
-use crate::header::HEADER_SIZE;
+use crate::header::{ServiceId, HEADER_SIZE};
 use crate::message::SomeIpMessage;
 use crate::types::ReturnCode;
 
-use super::header::TP_HEADER_SIZE;
-use super::reassembly::TpReassembler;
-use super::segment::{segment_message, TpSegment, DEFAULT_MAX_SEGMENT_PAYLOAD};
+use super::reassembly::{ReassemblyKey, TpReassembler};
+use super::segment::{
+    needs_segmentation, segment_message, TpSegment, DEFAULT_MAX_SEGMENT_PAYLOAD,
+};
 
 /// Maximum UDP datagram size for TP messages.
 const MAX_DATAGRAM_SIZE: usize = 1500;
@@ -71,13 +72,30 @@ impl TpUdpServer {
     ///
     /// Returns the complete message and the sender address.
     pub fn receive(&mut self) -> Result<(SomeIpMessage, SocketAddr)> {
+        self.receive_inner(false)
+    }
+
+    /// Receive a message, sweeping timed-out reassemblies along the way.
+    ///
+    /// Before each blocking read, stale contexts are removed once per
+    /// reassembly timeout interval. Prefer this over [`receive`](Self::receive)
+    /// in a receive loop that never calls [`cleanup`](Self::cleanup).
+    pub fn receive_with_cleanup(&mut self) -> Result<(SomeIpMessage, SocketAddr)> {
+        self.receive_inner(true)
+    }
+
+    /// Receive loop shared by the receive variants.
+    fn receive_inner(&mut self, lazy_cleanup: bool) -> Result<(SomeIpMessage, SocketAddr)> {
         loop {
+            if lazy_cleanup {
+                self.reassembler.cleanup_if_due();
+            }
+
             let (len, addr) = self.socket.recv_from(&mut self.recv_buffer)?;
             let data = &self.recv_buffer[..len];
 
-            // Check if this is a TP message
-            if len >= HEADER_SIZE + TP_HEADER_SIZE {
-                // Parse header to check message type
+            // Check if this is a TP message, regardless of its size
+            if len >= HEADER_SIZE {
                 let header = crate::header::SomeIpHeader::from_bytes(&data[..HEADER_SIZE])?;
 
                 if header.message_type.is_tp() {
@@ -101,18 +119,17 @@ impl TpUdpServer {
 
     /// Send a message to an address, segmenting if necessary.
     pub fn send_to(&self, message: &SomeIpMessage, addr: SocketAddr) -> Result<()> {
-        let segments = segment_message(message, self.max_segment_payload);
-
-        if segments.is_empty() {
+        if !needs_segmentation(message, self.max_segment_payload) {
             // Small message, send directly
             let data = message.to_bytes();
             self.socket.send_to(&data, addr)?;
-        } else {
-            // Large message, send as segments
-            for segment in segments {
-                let data = segment.to_bytes();
-                self.socket.send_to(&data, addr)?;
-            }
+            return Ok(());
+        }
+
+        // Large message, send as segments
+        for segment in segment_message(message, self.max_segment_payload) {
+            let data = segment.to_bytes();
+            self.socket.send_to(&data, addr)?;
         }
 
         Ok(())
@@ -145,7 +162,8 @@ impl TpUdpServer {
 
     /// Clean up timed-out reassembly contexts.
     ///
-    /// Should be called periodically to free resources.
+    /// Should be called periodically to free resources, unless
+    /// [`receive_with_cleanup`](Self::receive_with_cleanup) is used.
     pub fn cleanup(&mut self) -> usize {
         self.reassembler.cleanup()
     }
@@ -155,6 +173,16 @@ impl TpUdpServer {
         self.reassembler.active_contexts()
     }
 
+    /// Cancel a single in-flight reassembly.
+    pub fn cancel_reassembly(&mut self, key: ReassemblyKey) -> bool {
+        self.reassembler.cancel(key)
+    }
+
+    /// Cancel all in-flight reassemblies for a service.
+    pub fn cancel_reassemblies_for_service(&mut self, service_id: ServiceId) -> usize {
+        self.reassembler.cancel_for_service(service_id)
+    }
+
     /// Join a multicast group.
     pub fn join_multicast_v4(
         &self,
@@ -191,6 +219,91 @@ mod tests {
         assert!(server.local_addr().port() > 0);
     }
 
+    #[test]
+    fn test_tp_server_receive_with_cleanup() {
+        use crate::clock::MockClock;
+        use std::net::UdpSocket;
+        use std::sync::Arc;
+
+        let mut server = TpUdpServer::bind("127.0.0.1:0").unwrap();
+        server.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        server.set_reassembly_timeout(Duration::from_secs(5));
+        let clock = MockClock::new();
+        server.reassembler.set_clock(Arc::new(clock.clone()));
+        let server_addr = server.local_addr();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        // A first segment whose remaining segments never arrive
+        let message = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .payload_vec(vec![0; 32])
+            .build();
+        let segments = segment_message(&message, 16);
+        let event = SomeIpMessage::notification(ServiceId(0x1234), MethodId::event(0x0001))
+            .payload(b"event".as_slice())
+            .build();
+
+        sender.send_to(&segments[0].to_bytes(), server_addr).unwrap();
+        sender.send_to(&event.to_bytes(), server_addr).unwrap();
+        server.receive_with_cleanup().unwrap();
+        assert_eq!(server.active_reassemblies(), 1);
+
+        // Not yet timed out
+        clock.advance(Duration::from_secs(3));
+        sender.send_to(&event.to_bytes(), server_addr).unwrap();
+        server.receive_with_cleanup().unwrap();
+        assert_eq!(server.active_reassemblies(), 1);
+
+        clock.advance(Duration::from_secs(3));
+        sender.send_to(&event.to_bytes(), server_addr).unwrap();
+        let (received, _) = server.receive_with_cleanup().unwrap();
+        assert_eq!(received.payload.as_ref(), b"event");
+        assert_eq!(server.active_reassemblies(), 0);
+    }
+
+    #[test]
+    fn test_tp_server_small_final_segments() {
+        use super::super::header::TpHeader;
+        use bytes::Bytes;
+        use std::net::UdpSocket;
+
+        let mut server = TpUdpServer::bind("127.0.0.1:0").unwrap();
+        server.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let server_addr = server.local_addr();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        // 24 bytes split into 16 + 8, with a regular message in between
+        let message = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .payload_vec((0..24).collect())
+            .build();
+        let segments = segment_message(&message, 16);
+        assert_eq!(segments[1].payload.len(), 8);
+
+        let regular = SomeIpMessage::notification(ServiceId(0x1234), MethodId::event(0x0001))
+            .payload(b"event".as_slice())
+            .build();
+        sender.send_to(&segments[0].to_bytes(), server_addr).unwrap();
+        sender.send_to(&regular.to_bytes(), server_addr).unwrap();
+        sender.send_to(&segments[1].to_bytes(), server_addr).unwrap();
+
+        let (received, _) = server.receive().unwrap();
+        assert_eq!(received.payload.as_ref(), b"event");
+        let (received, _) = server.receive().unwrap();
+        assert_eq!(received.header.message_type, crate::types::MessageType::Request);
+        assert_eq!(received.payload.as_ref(), message.payload.as_ref());
+
+        // A final segment may carry no payload at all
+        let mut header = segments[0].header.clone();
+        header.session_id = crate::header::SessionId(0x0002);
+        let first = TpSegment::new(header.clone(), TpHeader::first(true), Bytes::from(vec![7; 16]));
+        header.length = 8 + 4;
+        let last = TpSegment::new(header, TpHeader::from_byte_offset(16, false), Bytes::new());
+        sender.send_to(&first.to_bytes(), server_addr).unwrap();
+        sender.send_to(&last.to_bytes(), server_addr).unwrap();
+
+        let (received, _) = server.receive().unwrap();
+        assert_eq!(received.payload.as_ref(), [7; 16].as_slice());
+    }
+
     #[test]
     fn test_tp_client_server_small_message() {
         use super::super::client::TpUdpClient;
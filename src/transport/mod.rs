@@ -6,5 +6,45 @@
 pub mod tcp;
 pub mod udp;
 
+#[cfg(target_os = "linux")]
+mod pktinfo;
+
 pub use tcp::{TcpClient, TcpConnection, TcpServer};
 pub use udp::{UdpClient, UdpServer};
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::error::{Result, SomeIpError};
+
+/// Turn an I/O error from a socket timeout into [`SomeIpError::Timeout`].
+pub(crate) fn map_io_timeout(err: SomeIpError) -> SomeIpError {
+    match err {
+        SomeIpError::Io(e)
+            if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) =>
+        {
+            SomeIpError::Timeout
+        }
+        err => err,
+    }
+}
+
+/// Call `read` until it yields a value or `deadline` passes.
+///
+/// `read` gets the time left, to use as its socket read timeout, and returns
+/// `Ok(None)` to keep waiting. Running out of time or a read timing out
+/// gives [`SomeIpError::Timeout`].
+pub(crate) fn read_until<T>(
+    deadline: Instant,
+    mut read: impl FnMut(Duration) -> Result<Option<T>>,
+) -> Result<T> {
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(SomeIpError::Timeout);
+        }
+        if let Some(value) = read(remaining).map_err(map_io_timeout)? {
+            return Ok(value);
+        }
+    }
+}
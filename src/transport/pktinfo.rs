@@ -0,0 +1,144 @@
+//! `IP_PKTINFO` support for UDP sockets (Linux only).
+//!
+//! Reports the local address a datagram arrived on and lets replies use it as
+//! their source address, so multi-homed hosts answer from the right interface.
+
+use std::io;
+use std::mem;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::os::fd::AsRawFd;
+use std::ptr;
+
+/// Control buffer size in 8-byte words, large enough for one `in_pktinfo`.
+const CONTROL_WORDS: usize = 8;
+
+/// Enable `IP_PKTINFO` reporting on a socket.
+pub(crate) fn enable(socket: &UdpSocket) -> io::Result<()> {
+    let on: libc::c_int = 1;
+    // SAFETY: the option value points to a live c_int of the given size.
+    let ret = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_IP,
+            libc::IP_PKTINFO,
+            (&on as *const libc::c_int).cast(),
+            mem::size_of_val(&on) as libc::socklen_t,
+        )
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Receive a datagram along with the local IPv4 address it arrived on.
+///
+/// This is `ipi_spec_dst`, a unicast address of the receiving interface, even
+/// for datagrams sent to a multicast group or broadcast address.
+pub(crate) fn recv_from(
+    socket: &UdpSocket,
+    buf: &mut [u8],
+) -> io::Result<(usize, SocketAddr, Option<Ipv4Addr>)> {
+    // SAFETY: sockaddr_in and msghdr are plain C structs for which all-zero is valid.
+    let mut source: libc::sockaddr_in = unsafe { mem::zeroed() };
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr().cast(),
+        iov_len: buf.len(),
+    };
+    let mut control = [0u64; CONTROL_WORDS];
+
+    msg.msg_name = (&mut source as *mut libc::sockaddr_in).cast();
+    msg.msg_namelen = mem::size_of::<libc::sockaddr_in>() as libc::socklen_t;
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = mem::size_of_val(&control) as _;
+
+    // SAFETY: every pointer in msg refers to a live buffer of the stated length.
+    let len = unsafe { libc::recvmsg(socket.as_raw_fd(), &mut msg, 0) };
+    if len < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    if source.sin_family != libc::AF_INET as libc::sa_family_t {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "IP_PKTINFO is only supported on IPv4 sockets",
+        ));
+    }
+
+    let mut destination = None;
+    // SAFETY: the kernel filled msg_control with well-formed control messages
+    // and the CMSG_* macros stay within msg_controllen.
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::IPPROTO_IP && (*cmsg).cmsg_type == libc::IP_PKTINFO {
+                let info: libc::in_pktinfo = ptr::read_unaligned(libc::CMSG_DATA(cmsg).cast());
+                destination = Some(Ipv4Addr::from(u32::from_be(info.ipi_spec_dst.s_addr)));
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+
+    let addr = SocketAddrV4::new(
+        Ipv4Addr::from(u32::from_be(source.sin_addr.s_addr)),
+        u16::from_be(source.sin_port),
+    );
+    Ok((len as usize, SocketAddr::V4(addr), destination))
+}
+
+/// Send a datagram using `source` as the local address.
+pub(crate) fn send_from(
+    socket: &UdpSocket,
+    data: &[u8],
+    addr: SocketAddrV4,
+    source: Ipv4Addr,
+) -> io::Result<usize> {
+    // SAFETY: sockaddr_in and msghdr are plain C structs for which all-zero is valid.
+    let mut destination: libc::sockaddr_in = unsafe { mem::zeroed() };
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    destination.sin_family = libc::AF_INET as libc::sa_family_t;
+    destination.sin_port = addr.port().to_be();
+    destination.sin_addr.s_addr = u32::from(*addr.ip()).to_be();
+
+    let mut iov = libc::iovec {
+        iov_base: data.as_ptr() as *mut libc::c_void,
+        iov_len: data.len(),
+    };
+    let mut control = [0u64; CONTROL_WORDS];
+    let info_size = mem::size_of::<libc::in_pktinfo>() as u32;
+
+    msg.msg_name = (&mut destination as *mut libc::sockaddr_in).cast();
+    msg.msg_namelen = mem::size_of::<libc::sockaddr_in>() as libc::socklen_t;
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    // SAFETY: CMSG_SPACE only computes a size.
+    msg.msg_controllen = unsafe { libc::CMSG_SPACE(info_size) } as _;
+
+    let info = libc::in_pktinfo {
+        ipi_ifindex: 0,
+        ipi_spec_dst: libc::in_addr {
+            s_addr: u32::from(source).to_be(),
+        },
+        ipi_addr: libc::in_addr { s_addr: 0 },
+    };
+    // SAFETY: the control buffer holds CMSG_SPACE(in_pktinfo) bytes, so the
+    // first header and its data fit.
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::IPPROTO_IP;
+        (*cmsg).cmsg_type = libc::IP_PKTINFO;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(info_size) as _;
+        ptr::write_unaligned(libc::CMSG_DATA(cmsg).cast(), info);
+    }
+
+    // SAFETY: every pointer in msg refers to a live buffer of the stated length.
+    let len = unsafe { libc::sendmsg(socket.as_raw_fd(), &msg, 0) };
+    if len < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(len as usize)
+}
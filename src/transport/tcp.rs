@@ -1,24 +1,41 @@
 //! TCP transport for SOME/IP.
 
-use std::io::{self, BufReader, BufWriter};
+use std::collections::VecDeque;
+use std::io::{self, BufReader, BufWriter, Read};
 use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
-use std::sync::atomic::{AtomicU16, Ordering};
-use std::time::Duration;
+use std::sync::atomic::{AtomicU16, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 
-use crate::codec::{read_message, write_message};
+use bytes::Bytes;
+use socket2::SockRef;
+
+use crate::codec::{read_message, read_message_streaming, read_payload_streaming, write_message};
 use crate::error::{Result, SomeIpError};
-use crate::header::{ClientId, SessionId};
+use crate::header::{ClientId, SessionId, SomeIpHeader, HEADER_SIZE};
 use crate::message::SomeIpMessage;
+use crate::types::ReturnCode;
+
+use super::read_until;
 
 /// Default TCP port for SOME/IP.
 pub const DEFAULT_PORT: u16 = 30490;
 
+/// Largest header length field accepted while resynchronization is enabled.
+const RESYNC_MAX_LENGTH: u32 = 1024 * 1024;
+
+/// Message IDs of the client and server magic cookies.
+const MAGIC_COOKIE_MESSAGE_IDS: [u32; 2] = [0xFFFF_0000, 0xFFFF_8000];
+
 /// A TCP connection wrapper with SOME/IP framing.
 #[derive(Debug)]
 pub struct TcpConnection {
     reader: BufReader<TcpStream>,
     writer: BufWriter<TcpStream>,
     peer_addr: SocketAddr,
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+    resync_on_error: bool,
+    resynced: Option<SomeIpMessage>,
 }
 
 impl TcpConnection {
@@ -31,6 +48,10 @@ impl TcpConnection {
             reader,
             writer,
             peer_addr,
+            bytes_read: AtomicU64::new(0),
+            bytes_written: AtomicU64::new(0),
+            resync_on_error: false,
+            resynced: None,
         })
     }
 
@@ -49,23 +70,190 @@ impl TcpConnection {
         self.writer.get_ref().set_write_timeout(timeout)
     }
 
+    /// Get the read timeout.
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        self.writer.get_ref().read_timeout()
+    }
+
     /// Set TCP nodelay option.
     pub fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
         self.writer.get_ref().set_nodelay(nodelay)
     }
 
+    /// Set the SO_LINGER option, controlling how long close waits for unsent data.
+    pub fn set_linger(&self, linger: Option<Duration>) -> io::Result<()> {
+        SockRef::from(self.writer.get_ref()).set_linger(linger)
+    }
+
+    /// Get the SO_LINGER option.
+    pub fn linger(&self) -> io::Result<Option<Duration>> {
+        SockRef::from(self.writer.get_ref()).linger()
+    }
+
+    /// Get the number of message bytes (header + payload) read so far.
+    pub fn read_bytes(&self) -> u64 {
+        self.bytes_read.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of message bytes (header + payload) written so far.
+    pub fn written_bytes(&self) -> u64 {
+        self.bytes_written.load(Ordering::Relaxed)
+    }
+
+    /// Enable resynchronization after a corrupted header.
+    ///
+    /// When enabled, an invalid header, or one whose length field is below 8 or
+    /// above 1 MiB, makes [`read_message`](Self::read_message) and
+    /// [`read_message_streaming`](Self::read_message_streaming) scan forward for
+    /// the next plausible header (or magic cookie) and return
+    /// [`SomeIpError::Resynchronized`]. The recovered message is returned by the
+    /// next read.
+    pub fn set_resync_on_error(&mut self, resync: bool) {
+        self.resync_on_error = resync;
+    }
+
+    /// Check if resynchronization is enabled.
+    pub fn resync_on_error(&self) -> bool {
+        self.resync_on_error
+    }
+
     /// Read a SOME/IP message from the connection.
     pub fn read_message(&mut self) -> Result<SomeIpMessage> {
-        read_message(&mut self.reader)
+        if let Some(message) = self.resynced.take() {
+            return Ok(message);
+        }
+
+        let message = if self.resync_on_error {
+            let mut header_buf = [0u8; HEADER_SIZE];
+            self.reader.read_exact(&mut header_buf)?;
+            match SomeIpHeader::from_bytes(&header_buf) {
+                Ok(header) if (8..=RESYNC_MAX_LENGTH).contains(&header.length) => {
+                    self.read_payload(header)?
+                }
+                _ => return Err(self.resynchronize(header_buf)),
+            }
+        } else {
+            read_message(&mut self.reader)?
+        };
+
+        self.bytes_read
+            .fetch_add(message.total_size() as u64, Ordering::Relaxed);
+        Ok(message)
+    }
+
+    /// Read the payload following an already parsed header.
+    fn read_payload(&mut self, header: SomeIpHeader) -> Result<SomeIpMessage> {
+        let mut payload = vec![0u8; header.payload_length() as usize];
+        self.reader.read_exact(&mut payload)?;
+        Ok(SomeIpMessage::new(header, payload))
+    }
+
+    /// Slide past a corrupted header until a plausible one is found.
+    fn resynchronize(&mut self, mut window: [u8; HEADER_SIZE]) -> SomeIpError {
+        let mut skipped = 0;
+        loop {
+            let mut byte = [0u8; 1];
+            if let Err(e) = self.reader.read_exact(&mut byte) {
+                return e.into();
+            }
+            window.copy_within(1.., 0);
+            window[HEADER_SIZE - 1] = byte[0];
+            skipped += 1;
+
+            let header = match SomeIpHeader::from_bytes(&window) {
+                Ok(header) if (8..=RESYNC_MAX_LENGTH).contains(&header.length) => header,
+                _ => continue,
+            };
+
+            let message = match self.read_payload(header) {
+                Ok(message) => message,
+                Err(e) => return e,
+            };
+            self.bytes_read
+                .fetch_add(message.total_size() as u64, Ordering::Relaxed);
+
+            // Magic cookies only mark message boundaries
+            if !MAGIC_COOKIE_MESSAGE_IDS.contains(&message.header.message_id()) {
+                self.resynced = Some(message);
+            }
+            return SomeIpError::Resynchronized { skipped };
+        }
+    }
+
+    /// Read a SOME/IP message, streaming its payload to `sink` in chunks.
+    ///
+    /// Returns the header without buffering the whole payload. If `sink`
+    /// fails, the rest of the payload is discarded so the connection stays
+    /// usable. Resynchronization applies as for
+    /// [`read_message`](Self::read_message); a message recovered by it is
+    /// already buffered and is passed to `sink` in one piece.
+    pub fn read_message_streaming(
+        &mut self,
+        mut sink: impl FnMut(&[u8]) -> Result<()>,
+    ) -> Result<SomeIpHeader> {
+        if let Some(message) = self.resynced.take() {
+            sink(&message.payload)?;
+            return Ok(message.header);
+        }
+
+        let header = if self.resync_on_error {
+            let mut header_buf = [0u8; HEADER_SIZE];
+            self.reader.read_exact(&mut header_buf)?;
+            match SomeIpHeader::from_bytes(&header_buf) {
+                Ok(header) if (8..=RESYNC_MAX_LENGTH).contains(&header.length) => {
+                    read_payload_streaming(&mut self.reader, &header, sink)?;
+                    header
+                }
+                _ => return Err(self.resynchronize(header_buf)),
+            }
+        } else {
+            read_message_streaming(&mut self.reader, sink)?
+        };
+        self.bytes_read.fetch_add(
+            (HEADER_SIZE + header.payload_length() as usize) as u64,
+            Ordering::Relaxed,
+        );
+        Ok(header)
     }
 
     /// Write a SOME/IP message to the connection.
     pub fn write_message(&mut self, message: &SomeIpMessage) -> Result<()> {
         write_message(&mut self.writer, message)?;
         self.flush()?;
+        self.bytes_written
+            .fetch_add(message.total_size() as u64, Ordering::Relaxed);
         Ok(())
     }
 
+    /// Send a response to a request.
+    pub fn respond(&mut self, request: &SomeIpMessage, payload: impl Into<Bytes>) -> Result<()> {
+        let response = request.create_response().payload(payload).build();
+        self.write_message(&response)
+    }
+
+    /// Send a response to a request with a specific return code.
+    ///
+    /// The message type stays `Response`, unlike [`respond_error`](Self::respond_error).
+    pub fn respond_with_code(
+        &mut self,
+        request: &SomeIpMessage,
+        return_code: ReturnCode,
+        payload: impl Into<Bytes>,
+    ) -> Result<()> {
+        let response = request
+            .create_response()
+            .return_code(return_code)
+            .payload(payload)
+            .build();
+        self.write_message(&response)
+    }
+
+    /// Send an error response to a request.
+    pub fn respond_error(&mut self, request: &SomeIpMessage, return_code: ReturnCode) -> Result<()> {
+        let response = request.create_error_response(return_code).build();
+        self.write_message(&response)
+    }
+
     /// Flush the write buffer.
     pub fn flush(&mut self) -> io::Result<()> {
         use std::io::Write;
@@ -86,6 +274,7 @@ pub struct TcpClient {
     connection: TcpConnection,
     client_id: ClientId,
     session_counter: AtomicU16,
+    pending: VecDeque<SomeIpMessage>,
 }
 
 impl TcpClient {
@@ -108,6 +297,7 @@ impl TcpClient {
             connection,
             client_id: ClientId(0x0001), // Default client ID
             session_counter: AtomicU16::new(1),
+            pending: VecDeque::new(),
         })
     }
 
@@ -143,15 +333,23 @@ impl TcpClient {
         self.connection.set_write_timeout(timeout)
     }
 
+    /// Set the SO_LINGER option.
+    pub fn set_linger(&self, linger: Option<Duration>) -> io::Result<()> {
+        self.connection.set_linger(linger)
+    }
+
     /// Send a request and wait for a response.
     ///
     /// This method assigns client ID and session ID to the message.
+    /// Responses with session ID 0 (session handling disabled) match any
+    /// session from this client, as long as service and method ID match.
     pub fn call(&mut self, mut message: SomeIpMessage) -> Result<SomeIpMessage> {
         // Assign client and session IDs
         message.header.client_id = self.client_id;
         message.header.session_id = self.next_session_id();
 
         let request_id = message.header.request_id();
+        let (service_id, method_id) = (message.header.service_id, message.header.method_id);
 
         // Send request
         self.connection.write_message(&message)?;
@@ -164,6 +362,14 @@ impl TcpClient {
             if response.header.request_id() == request_id {
                 return Ok(response);
             }
+            if !response.session_handling_active()
+                && response.is_response()
+                && response.header.client_id == self.client_id
+                && response.header.service_id == service_id
+                && response.header.method_id == method_id
+            {
+                return Ok(response);
+            }
 
             // Store other responses (e.g., notifications) for later
             // In a real implementation, you might want a callback mechanism
@@ -178,8 +384,51 @@ impl TcpClient {
     }
 
     /// Receive a message (e.g., notification).
+    ///
+    /// Messages buffered by [`receive_matching`](Self::receive_matching) are
+    /// returned first, in arrival order.
     pub fn receive(&mut self) -> Result<SomeIpMessage> {
-        self.connection.read_message()
+        match self.pending.pop_front() {
+            Some(message) => Ok(message),
+            None => self.connection.read_message(),
+        }
+    }
+
+    /// Receive the next message satisfying `pred`, waiting at most `timeout`.
+    ///
+    /// Non-matching messages are buffered and later returned by
+    /// [`receive`](Self::receive). Returns [`SomeIpError::Timeout`] if no
+    /// matching message arrives in time. A timeout that fires while a message
+    /// is only partially received leaves the connection unusable.
+    pub fn receive_matching(
+        &mut self,
+        pred: impl Fn(&SomeIpMessage) -> bool,
+        timeout: Duration,
+    ) -> Result<SomeIpMessage> {
+        if let Some(pos) = self.pending.iter().position(&pred) {
+            return Ok(self.pending.remove(pos).unwrap());
+        }
+
+        let deadline = Instant::now() + timeout;
+        let previous_timeout = self.connection.read_timeout()?;
+
+        let result = read_until(deadline, |remaining| {
+            self.connection.set_read_timeout(Some(remaining))?;
+            let message = self.connection.read_message()?;
+            if pred(&message) {
+                return Ok(Some(message));
+            }
+            self.pending.push_back(message);
+            Ok(None)
+        });
+
+        self.connection.set_read_timeout(previous_timeout)?;
+        result
+    }
+
+    /// Get the number of messages buffered by [`receive_matching`](Self::receive_matching).
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
     }
 
     /// Get a reference to the underlying connection.
@@ -252,8 +501,73 @@ pub type RequestHandler = Box<dyn Fn(&SomeIpMessage) -> Option<SomeIpMessage> +
 mod tests {
     use super::*;
     use crate::header::{MethodId, ServiceId};
+    use std::io::Write;
     use std::thread;
 
+    #[test]
+    fn test_tcp_resync_on_error() {
+        let server = TcpServer::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr();
+
+        let first = SomeIpMessage::notification(ServiceId(0x1234), MethodId::event(0x0001))
+            .payload(b"first".as_slice())
+            .build();
+        let second = SomeIpMessage::notification(ServiceId(0x1234), MethodId::event(0x0002))
+            .payload(b"second".as_slice())
+            .build();
+
+        let mut data = first.to_bytes();
+        data.extend_from_slice(&[0xFF; 10]);
+        data.extend_from_slice(&second.to_bytes());
+
+        let sender = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.write_all(&data).unwrap();
+        });
+
+        let (mut conn, _) = server.accept().unwrap();
+        conn.set_resync_on_error(true);
+        sender.join().unwrap();
+
+        assert_eq!(conn.read_message().unwrap(), first);
+        assert!(matches!(
+            conn.read_message(),
+            Err(SomeIpError::Resynchronized { skipped: 10 })
+        ));
+        assert_eq!(conn.read_message().unwrap(), second);
+    }
+
+    #[test]
+    fn test_tcp_resync_on_implausible_length() {
+        let server = TcpServer::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr();
+
+        let message = SomeIpMessage::notification(ServiceId(0x1234), MethodId::event(0x0002))
+            .payload(b"data".as_slice())
+            .build();
+
+        let mut corrupted = message.to_bytes();
+        corrupted.truncate(HEADER_SIZE);
+        corrupted[4..8].copy_from_slice(&u32::MAX.to_be_bytes());
+        let mut data = corrupted;
+        data.extend_from_slice(&message.to_bytes());
+
+        let sender = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.write_all(&data).unwrap();
+        });
+
+        let (mut conn, _) = server.accept().unwrap();
+        conn.set_resync_on_error(true);
+        sender.join().unwrap();
+
+        assert!(matches!(
+            conn.read_message(),
+            Err(SomeIpError::Resynchronized { skipped: 16 })
+        ));
+        assert_eq!(conn.read_message().unwrap(), message);
+    }
+
     #[test]
     fn test_tcp_client_server() {
         // Start server
@@ -287,6 +601,197 @@ mod tests {
         server_handle.join().unwrap();
     }
 
+    #[test]
+    fn test_tcp_respond_with_code() {
+        use crate::types::MessageType;
+
+        let server = TcpServer::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr();
+
+        let server_handle = thread::spawn(move || {
+            let (mut conn, _) = server.accept().unwrap();
+            let request = conn.read_message().unwrap();
+            conn.respond_with_code(&request, ReturnCode::NotReady, b"later".as_slice())
+                .unwrap();
+        });
+
+        let mut client = TcpClient::connect(addr).unwrap();
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001)).build();
+
+        let response = client.call(request).unwrap();
+        assert_eq!(response.header.message_type, MessageType::Response);
+        assert_eq!(response.header.return_code, ReturnCode::NotReady);
+        assert_eq!(response.payload.as_ref(), b"later");
+
+        server_handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_tcp_byte_counters() {
+        let server = TcpServer::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr();
+
+        let handle = thread::spawn(move || {
+            let (mut conn, _) = server.accept().unwrap();
+            let request = conn.read_message().unwrap();
+            conn.respond(&request, vec![0u8; 100]).unwrap();
+            (conn.read_bytes(), conn.written_bytes())
+        });
+
+        let mut client = TcpClient::connect(addr).unwrap();
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .payload(vec![0u8; 10])
+            .build();
+        client.call(request).unwrap();
+
+        let (server_read, server_written) = handle.join().unwrap();
+        assert_eq!(server_read, (HEADER_SIZE + 10) as u64);
+        assert_eq!(server_written, (HEADER_SIZE + 100) as u64);
+        assert_eq!(client.connection().written_bytes(), server_read);
+        assert_eq!(client.connection().read_bytes(), server_written);
+    }
+
+    #[test]
+    fn test_read_message_streaming() {
+        let server = TcpServer::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr();
+
+        let payload: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+        let expected = payload.clone();
+
+        let handle = thread::spawn(move || {
+            let (mut conn, _) = server.accept().unwrap();
+            let mut received = Vec::new();
+            let mut chunks = 0;
+            let header = conn
+                .read_message_streaming(|chunk| {
+                    chunks += 1;
+                    received.extend_from_slice(chunk);
+                    Ok(())
+                })
+                .unwrap();
+            (header, received, chunks)
+        });
+
+        let mut client = TcpClient::connect(addr).unwrap();
+        let msg = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .payload(payload)
+            .build();
+        client.send(msg).unwrap();
+
+        let (header, received, chunks) = handle.join().unwrap();
+        assert_eq!(header.service_id, ServiceId(0x1234));
+        assert_eq!(header.payload_length() as usize, expected.len());
+        assert!(chunks > 1);
+        assert_eq!(received, expected);
+    }
+
+    #[test]
+    fn test_read_message_streaming_sink_error() {
+        let server = TcpServer::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr();
+
+        let handle = thread::spawn(move || {
+            let (mut conn, _) = server.accept().unwrap();
+            let result = conn.read_message_streaming(|_| Err(SomeIpError::Timeout));
+            assert!(matches!(result, Err(SomeIpError::Timeout)));
+            // The failed message was skipped, so the next one is read intact
+            conn.read_message().unwrap()
+        });
+
+        let mut client = TcpClient::connect(addr).unwrap();
+        let large = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .payload(vec![0xAB; 100_000])
+            .build();
+        client.send(large).unwrap();
+        let small = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0002))
+            .payload(b"next".as_slice())
+            .build();
+        client.send(small).unwrap();
+
+        let next = handle.join().unwrap();
+        assert_eq!(next.header.method_id, MethodId(0x0002));
+        assert_eq!(next.payload.as_ref(), b"next");
+    }
+
+    #[test]
+    fn test_read_message_streaming_resync() {
+        let server = TcpServer::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr();
+
+        let first = SomeIpMessage::notification(ServiceId(0x1234), MethodId::event(0x0001))
+            .payload(b"first".as_slice())
+            .build();
+        let second = SomeIpMessage::notification(ServiceId(0x1234), MethodId::event(0x0002))
+            .payload(b"second".as_slice())
+            .build();
+
+        let mut data = first.to_bytes();
+        data.extend_from_slice(&[0xFF; 10]);
+        data.extend_from_slice(&second.to_bytes());
+
+        let sender = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.write_all(&data).unwrap();
+        });
+
+        let (mut conn, _) = server.accept().unwrap();
+        conn.set_resync_on_error(true);
+        sender.join().unwrap();
+
+        let mut received = Vec::new();
+        let mut sink = |chunk: &[u8]| {
+            received.extend_from_slice(chunk);
+            Ok(())
+        };
+        assert_eq!(conn.read_message_streaming(&mut sink).unwrap(), first.header);
+        assert!(matches!(
+            conn.read_message_streaming(&mut sink),
+            Err(SomeIpError::Resynchronized { skipped: 10 })
+        ));
+        assert_eq!(conn.read_message_streaming(&mut sink).unwrap(), second.header);
+        assert_eq!(received, b"firstsecond");
+        assert_eq!(conn.read_bytes(), (first.total_size() + second.total_size()) as u64);
+    }
+
+    #[test]
+    fn test_receive_matching() {
+        let server = TcpServer::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr();
+
+        let (done_tx, done_rx) = std::sync::mpsc::channel::<()>();
+        let handle = thread::spawn(move || {
+            let (mut conn, _) = server.accept().unwrap();
+            for event in [0x8001, 0x8002, 0x8003] {
+                let msg = SomeIpMessage::notification(ServiceId(0x1234), MethodId(event)).build();
+                conn.write_message(&msg).unwrap();
+            }
+            // Keep the connection open until the client is done
+            let _ = done_rx.recv();
+        });
+
+        let mut client = TcpClient::connect(addr).unwrap();
+
+        let event = client
+            .receive_matching(
+                |msg| msg.header.method_id == MethodId(0x8003),
+                Duration::from_secs(5),
+            )
+            .unwrap();
+        assert_eq!(event.header.method_id, MethodId(0x8003));
+        assert_eq!(client.pending_count(), 2);
+
+        // Intervening messages are still delivered in order
+        assert_eq!(client.receive().unwrap().header.method_id, MethodId(0x8001));
+        assert_eq!(client.receive().unwrap().header.method_id, MethodId(0x8002));
+
+        let result = client.receive_matching(|_| true, Duration::from_millis(50));
+        assert!(matches!(result, Err(SomeIpError::Timeout)));
+
+        done_tx.send(()).unwrap();
+        handle.join().unwrap();
+    }
+
     #[test]
     fn test_session_id_increment() {
         let server = TcpServer::bind("127.0.0.1:0").unwrap();
@@ -309,4 +814,47 @@ mod tests {
             assert_eq!(response.header.session_id, SessionId(expected_session));
         }
     }
+
+    #[test]
+    fn test_tcp_set_linger() {
+        let server = TcpServer::bind("127.0.0.1:0").unwrap();
+        let client = TcpClient::connect(server.local_addr()).unwrap();
+
+        assert_eq!(client.connection().linger().unwrap(), None);
+        client.set_linger(Some(Duration::from_secs(1))).unwrap();
+        assert_eq!(client.connection().linger().unwrap(), Some(Duration::from_secs(1)));
+        client.set_linger(None).unwrap();
+        assert_eq!(client.connection().linger().unwrap(), None);
+    }
+
+    #[test]
+    fn test_call_matches_session_zero_response() {
+        let server = TcpServer::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr();
+
+        thread::spawn(move || {
+            let (mut conn, _) = server.accept().unwrap();
+            let request = conn.read_message().unwrap();
+            // A session-less response for another method must be skipped
+            let mut other = request
+                .create_response()
+                .session_id(SessionId(0))
+                .payload(b"other method".as_slice())
+                .build();
+            other.header.method_id = MethodId(0x0002);
+            conn.write_message(&other).unwrap();
+            let response = request
+                .create_response()
+                .session_id(SessionId(0))
+                .payload(b"sessionless".as_slice())
+                .build();
+            conn.write_message(&response).unwrap();
+        });
+
+        let mut client = TcpClient::connect(addr).unwrap();
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001)).build();
+        let response = client.call(request).unwrap();
+        assert!(!response.session_handling_active());
+        assert_eq!(response.payload.as_ref(), b"sessionless");
+    }
 }
@@ -1,20 +1,31 @@
 //! UDP transport for SOME/IP.
 
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::io;
-use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::net::{Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket};
 use std::sync::atomic::{AtomicU16, Ordering};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use crate::error::Result;
-use crate::header::{ClientId, SessionId};
+use bytes::{Bytes, BytesMut};
+
+use crate::error::{Result, SomeIpError};
+use crate::header::{ClientId, MethodId, ServiceId, SessionId, SomeIpHeader, HEADER_SIZE};
 use crate::message::SomeIpMessage;
 
+use super::{map_io_timeout, read_until};
+
 /// Default maximum UDP datagram size for SOME/IP.
 pub const DEFAULT_MAX_DATAGRAM_SIZE: usize = 1400;
 
+/// Largest payload a UDP datagram over IPv4 can carry.
+pub const MAX_DATAGRAM_SIZE: usize = 65507;
+
 /// Default UDP port for SOME/IP.
 pub const DEFAULT_PORT: u16 = 30490;
 
+/// Default number of peers whose destination IP a pktinfo server remembers.
+pub const DEFAULT_MAX_DESTINATIONS: usize = 1024;
+
 /// A SOME/IP UDP client.
 ///
 /// Provides request/response and fire-and-forget functionality over UDP.
@@ -25,6 +36,10 @@ pub struct UdpClient {
     session_counter: AtomicU16,
     recv_buffer: Vec<u8>,
     max_datagram_size: usize,
+    implicit_length: bool,
+    coalesce: bool,
+    /// Messages left over from a coalesced datagram.
+    pending: VecDeque<(SomeIpMessage, SocketAddr)>,
 }
 
 impl UdpClient {
@@ -40,8 +55,12 @@ impl UdpClient {
             socket,
             client_id: ClientId(0x0001),
             session_counter: AtomicU16::new(1),
-            recv_buffer: vec![0u8; DEFAULT_MAX_DATAGRAM_SIZE],
+            // One spare byte to detect datagrams larger than the maximum
+            recv_buffer: vec![0u8; DEFAULT_MAX_DATAGRAM_SIZE + 1],
             max_datagram_size: DEFAULT_MAX_DATAGRAM_SIZE,
+            implicit_length: false,
+            coalesce: false,
+            pending: VecDeque::new(),
         })
     }
 
@@ -63,10 +82,57 @@ impl UdpClient {
         self.client_id
     }
 
-    /// Set the maximum datagram size.
+    /// Set the maximum datagram size, capped at [`MAX_DATAGRAM_SIZE`].
+    ///
+    /// Larger datagrams are reported as [`SomeIpError::DatagramTruncated`].
     pub fn set_max_datagram_size(&mut self, size: usize) {
-        self.max_datagram_size = size;
-        self.recv_buffer.resize(size, 0);
+        self.max_datagram_size = size.min(MAX_DATAGRAM_SIZE);
+        self.recv_buffer.resize(self.max_datagram_size + 1, 0);
+    }
+
+    /// Trust the datagram size instead of the header length field on receive.
+    ///
+    /// When enabled, a received datagram whose length field disagrees with its
+    /// size has `header.length` rebuilt from the datagram size. Off by default.
+    pub fn set_implicit_length(&mut self, implicit: bool) {
+        self.implicit_length = implicit;
+    }
+
+    /// Check if implicit length framing is enabled.
+    pub fn implicit_length(&self) -> bool {
+        self.implicit_length
+    }
+
+    /// Pack multiple messages per datagram in [`send_batch`](Self::send_batch).
+    ///
+    /// Receivers unpack them with [`UdpServer::receive_all`] or successive
+    /// receive calls. Off by default.
+    pub fn set_coalesce(&mut self, coalesce: bool) {
+        self.coalesce = coalesce;
+    }
+
+    /// Receive the next message, taking those left over from a coalesced
+    /// datagram first.
+    fn recv_message(&mut self) -> Result<(SomeIpMessage, SocketAddr)> {
+        loop {
+            if let Some(queued) = self.pending.pop_front() {
+                return Ok(queued);
+            }
+            let (len, addr) = self.socket.recv_from(&mut self.recv_buffer)?;
+            self.check_datagram_size(len)?;
+            let messages = parse_messages(&self.recv_buffer[..len], self.implicit_length)?;
+            self.pending.extend(messages.into_iter().map(|m| (m, addr)));
+        }
+    }
+
+    /// Reject datagrams that did not fit the receive buffer.
+    fn check_datagram_size(&self, len: usize) -> Result<()> {
+        if len > self.max_datagram_size {
+            return Err(SomeIpError::DatagramTruncated {
+                buffer_size: self.max_datagram_size,
+            });
+        }
+        Ok(())
     }
 
     /// Get the next session ID.
@@ -112,8 +178,7 @@ impl UdpClient {
 
         // Wait for matching response
         loop {
-            let (len, _) = self.socket.recv_from(&mut self.recv_buffer)?;
-            let response = SomeIpMessage::from_bytes(&self.recv_buffer[..len])?;
+            let (response, _) = self.recv_message()?;
 
             if response.header.request_id() == request_id {
                 return Ok(response);
@@ -121,6 +186,38 @@ impl UdpClient {
         }
     }
 
+    /// Build a request, send it to the connected address and wait for the response.
+    ///
+    /// Returns [`SomeIpError::Timeout`] if no matching response arrives in time.
+    pub fn request(
+        &mut self,
+        service_id: ServiceId,
+        method_id: MethodId,
+        payload: impl Into<Bytes>,
+        timeout: Duration,
+    ) -> Result<SomeIpMessage> {
+        let message = SomeIpMessage::request(service_id, method_id)
+            .client_id(self.client_id)
+            .session_id(self.next_session_id())
+            .payload(payload)
+            .build();
+
+        let request_id = message.header.request_id();
+        self.socket.send(&message.to_bytes())?;
+
+        let deadline = Instant::now() + timeout;
+        let previous_timeout = self.socket.read_timeout()?;
+
+        let result = read_until(deadline, |remaining| {
+            self.socket.set_read_timeout(Some(remaining))?;
+            let (response, _) = self.recv_message()?;
+            Ok((response.header.request_id() == request_id).then_some(response))
+        });
+
+        self.socket.set_read_timeout(previous_timeout)?;
+        result
+    }
+
     /// Send a request to a specific address and wait for a response.
     pub fn call_to<A: ToSocketAddrs>(
         &mut self,
@@ -137,8 +234,7 @@ impl UdpClient {
 
         // Wait for matching response
         loop {
-            let (len, _) = self.socket.recv_from(&mut self.recv_buffer)?;
-            let response = SomeIpMessage::from_bytes(&self.recv_buffer[..len])?;
+            let (response, _) = self.recv_message()?;
 
             if response.header.request_id() == request_id {
                 return Ok(response);
@@ -156,6 +252,37 @@ impl UdpClient {
         Ok(())
     }
 
+    /// Send several fire-and-forget messages to the connected address.
+    ///
+    /// With coalescing enabled, messages are packed back to back into
+    /// datagrams of up to the maximum datagram size; otherwise each message is
+    /// sent on its own. Returns the number of datagrams sent.
+    pub fn send_batch(&mut self, messages: Vec<SomeIpMessage>) -> Result<usize> {
+        let mut datagrams = 0;
+        let mut buf = Vec::with_capacity(self.max_datagram_size);
+
+        for mut message in messages {
+            message.header.client_id = self.client_id;
+            message.header.session_id = self.next_session_id();
+
+            let data = message.to_bytes();
+            let fits = self.coalesce && buf.len() + data.len() <= self.max_datagram_size;
+            if !buf.is_empty() && !fits {
+                self.socket.send(&buf)?;
+                datagrams += 1;
+                buf.clear();
+            }
+            buf.extend_from_slice(&data);
+        }
+
+        if !buf.is_empty() {
+            self.socket.send(&buf)?;
+            datagrams += 1;
+        }
+
+        Ok(datagrams)
+    }
+
     /// Send a fire-and-forget message to a specific address.
     pub fn send_to<A: ToSocketAddrs>(&mut self, addr: A, mut message: SomeIpMessage) -> Result<()> {
         message.header.client_id = self.client_id;
@@ -167,10 +294,29 @@ impl UdpClient {
     }
 
     /// Receive a message.
+    ///
+    /// Messages coalesced into one datagram are returned by successive calls.
     pub fn receive(&mut self) -> Result<(SomeIpMessage, SocketAddr)> {
-        let (len, addr) = self.socket.recv_from(&mut self.recv_buffer)?;
-        let message = SomeIpMessage::from_bytes(&self.recv_buffer[..len])?;
-        Ok((message, addr))
+        self.recv_message()
+    }
+
+    /// Receive a message into a caller-provided buffer.
+    ///
+    /// The payload of the returned message is split off `buf` without copying.
+    /// Once the message is dropped, the next call reuses the same allocation.
+    /// Messages coalesced into one datagram are returned by successive calls.
+    pub fn receive_into(&mut self, buf: &mut BytesMut) -> Result<(SomeIpMessage, SocketAddr)> {
+        loop {
+            if let Some(queued) = self.pending.pop_front() {
+                return Ok(queued);
+            }
+            let (datagram, addr) = recv_into(buf, self.max_datagram_size + 1, |data| {
+                self.socket.recv_from(data)
+            })?;
+            self.check_datagram_size(datagram.len())?;
+            let messages = parse_shared_messages(datagram, self.implicit_length)?;
+            self.pending.extend(messages.into_iter().map(|m| (m, addr)));
+        }
     }
 
     /// Get a reference to the underlying socket.
@@ -179,6 +325,81 @@ impl UdpClient {
     }
 }
 
+/// Parse a datagram, optionally deriving the length field from its size.
+fn parse_datagram(data: &[u8], implicit_length: bool) -> Result<SomeIpMessage> {
+    if !implicit_length {
+        return SomeIpMessage::from_bytes(data);
+    }
+
+    let header = SomeIpHeader::from_bytes(data)?;
+    if header.length as usize + 8 == data.len() {
+        return SomeIpMessage::from_bytes(data);
+    }
+
+    Ok(SomeIpMessage::new(header, data[HEADER_SIZE..].to_vec()))
+}
+
+/// Parse every message packed back to back into a datagram.
+///
+/// A datagram holds at least one message; trailing bytes that do not form a
+/// complete message fail the whole datagram.
+fn parse_messages(mut data: &[u8], implicit_length: bool) -> Result<Vec<SomeIpMessage>> {
+    let mut messages = Vec::new();
+    loop {
+        let message = parse_datagram(data, implicit_length)?;
+        data = &data[HEADER_SIZE + message.payload.len()..];
+        messages.push(message);
+        if data.is_empty() {
+            return Ok(messages);
+        }
+    }
+}
+
+/// Receive one datagram into `buf` and split it off as shared bytes.
+fn recv_into(
+    buf: &mut BytesMut,
+    max_size: usize,
+    recv: impl FnOnce(&mut [u8]) -> io::Result<(usize, SocketAddr)>,
+) -> io::Result<(Bytes, SocketAddr)> {
+    buf.clear();
+    buf.resize(max_size, 0);
+    let result = recv(&mut buf[..]);
+    let received = result.map(|(len, addr)| (buf.split_to(len).freeze(), addr));
+    buf.clear();
+    received
+}
+
+/// Parse a datagram held in shared bytes, slicing the payload without copying.
+fn parse_shared_datagram(data: Bytes, implicit_length: bool) -> Result<SomeIpMessage> {
+    let header = SomeIpHeader::from_bytes(&data)?;
+    let end = HEADER_SIZE + header.payload_length() as usize;
+
+    if implicit_length && end != data.len() {
+        return Ok(SomeIpMessage::new(header, data.slice(HEADER_SIZE..)));
+    }
+    if data.len() < end {
+        return Err(SomeIpError::LengthMismatch {
+            header_length: header.length,
+            actual_length: data.len() - 8,
+        });
+    }
+
+    Ok(SomeIpMessage::from_parts(header, data.slice(HEADER_SIZE..end)))
+}
+
+/// Parse every message packed into a datagram held in shared bytes.
+fn parse_shared_messages(mut data: Bytes, implicit_length: bool) -> Result<Vec<SomeIpMessage>> {
+    let mut messages = Vec::new();
+    loop {
+        let message = parse_shared_datagram(data.clone(), implicit_length)?;
+        data = data.slice(HEADER_SIZE + message.payload.len()..);
+        messages.push(message);
+        if data.is_empty() {
+            return Ok(messages);
+        }
+    }
+}
+
 /// A SOME/IP UDP server.
 ///
 /// Binds to an address and handles incoming messages.
@@ -187,6 +408,15 @@ pub struct UdpServer {
     socket: UdpSocket,
     recv_buffer: Vec<u8>,
     local_addr: SocketAddr,
+    pktinfo: bool,
+    /// Destination IP and last-use stamp per peer, bounded by `max_destinations`.
+    destinations: HashMap<SocketAddr, (Ipv4Addr, u64)>,
+    /// Peers by last-use stamp, oldest first.
+    destination_order: BTreeMap<u64, SocketAddr>,
+    max_destinations: usize,
+    destination_stamp: u64,
+    /// Messages left over from a coalesced datagram.
+    pending: VecDeque<(SomeIpMessage, SocketAddr)>,
 }
 
 impl UdpServer {
@@ -198,9 +428,99 @@ impl UdpServer {
             socket,
             recv_buffer: vec![0u8; DEFAULT_MAX_DATAGRAM_SIZE],
             local_addr,
+            pktinfo: false,
+            destinations: HashMap::new(),
+            destination_order: BTreeMap::new(),
+            max_destinations: DEFAULT_MAX_DESTINATIONS,
+            destination_stamp: 0,
+            pending: VecDeque::new(),
         })
     }
 
+    /// Bind to an IPv4 address, replying from the address each request arrived on.
+    ///
+    /// Uses `IP_PKTINFO` to record the destination IP of incoming datagrams and
+    /// sends to that peer with it as the source IP. Useful on multi-homed hosts
+    /// bound to the unspecified address.
+    #[cfg(target_os = "linux")]
+    pub fn bind_with_pktinfo<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        let mut server = Self::bind(addr)?;
+        super::pktinfo::enable(&server.socket)?;
+        server.pktinfo = true;
+        Ok(server)
+    }
+
+    /// Get the local IP the last datagram from `peer` arrived on.
+    ///
+    /// Only recorded for servers created with `bind_with_pktinfo`.
+    pub fn destination_for(&self, peer: SocketAddr) -> Option<Ipv4Addr> {
+        self.destinations.get(&peer).map(|&(destination, _)| destination)
+    }
+
+    /// Limit how many peers' destination IPs are remembered.
+    ///
+    /// When full, the least recently seen peer is forgotten, and replies to it
+    /// are sent from the default source address.
+    pub fn set_max_destinations(&mut self, max: usize) {
+        self.max_destinations = max;
+        while self.destinations.len() > max {
+            self.evict_destination();
+        }
+    }
+
+    /// Record the destination IP of a datagram from `peer`.
+    fn record_destination(&mut self, peer: SocketAddr, destination: Ipv4Addr) {
+        if self.max_destinations == 0 {
+            return;
+        }
+        match self.destinations.get(&peer) {
+            Some(&(_, stamp)) => {
+                self.destination_order.remove(&stamp);
+            }
+            None if self.destinations.len() >= self.max_destinations => {
+                self.evict_destination();
+            }
+            None => {}
+        }
+        self.destination_stamp += 1;
+        self.destinations.insert(peer, (destination, self.destination_stamp));
+        self.destination_order.insert(self.destination_stamp, peer);
+    }
+
+    /// Forget the least recently seen peer.
+    fn evict_destination(&mut self) {
+        if let Some((_, oldest)) = self.destination_order.pop_first() {
+            self.destinations.remove(&oldest);
+        }
+    }
+
+    /// Receive a datagram into the receive buffer.
+    fn recv_datagram(&mut self) -> io::Result<(usize, SocketAddr)> {
+        let mut buf = std::mem::take(&mut self.recv_buffer);
+        let result = self.recv_datagram_into(&mut buf);
+        self.recv_buffer = buf;
+        result
+    }
+
+    /// Receive a datagram into `buf`, recording its destination if enabled.
+    fn recv_datagram_into(&mut self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        if self.pktinfo {
+            #[cfg(target_os = "linux")]
+            {
+                let (len, addr, destination) = super::pktinfo::recv_from(&self.socket, buf)?;
+                // Never reply from a group or broadcast address
+                let unicast = destination
+                    .filter(|ip| !ip.is_multicast() && !ip.is_broadcast() && !ip.is_unspecified());
+                if let Some(destination) = unicast {
+                    self.record_destination(addr, destination);
+                }
+                return Ok((len, addr));
+            }
+        }
+
+        self.socket.recv_from(buf)
+    }
+
     /// Get the local address.
     pub fn local_addr(&self) -> SocketAddr {
         self.local_addr
@@ -217,15 +537,77 @@ impl UdpServer {
     }
 
     /// Receive a message.
+    ///
+    /// Messages coalesced into one datagram are returned by successive calls.
     pub fn receive(&mut self) -> Result<(SomeIpMessage, SocketAddr)> {
-        let (len, addr) = self.socket.recv_from(&mut self.recv_buffer)?;
-        let message = SomeIpMessage::from_bytes(&self.recv_buffer[..len])?;
-        Ok((message, addr))
+        loop {
+            if let Some(queued) = self.pending.pop_front() {
+                return Ok(queued);
+            }
+            let (len, addr) = self.recv_datagram()?;
+            let messages = parse_messages(&self.recv_buffer[..len], false)?;
+            self.pending.extend(messages.into_iter().map(|m| (m, addr)));
+        }
+    }
+
+    /// Receive a message into a caller-provided buffer.
+    ///
+    /// The payload of the returned message is split off `buf` without copying.
+    /// Once the message is dropped, the next call reuses the same allocation.
+    /// Messages coalesced into one datagram are returned by successive calls.
+    pub fn receive_into(&mut self, buf: &mut BytesMut) -> Result<(SomeIpMessage, SocketAddr)> {
+        loop {
+            if let Some(queued) = self.pending.pop_front() {
+                return Ok(queued);
+            }
+            let max_size = self.recv_buffer.len();
+            let (datagram, addr) = recv_into(buf, max_size, |data| self.recv_datagram_into(data))?;
+            let messages = parse_shared_messages(datagram, false)?;
+            self.pending.extend(messages.into_iter().map(|m| (m, addr)));
+        }
+    }
+
+    /// Receive a message, waiting at most `duration` for this call only.
+    ///
+    /// The previous read timeout is restored afterwards.
+    pub fn receive_timeout(&mut self, duration: Duration) -> Result<(SomeIpMessage, SocketAddr)> {
+        let previous_timeout = self.socket.read_timeout()?;
+        self.socket.set_read_timeout(Some(duration))?;
+
+        let result = self.receive().map_err(map_io_timeout);
+
+        self.socket.set_read_timeout(previous_timeout)?;
+        result
+    }
+
+    /// Receive all messages packed into a single datagram.
+    ///
+    /// Counterpart of [`UdpClient::send_batch`] with coalescing enabled. If an
+    /// earlier [`receive`](Self::receive) left messages of a datagram
+    /// unreturned, those are returned instead.
+    pub fn receive_all(&mut self) -> Result<(Vec<SomeIpMessage>, SocketAddr)> {
+        if let Some(&(_, addr)) = self.pending.front() {
+            let messages = self.pending.drain(..).map(|(message, _)| message).collect();
+            return Ok((messages, addr));
+        }
+
+        let (len, addr) = self.recv_datagram()?;
+        let messages = parse_messages(&self.recv_buffer[..len], false)?;
+        Ok((messages, addr))
     }
 
     /// Send a message to an address.
+    ///
+    /// With `IP_PKTINFO` enabled, the source IP is the address the peer last sent to.
     pub fn send_to(&self, message: &SomeIpMessage, addr: SocketAddr) -> Result<()> {
         let data = message.to_bytes();
+
+        #[cfg(target_os = "linux")]
+        if let (SocketAddr::V4(peer), Some(source)) = (addr, self.destination_for(addr)) {
+            super::pktinfo::send_from(&self.socket, &data, peer, source)?;
+            return Ok(());
+        }
+
         self.socket.send_to(&data, addr)?;
         Ok(())
     }
@@ -243,6 +625,25 @@ impl UdpServer {
         self.send_to(&response, addr)
     }
 
+    /// Send a response to a request with a specific return code.
+    ///
+    /// Unlike [`respond_error`](Self::respond_error), the message type stays
+    /// `Response`, which allows non-error codes other than `Ok` to be sent.
+    pub fn respond_with_code(
+        &self,
+        request: &SomeIpMessage,
+        return_code: crate::types::ReturnCode,
+        payload: impl Into<bytes::Bytes>,
+        addr: SocketAddr,
+    ) -> Result<()> {
+        let response = request
+            .create_response()
+            .return_code(return_code)
+            .payload(payload)
+            .build();
+        self.send_to(&response, addr)
+    }
+
     /// Send an error response to a request.
     pub fn respond_error(
         &self,
@@ -254,6 +655,22 @@ impl UdpServer {
         self.send_to(&response, addr)
     }
 
+    /// Send a `MalformedMessage` error response to a request that failed parsing.
+    ///
+    /// Does nothing if `raw` has no parseable header or is not a request
+    /// expecting a response.
+    pub fn respond_malformed(&self, raw: &[u8], addr: SocketAddr) -> Result<()> {
+        let Some(header) = crate::codec::try_parse_header(raw) else {
+            return Ok(());
+        };
+        if !header.message_type.expects_response() {
+            return Ok(());
+        }
+
+        let request = SomeIpMessage::new(header, Vec::new());
+        self.respond_error(&request, crate::types::ReturnCode::MalformedMessage, addr)
+    }
+
     /// Join a multicast group.
     pub fn join_multicast_v4(
         &self,
@@ -281,9 +698,100 @@ impl UdpServer {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::header::{MethodId, ServiceId};
     use std::thread;
 
+    #[test]
+    fn test_udp_send_batch_coalesced() {
+        let mut server = UdpServer::bind("127.0.0.1:0").unwrap();
+        let server_addr = server.local_addr();
+
+        let mut client = UdpClient::bind("127.0.0.1:0").unwrap();
+        client.connect(server_addr).unwrap();
+        client.set_coalesce(true);
+
+        let messages = (1..=3)
+            .map(|i| {
+                SomeIpMessage::notification(ServiceId(0x1234), MethodId(0x8000 + i))
+                    .payload(vec![i as u8; 10])
+                    .build()
+            })
+            .collect();
+        assert_eq!(client.send_batch(messages).unwrap(), 1);
+
+        let (received, _) = server.receive_all().unwrap();
+        assert_eq!(received.len(), 3);
+        for (i, msg) in received.iter().enumerate() {
+            assert_eq!(msg.header.method_id, MethodId(0x8001 + i as u16));
+            assert_eq!(msg.payload.len(), 10);
+        }
+    }
+
+    #[test]
+    fn test_udp_receive_coalesced() {
+        let mut server = UdpServer::bind("127.0.0.1:0").unwrap();
+        server.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let server_addr = server.local_addr();
+
+        let mut client = UdpClient::bind("127.0.0.1:0").unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        client.connect(server_addr).unwrap();
+        let client_addr = client.local_addr().unwrap();
+        client.set_coalesce(true);
+
+        let batch = |base: u16| -> Vec<_> {
+            (0..3)
+                .map(|i| SomeIpMessage::notification(ServiceId(0x1234), MethodId(base + i)).build())
+                .collect()
+        };
+        assert_eq!(client.send_batch(batch(0x8000)).unwrap(), 1);
+        assert_eq!(client.send_batch(batch(0x8010)).unwrap(), 1);
+
+        // One message per receive, whichever receive method is used
+        let mut buf = BytesMut::new();
+        let method_ids = [
+            server.receive().unwrap().0.header.method_id,
+            server.receive_into(&mut buf).unwrap().0.header.method_id,
+            server.receive().unwrap().0.header.method_id,
+            server.receive_into(&mut buf).unwrap().0.header.method_id,
+        ];
+        assert_eq!(method_ids.map(|id| id.0), [0x8000, 0x8001, 0x8002, 0x8010]);
+
+        let (rest, addr) = server.receive_all().unwrap();
+        assert_eq!(addr, client_addr);
+        assert_eq!(rest.len(), 2);
+        assert_eq!(rest[0].header.method_id, MethodId(0x8011));
+
+        let data: Vec<u8> = batch(0x8020).iter().take(2).flat_map(|m| m.to_bytes()).collect();
+        server.socket().send_to(&data, client_addr).unwrap();
+        assert_eq!(client.receive().unwrap().0.header.method_id, MethodId(0x8020));
+        assert_eq!(client.receive_into(&mut buf).unwrap().0.header.method_id, MethodId(0x8021));
+    }
+
+    #[test]
+    fn test_udp_implicit_length() {
+        let server = UdpServer::bind("127.0.0.1:0").unwrap();
+        let server_addr = server.local_addr();
+
+        let mut client = UdpClient::bind("127.0.0.1:0").unwrap();
+        client.set_implicit_length(true);
+        let client_addr = client.local_addr().unwrap();
+
+        let msg = SomeIpMessage::notification(ServiceId(0x1234), MethodId(0x8001))
+            .payload(b"hello".as_slice())
+            .build();
+        let mut data = msg.to_bytes();
+        data[4..8].copy_from_slice(&[0, 0, 0, 0]);
+        server.socket().send_to(&data, client_addr).unwrap();
+
+        let (received, addr) = client.receive().unwrap();
+        assert_eq!(addr, server_addr);
+        assert_eq!(received.header.length, 8 + 5);
+        assert_eq!(received.payload.as_ref(), b"hello");
+
+        // Without implicit length the zeroed length yields an empty payload
+        assert!(parse_datagram(&data, false).unwrap().payload.is_empty());
+    }
+
     #[test]
     fn test_udp_client_server() {
         // Start server
@@ -315,6 +823,154 @@ mod tests {
         server_handle.join().unwrap();
     }
 
+    #[test]
+    fn test_udp_large_single_datagram() {
+        let mut server = UdpServer::bind("127.0.0.1:0").unwrap();
+        let server_addr = server.local_addr();
+
+        // Oversized response sent as a single non-TP datagram
+        let server_handle = thread::spawn(move || {
+            for _ in 0..2 {
+                let (request, client_addr) = server.receive().unwrap();
+                server.respond(&request, vec![0xAB; 3000], client_addr).unwrap();
+            }
+        });
+
+        let mut client = UdpClient::bind("127.0.0.1:0").unwrap();
+        client.connect(server_addr).unwrap();
+
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001)).build();
+        assert!(matches!(
+            client.call(request.clone()),
+            Err(SomeIpError::DatagramTruncated { buffer_size: 1400 })
+        ));
+
+        client.set_max_datagram_size(100_000);
+        assert_eq!(client.max_datagram_size, MAX_DATAGRAM_SIZE);
+
+        let response = client.call(request).unwrap();
+        assert!(!response.header.message_type.is_tp());
+        assert_eq!(response.payload.len(), 3000);
+        assert!(response.payload.iter().all(|&b| b == 0xAB));
+
+        server_handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_udp_request() {
+        let mut server = UdpServer::bind("127.0.0.1:0").unwrap();
+        let server_addr = server.local_addr();
+
+        // Echo server that ignores the second request
+        let server_handle = thread::spawn(move || {
+            let (request, client_addr) = server.receive().unwrap();
+            server
+                .respond(&request, request.payload.clone(), client_addr)
+                .unwrap();
+            server.receive().unwrap();
+        });
+
+        let mut client = UdpClient::new().unwrap();
+        client.connect(server_addr).unwrap();
+
+        let timeout = Duration::from_secs(1);
+        let response = client
+            .request(ServiceId(0x1234), MethodId(0x0001), b"echo".as_slice(), timeout)
+            .unwrap();
+        assert_eq!(response.payload.as_ref(), b"echo");
+        assert_eq!(response.header.client_id, client.client_id());
+
+        let timeout = Duration::from_millis(50);
+        let result = client.request(ServiceId(0x1234), MethodId(0x0001), Bytes::new(), timeout);
+        assert!(matches!(result, Err(SomeIpError::Timeout)));
+
+        server_handle.join().unwrap();
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_udp_server_pktinfo() {
+        let mut server = UdpServer::bind_with_pktinfo("0.0.0.0:0").unwrap();
+        let port = server.local_addr().port();
+
+        // 127.0.0.2 is a loopback address distinct from the client's own
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001)).build();
+        client.send_to(&request.to_bytes(), ("127.0.0.2", port)).unwrap();
+
+        let (received, peer) = server.receive().unwrap();
+        assert_eq!(peer, client.local_addr().unwrap());
+        assert_eq!(server.destination_for(peer), Some(Ipv4Addr::new(127, 0, 0, 2)));
+
+        server.respond(&received, b"pong".as_slice(), peer).unwrap();
+        let mut buf = [0u8; 1500];
+        let (len, from) = client.recv_from(&mut buf).unwrap();
+        assert_eq!(from, SocketAddr::from(([127, 0, 0, 2], port)));
+        assert_eq!(SomeIpMessage::from_bytes(&buf[..len]).unwrap().payload.as_ref(), b"pong");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_udp_server_pktinfo_multicast() {
+        let mut server = UdpServer::bind_with_pktinfo("0.0.0.0:0").unwrap();
+        let port = server.local_addr().port();
+        let group = Ipv4Addr::new(239, 192, 0, 43);
+        server.socket().join_multicast_v4(&group, &Ipv4Addr::LOCALHOST).unwrap();
+
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+        socket2::SockRef::from(&client).set_multicast_if_v4(&Ipv4Addr::LOCALHOST).unwrap();
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001)).build();
+        client.send_to(&request.to_bytes(), (group, port)).unwrap();
+
+        // The reply comes from the receiving interface, not from the group
+        let (received, peer) = server.receive().unwrap();
+        assert_eq!(server.destination_for(peer), Some(Ipv4Addr::LOCALHOST));
+        server.respond(&received, b"pong".as_slice(), peer).unwrap();
+        let mut buf = [0u8; 1500];
+        let (_, from) = client.recv_from(&mut buf).unwrap();
+        assert_eq!(from, SocketAddr::from(([127, 0, 0, 1], port)));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_udp_server_pktinfo_max_destinations() {
+        let mut server = UdpServer::bind_with_pktinfo("0.0.0.0:0").unwrap();
+        server.set_max_destinations(2);
+        let port = server.local_addr().port();
+
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001)).build();
+        let clients: Vec<_> = (0..3).map(|_| UdpSocket::bind("127.0.0.1:0").unwrap()).collect();
+        let mut peers = Vec::new();
+        for client in [&clients[0], &clients[1], &clients[0], &clients[2]] {
+            client.send_to(&request.to_bytes(), ("127.0.0.2", port)).unwrap();
+            peers.push(server.receive().unwrap().1);
+        }
+
+        // clients[1] was seen least recently, so it was forgotten
+        assert!(server.destination_for(peers[0]).is_some());
+        assert_eq!(server.destination_for(peers[1]), None);
+        assert!(server.destination_for(peers[3]).is_some());
+    }
+
+    #[test]
+    fn test_udp_server_receive_timeout() {
+        let mut server = UdpServer::bind("127.0.0.1:0").unwrap();
+        let timeout = Duration::from_millis(50);
+
+        assert!(matches!(server.receive_timeout(timeout), Err(SomeIpError::Timeout)));
+        assert_eq!(server.socket().read_timeout().unwrap(), None);
+
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001)).build();
+        client.send_to(&request.to_bytes(), server.local_addr()).unwrap();
+
+        let (received, addr) = server.receive_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(received, request);
+        assert_eq!(addr, client.local_addr().unwrap());
+    }
+
     #[test]
     fn test_udp_fire_and_forget() {
         let mut server = UdpServer::bind("127.0.0.1:0").unwrap();
@@ -357,4 +1013,98 @@ mod tests {
 
         server_handle.join().unwrap();
     }
+
+    #[test]
+    fn test_udp_respond_with_code() {
+        use crate::types::{MessageType, ReturnCode};
+
+        let mut server = UdpServer::bind("127.0.0.1:0").unwrap();
+        let server_addr = server.local_addr();
+
+        let server_handle = thread::spawn(move || {
+            let (request, client_addr) = server.receive().unwrap();
+            server
+                .respond_with_code(
+                    &request,
+                    ReturnCode::NotReady,
+                    b"later".as_slice(),
+                    client_addr,
+                )
+                .unwrap();
+        });
+
+        let mut client = UdpClient::new().unwrap();
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001)).build();
+
+        let response = client.call_to(server_addr, request).unwrap();
+        assert_eq!(response.header.message_type, MessageType::Response);
+        assert_eq!(response.header.return_code, ReturnCode::NotReady);
+        assert_eq!(response.payload.as_ref(), b"later");
+
+        server_handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_udp_respond_malformed() {
+        use crate::types::{MessageType, ReturnCode};
+
+        let server = UdpServer::bind("127.0.0.1:0").unwrap();
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+
+        // Payload declared as 10 bytes, but only 4 are sent
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+            .session_id(SessionId(0x0042))
+            .payload(vec![0u8; 10])
+            .build();
+        let bytes = request.to_bytes();
+        client.send_to(&bytes[..HEADER_SIZE + 4], server.local_addr()).unwrap();
+
+        let mut raw = [0u8; 64];
+        let (len, client_addr) = server.socket().recv_from(&mut raw).unwrap();
+        assert!(SomeIpMessage::from_bytes(&raw[..len]).is_err());
+        server.respond_malformed(&raw[..len], client_addr).unwrap();
+
+        let mut buf = [0u8; 64];
+        let (len, _) = client.recv_from(&mut buf).unwrap();
+        let response = SomeIpMessage::from_bytes(&buf[..len]).unwrap();
+        assert!(response.is_response_to(&request));
+        assert_eq!(response.header.message_type, MessageType::Error);
+        assert_eq!(response.header.return_code, ReturnCode::MalformedMessage);
+
+        // Unparseable headers are ignored
+        server.respond_malformed(&bytes[..8], client_addr).unwrap();
+    }
+
+    #[test]
+    fn test_udp_receive_into_reuses_buffer() {
+        let mut server = UdpServer::bind("127.0.0.1:0").unwrap();
+        server.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let mut client = UdpClient::new().unwrap();
+        client.connect(server.local_addr()).unwrap();
+
+        let mut buf = BytesMut::with_capacity(DEFAULT_MAX_DATAGRAM_SIZE);
+        let capacity = buf.capacity();
+
+        let first = SomeIpMessage::notification(ServiceId(0x1234), MethodId::event(0x0001))
+            .payload(b"first".as_slice())
+            .build();
+        client.send(first).unwrap();
+        let (message, _) = server.receive_into(&mut buf).unwrap();
+        assert_eq!(message.payload.as_ref(), b"first");
+        let first_payload = message.payload.as_ptr();
+        drop(message);
+
+        let second = SomeIpMessage::notification(ServiceId(0x1234), MethodId::event(0x0002))
+            .payload(b"second".as_slice())
+            .build();
+        client.send(second).unwrap();
+        let (message, _) = server.receive_into(&mut buf).unwrap();
+        assert_eq!(message.header.method_id, MethodId::event(0x0002));
+        assert_eq!(message.payload.as_ref(), b"second");
+
+        // The same allocation was reused without growing
+        assert_eq!(message.payload.as_ptr(), first_payload);
+        assert!(buf.capacity() <= capacity);
+    }
 }
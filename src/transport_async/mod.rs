@@ -26,5 +26,7 @@
 mod tcp;
 mod udp;
 
-pub use tcp::{AsyncTcpClient, AsyncTcpConnection, AsyncTcpServer};
+pub use tcp::{
+    AbortHandle, AsyncTcpClient, AsyncTcpConnection, AsyncTcpServer, Pipeline, ResponseFuture,
+};
 pub use udp::{AsyncUdpClient, AsyncUdpServer};
@@ -1,22 +1,43 @@
 //! Async TCP transport for SOME/IP.
 
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
 use std::net::SocketAddr;
-use std::sync::atomic::{AtomicU16, Ordering};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
 use std::time::Duration;
 
-use tokio::io::{AsyncWriteExt, BufReader, BufWriter};
+use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader, BufWriter};
 use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
 use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::{mpsc, oneshot, Notify, OwnedSemaphorePermit, Semaphore};
+use tokio::task::JoinHandle;
 use tokio::time::timeout;
 
+use crate::codec::MessageReader;
 use crate::codec_async::{read_message_async, write_message_async};
 use crate::error::{Result, SomeIpError};
 use crate::header::{ClientId, SessionId};
 use crate::message::SomeIpMessage;
 
 /// Default TCP port for SOME/IP.
+#[allow(dead_code)]
 pub const DEFAULT_PORT: u16 = 30490;
 
+/// Maximum unread messages a [`Pipeline`] keeps besides responses.
+///
+/// Further messages are dropped until some are taken with
+/// [`Pipeline::try_recv_notification`].
+const MAX_PIPELINE_NOTIFICATIONS: usize = 256;
+
+/// Maximum messages an [`AsyncTcpClient`] buffers while waiting for responses.
+///
+/// Further messages are dropped until some are taken with
+/// [`AsyncTcpClient::receive`] or the notification accessors.
+const MAX_PENDING_MESSAGES: usize = 256;
+
 /// An async TCP connection wrapper with SOME/IP framing.
 pub struct AsyncTcpConnection {
     reader: BufReader<OwnedReadHalf>,
@@ -66,13 +87,102 @@ impl AsyncTcpConnection {
     }
 }
 
+/// Handle for cancelling a call started with [`AsyncTcpClient::call_cancelable`].
+#[derive(Debug, Clone, Default)]
+pub struct AbortHandle {
+    inner: Arc<AbortState>,
+}
+
+#[derive(Debug, Default)]
+struct AbortState {
+    aborted: AtomicBool,
+    notify: Notify,
+}
+
+impl AbortHandle {
+    /// Cancel the associated call.
+    ///
+    /// The call resolves with [`SomeIpError::Cancelled`], and its response is
+    /// discarded when it arrives.
+    pub fn abort(&self) {
+        self.inner.aborted.store(true, Ordering::Release);
+        self.inner.notify.notify_one();
+    }
+
+    /// Check if the call has been aborted.
+    pub fn is_aborted(&self) -> bool {
+        self.inner.aborted.load(Ordering::Acquire)
+    }
+
+    /// Wait until the call is aborted.
+    async fn aborted(&self) {
+        while !self.is_aborted() {
+            self.inner.notify.notified().await;
+        }
+    }
+}
+
+/// Marks a request as abandoned unless the call completes.
+///
+/// Dropping a pending call future registers its request ID so that the
+/// late response is skipped by subsequent reads.
+struct PendingCall {
+    request_id: u32,
+    abandoned: Arc<Mutex<HashSet<u32>>>,
+    completed: bool,
+}
+
+impl Drop for PendingCall {
+    fn drop(&mut self) {
+        if !self.completed {
+            self.abandoned.lock().unwrap().insert(self.request_id);
+        }
+    }
+}
+
+/// Marks the unanswered requests of a pipelined batch as abandoned.
+///
+/// Dropping the batch on error or cancellation registers every request ID
+/// still in flight, so their late responses are skipped by subsequent reads.
+struct PendingBatch {
+    in_flight: HashMap<u32, usize>,
+    abandoned: Arc<Mutex<HashSet<u32>>>,
+}
+
+impl Drop for PendingBatch {
+    fn drop(&mut self) {
+        self.abandoned
+            .lock()
+            .unwrap()
+            .extend(self.in_flight.keys().copied());
+    }
+}
+
 /// An async SOME/IP TCP client.
 ///
 /// Provides request/response functionality over TCP.
+///
+/// # Cancel safety
+///
+/// Incoming bytes are accumulated in an internal buffer, so dropping a
+/// [`call`](Self::call) or [`receive`](Self::receive) future (e.g. in
+/// `tokio::select!`) never leaves a partially read message on the stream.
+/// A request that was already sent still gets answered by the server, though;
+/// use [`call_cancelable`](Self::call_cancelable) to have that response
+/// discarded instead of being returned by a later `receive`.
+///
+/// Messages that arrive while a call waits for its response (e.g.
+/// notifications) are buffered and returned by [`receive`](Self::receive),
+/// [`try_recv_notification`](Self::try_recv_notification) and
+/// [`drain_notifications`](Self::drain_notifications). At most 256 are kept,
+/// and responses no call is waiting for are dropped.
 pub struct AsyncTcpClient {
     connection: AsyncTcpConnection,
     client_id: ClientId,
     session_counter: AtomicU16,
+    read_buffer: MessageReader,
+    abandoned: Arc<Mutex<HashSet<u32>>>,
+    pending: VecDeque<SomeIpMessage>,
 }
 
 impl AsyncTcpClient {
@@ -100,6 +210,9 @@ impl AsyncTcpClient {
             connection,
             client_id: ClientId(0x0001),
             session_counter: AtomicU16::new(1),
+            read_buffer: MessageReader::new(),
+            abandoned: Arc::new(Mutex::new(HashSet::new())),
+            pending: VecDeque::new(),
         })
     }
 
@@ -115,37 +228,106 @@ impl AsyncTcpClient {
 
     /// Get the next session ID.
     fn next_session_id(&self) -> SessionId {
-        let id = self.session_counter.fetch_add(1, Ordering::Relaxed);
-        if id == 0 {
-            self.session_counter.store(2, Ordering::Relaxed);
-            SessionId(1)
-        } else {
-            SessionId(id)
+        next_session_id(&self.session_counter)
+    }
+
+    /// Read the next message, skipping responses to abandoned calls.
+    ///
+    /// This is cancel-safe: bytes are only consumed into the internal buffer.
+    async fn read_next(&mut self) -> Result<SomeIpMessage> {
+        read_buffered(&mut self.connection.reader, &mut self.read_buffer, &self.abandoned).await
+    }
+
+    /// Keep a message that arrived during a call for a later receive.
+    ///
+    /// Responses are dropped, since the only call waiting is the current one,
+    /// and so is anything beyond [`MAX_PENDING_MESSAGES`].
+    fn buffer_message(&mut self, message: SomeIpMessage) {
+        if !message.is_response() && self.pending.len() < MAX_PENDING_MESSAGES {
+            self.pending.push_back(message);
         }
     }
 
     /// Send a request and wait for a response.
     ///
-    /// This method assigns client ID and session ID to the message.
+    /// This method assigns client ID and session ID to the message. If the
+    /// future is dropped after the request was sent (e.g. by
+    /// [`call_timeout`](Self::call_timeout)), the late response is discarded.
     pub async fn call(&mut self, mut message: SomeIpMessage) -> Result<SomeIpMessage> {
         message.header.client_id = self.client_id;
         message.header.session_id = self.next_session_id();
 
-        let request_id = message.header.request_id();
+        let mut pending = PendingCall {
+            request_id: message.header.request_id(),
+            abandoned: Arc::clone(&self.abandoned),
+            completed: false,
+        };
 
         // Send request
         self.connection.write_message(&message).await?;
 
         // Wait for response
         loop {
-            let response = self.connection.read_message().await?;
+            let response = self.read_next().await?;
 
-            if response.header.request_id() == request_id {
+            if response.header.request_id() == pending.request_id {
+                pending.completed = true;
                 return Ok(response);
             }
+
+            // Keep other messages (e.g., notifications) for later
+            self.buffer_message(response);
         }
     }
 
+    /// Send a request that can be cancelled through the returned [`AbortHandle`].
+    ///
+    /// When aborted (or when the future is dropped after the request was sent),
+    /// the call resolves with [`SomeIpError::Cancelled`] and the late response
+    /// is drained and discarded before any later call or receive sees it,
+    /// keeping the stream consistent.
+    pub fn call_cancelable(
+        &mut self,
+        mut message: SomeIpMessage,
+    ) -> (impl Future<Output = Result<SomeIpMessage>>, AbortHandle) {
+        let handle = AbortHandle::default();
+        let abort = handle.clone();
+
+        let future = async move {
+            if abort.is_aborted() {
+                return Err(SomeIpError::Cancelled);
+            }
+
+            message.header.client_id = self.client_id;
+            message.header.session_id = self.next_session_id();
+
+            let mut pending = PendingCall {
+                request_id: message.header.request_id(),
+                abandoned: Arc::clone(&self.abandoned),
+                completed: false,
+            };
+
+            self.connection.write_message(&message).await?;
+
+            loop {
+                let response = tokio::select! {
+                    biased;
+                    _ = abort.aborted() => return Err(SomeIpError::Cancelled),
+                    response = self.read_next() => response?,
+                };
+
+                if response.header.request_id() == pending.request_id {
+                    pending.completed = true;
+                    return Ok(response);
+                }
+
+                self.buffer_message(response);
+            }
+        };
+
+        (future, handle)
+    }
+
     /// Send a request with timeout.
     pub async fn call_timeout(
         &mut self,
@@ -157,6 +339,95 @@ impl AsyncTcpClient {
             .map_err(|_| SomeIpError::Timeout)?
     }
 
+    /// Send several requests, keeping at most `max_in_flight` unanswered.
+    ///
+    /// Responses are returned in request order, regardless of the order in
+    /// which the server answers. A `max_in_flight` of 0 is treated as 1.
+    ///
+    /// If the batch fails or is dropped, responses to requests still in
+    /// flight are discarded when they arrive.
+    pub async fn call_pipelined(
+        &mut self,
+        requests: Vec<SomeIpMessage>,
+        max_in_flight: usize,
+    ) -> Result<Vec<SomeIpMessage>> {
+        let max_in_flight = max_in_flight.max(1);
+        let mut responses: Vec<Option<SomeIpMessage>> = vec![None; requests.len()];
+        let mut batch = PendingBatch {
+            in_flight: HashMap::new(),
+            abandoned: Arc::clone(&self.abandoned),
+        };
+        let mut requests = requests.into_iter().enumerate();
+        let mut remaining = responses.len();
+
+        while remaining > 0 {
+            // Fill the window before waiting for the next response
+            while batch.in_flight.len() < max_in_flight {
+                let Some((index, mut message)) = requests.next() else {
+                    break;
+                };
+                message.header.client_id = self.client_id;
+                message.header.session_id = self.next_session_id();
+                batch.in_flight.insert(message.header.request_id(), index);
+                self.connection.write_message(&message).await?;
+            }
+
+            let response = self.read_next().await?;
+            let index = if response.is_response() {
+                batch.in_flight.remove(&response.header.request_id())
+            } else {
+                None
+            };
+            match index {
+                Some(index) => {
+                    responses[index] = Some(response);
+                    remaining -= 1;
+                }
+                // Keep other messages (e.g., notifications) for later
+                None => self.buffer_message(response),
+            }
+        }
+
+        Ok(responses.into_iter().flatten().collect())
+    }
+
+    /// Switch to a persistent pipelined mode with at most `max_in_flight`
+    /// unanswered requests.
+    ///
+    /// Responses are read by a background task and delivered to the
+    /// [`ResponseFuture`] of the matching request. A `max_in_flight` of 0 is
+    /// treated as 1.
+    pub fn pipeline(self, max_in_flight: usize) -> Pipeline {
+        let max_in_flight = max_in_flight.max(1);
+        let AsyncTcpConnection { reader, writer, .. } = self.connection;
+        let waiters = Arc::new(Mutex::new(Some(HashMap::new())));
+        let (notification_tx, notifications) = mpsc::channel(MAX_PIPELINE_NOTIFICATIONS);
+
+        for message in self.pending {
+            let _ = notification_tx.try_send(message);
+        }
+
+        let reader = tokio::spawn(pipeline_reader(
+            reader,
+            self.read_buffer,
+            Arc::clone(&self.abandoned),
+            Arc::clone(&waiters),
+            notification_tx,
+        ));
+
+        Pipeline {
+            writer,
+            client_id: self.client_id,
+            session_counter: self.session_counter,
+            window: Arc::new(Semaphore::new(max_in_flight)),
+            max_in_flight,
+            waiters,
+            abandoned: self.abandoned,
+            notifications,
+            reader,
+        }
+    }
+
     /// Send a fire-and-forget message (no response expected).
     pub async fn send(&mut self, mut message: SomeIpMessage) -> Result<()> {
         message.header.client_id = self.client_id;
@@ -165,8 +436,23 @@ impl AsyncTcpClient {
     }
 
     /// Receive a message (e.g., notification).
+    ///
+    /// Messages buffered during calls are returned first, in arrival order.
     pub async fn receive(&mut self) -> Result<SomeIpMessage> {
-        self.connection.read_message().await
+        match self.pending.pop_front() {
+            Some(message) => Ok(message),
+            None => self.read_next().await,
+        }
+    }
+
+    /// Take the oldest message buffered during calls, if any.
+    pub fn try_recv_notification(&mut self) -> Option<SomeIpMessage> {
+        self.pending.pop_front()
+    }
+
+    /// Take all messages buffered during calls, in arrival order.
+    pub fn drain_notifications(&mut self) -> Vec<SomeIpMessage> {
+        self.pending.drain(..).collect()
     }
 
     /// Get a reference to the underlying connection.
@@ -175,6 +461,9 @@ impl AsyncTcpClient {
     }
 
     /// Get a mutable reference to the underlying connection.
+    ///
+    /// Reading directly from the connection bypasses the client's internal
+    /// buffer and may miss data that was already received.
     pub fn connection_mut(&mut self) -> &mut AsyncTcpConnection {
         &mut self.connection
     }
@@ -185,6 +474,197 @@ impl AsyncTcpClient {
     }
 }
 
+/// Get the next session ID from a counter, skipping 0.
+fn next_session_id(counter: &AtomicU16) -> SessionId {
+    let id = counter.fetch_add(1, Ordering::Relaxed);
+    if id == 0 {
+        counter.store(2, Ordering::Relaxed);
+        SessionId(1)
+    } else {
+        SessionId(id)
+    }
+}
+
+/// Read the next buffered message, skipping responses to abandoned calls.
+async fn read_buffered(
+    reader: &mut BufReader<OwnedReadHalf>,
+    buffer: &mut MessageReader,
+    abandoned: &Mutex<HashSet<u32>>,
+) -> Result<SomeIpMessage> {
+    let mut chunk = [0u8; 4096];
+    loop {
+        while let Some(message) = buffer.try_parse()? {
+            if message.is_response()
+                && abandoned.lock().unwrap().remove(&message.header.request_id())
+            {
+                continue;
+            }
+            return Ok(message);
+        }
+
+        let len = reader.read(&mut chunk).await?;
+        if len == 0 {
+            return Err(SomeIpError::ConnectionClosed);
+        }
+        buffer.feed(&chunk[..len]);
+    }
+}
+
+/// Response channel and window slot of a pipelined request.
+type Waiter = (oneshot::Sender<SomeIpMessage>, OwnedSemaphorePermit);
+
+/// Waiters by request ID, or `None` once the connection is closed.
+type Waiters = Arc<Mutex<Option<HashMap<u32, Waiter>>>>;
+
+/// Background task delivering responses to pipelined requests.
+async fn pipeline_reader(
+    mut reader: BufReader<OwnedReadHalf>,
+    mut buffer: MessageReader,
+    abandoned: Arc<Mutex<HashSet<u32>>>,
+    waiters: Waiters,
+    notifications: mpsc::Sender<SomeIpMessage>,
+) {
+    while let Ok(message) = read_buffered(&mut reader, &mut buffer, &abandoned).await {
+        let waiter = if message.is_response() {
+            waiters
+                .lock()
+                .unwrap()
+                .as_mut()
+                .and_then(|waiters| waiters.remove(&message.header.request_id()))
+        } else {
+            None
+        };
+
+        match waiter {
+            Some((response_tx, permit)) => {
+                // Free the window slot before waking the caller
+                drop(permit);
+                let _ = response_tx.send(message);
+            }
+            // Its ResponseFuture was dropped while this response was parsed
+            None if message.is_response()
+                && abandoned.lock().unwrap().remove(&message.header.request_id()) => {}
+            None => {
+                // Dropped if the notification queue is full
+                let _ = notifications.try_send(message);
+            }
+        }
+    }
+
+    // Fail outstanding and future requests
+    waiters.lock().unwrap().take();
+}
+
+/// A persistent pipelined SOME/IP TCP client.
+///
+/// Created by [`AsyncTcpClient::pipeline`]. [`send`](Self::send) waits while
+/// the window of unanswered requests is full. At most 256 other messages are
+/// kept for [`try_recv_notification`](Self::try_recv_notification); further
+/// ones are dropped until some are taken.
+pub struct Pipeline {
+    writer: BufWriter<OwnedWriteHalf>,
+    client_id: ClientId,
+    session_counter: AtomicU16,
+    window: Arc<Semaphore>,
+    max_in_flight: usize,
+    waiters: Waiters,
+    abandoned: Arc<Mutex<HashSet<u32>>>,
+    notifications: mpsc::Receiver<SomeIpMessage>,
+    reader: JoinHandle<()>,
+}
+
+impl Pipeline {
+    /// Send a request, returning a future for its response.
+    ///
+    /// Waits for a free slot if `max_in_flight` requests are unanswered.
+    pub async fn send(&mut self, mut message: SomeIpMessage) -> Result<ResponseFuture> {
+        let permit = Arc::clone(&self.window)
+            .acquire_owned()
+            .await
+            .map_err(|_| SomeIpError::ConnectionClosed)?;
+
+        message.header.client_id = self.client_id;
+        message.header.session_id = next_session_id(&self.session_counter);
+        let request_id = message.header.request_id();
+
+        // Register before writing so a fast response is not missed
+        let (response_tx, response_rx) = oneshot::channel();
+        self.waiters
+            .lock()
+            .unwrap()
+            .as_mut()
+            .ok_or(SomeIpError::ConnectionClosed)?
+            .insert(request_id, (response_tx, permit));
+
+        let written = match write_message_async(&mut self.writer, &message).await {
+            Ok(()) => self.writer.flush().await.map_err(SomeIpError::from),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = written {
+            if let Some(waiters) = self.waiters.lock().unwrap().as_mut() {
+                waiters.remove(&request_id);
+            }
+            return Err(e);
+        }
+
+        Ok(ResponseFuture {
+            response_rx,
+            request_id,
+            waiters: Arc::clone(&self.waiters),
+            abandoned: Arc::clone(&self.abandoned),
+        })
+    }
+
+    /// Get the number of requests waiting for a response.
+    pub fn in_flight(&self) -> usize {
+        self.max_in_flight - self.window.available_permits()
+    }
+
+    /// Take the oldest message that did not answer a pipelined request.
+    pub fn try_recv_notification(&mut self) -> Option<SomeIpMessage> {
+        self.notifications.try_recv().ok()
+    }
+}
+
+impl Drop for Pipeline {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+/// The response to a request sent through a [`Pipeline`].
+///
+/// Resolves with [`SomeIpError::ConnectionClosed`] if the connection closes
+/// before the response arrives. Dropping it before then frees its window slot
+/// and discards the late response.
+pub struct ResponseFuture {
+    response_rx: oneshot::Receiver<SomeIpMessage>,
+    request_id: u32,
+    waiters: Waiters,
+    abandoned: Arc<Mutex<HashSet<u32>>>,
+}
+
+impl Drop for ResponseFuture {
+    fn drop(&mut self) {
+        let mut waiters = self.waiters.lock().unwrap();
+        let waiter = waiters.as_mut().and_then(|w| w.remove(&self.request_id));
+        if waiter.is_some() {
+            // Still unanswered; the reader skips the response when it arrives
+            self.abandoned.lock().unwrap().insert(self.request_id);
+        }
+    }
+}
+
+impl Future for ResponseFuture {
+    type Output = Result<SomeIpMessage>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.response_rx)
+            .poll(cx)
+            .map(|response| response.map_err(|_| SomeIpError::ConnectionClosed))
+    }
+}
+
 /// An async SOME/IP TCP server.
 ///
 /// Accepts connections and handles incoming messages.
@@ -255,6 +735,132 @@ mod tests {
         server_handle.await.unwrap();
     }
 
+    #[tokio::test]
+    async fn test_async_drain_notifications() {
+        let server = AsyncTcpServer::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr();
+
+        tokio::spawn(async move {
+            let (mut conn, _) = server.accept().await.unwrap();
+            let request = conn.read_message().await.unwrap();
+
+            // Notify before answering the request
+            let notification =
+                SomeIpMessage::notification(ServiceId(0x1234), MethodId::event(0x0001))
+                    .payload(b"event".as_slice())
+                    .build();
+            conn.write_message(&notification).await.unwrap();
+            conn.write_message(&request.create_response().build()).await.unwrap();
+        });
+
+        let mut client = AsyncTcpClient::connect(addr).await.unwrap();
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001)).build();
+        let response = client.call(request).await.unwrap();
+        assert!(response.is_response());
+
+        let notifications = client.drain_notifications();
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0].header.method_id, MethodId::event(0x0001));
+        assert_eq!(notifications[0].payload.as_ref(), b"event");
+        assert!(client.try_recv_notification().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_async_pending_limit() {
+        let server = AsyncTcpServer::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr();
+
+        tokio::spawn(async move {
+            let (mut conn, _) = server.accept().await.unwrap();
+            let request = conn.read_message().await.unwrap();
+            for _ in 0..=MAX_PENDING_MESSAGES {
+                let notification =
+                    SomeIpMessage::notification(ServiceId(0x1234), MethodId::event(0x0001))
+                        .build();
+                conn.write_message(&notification).await.unwrap();
+            }
+            // A response to a request that is not awaited
+            let stray = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0002))
+                .session_id(SessionId(0x0100))
+                .build();
+            conn.write_message(&stray.create_response().build()).await.unwrap();
+            conn.write_message(&request.create_response().build()).await.unwrap();
+        });
+
+        let mut client = AsyncTcpClient::connect(addr).await.unwrap();
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001)).build();
+        client.call(request).await.unwrap();
+
+        let notifications = client.drain_notifications();
+        assert_eq!(notifications.len(), MAX_PENDING_MESSAGES);
+        assert!(notifications.iter().all(|message| !message.is_response()));
+    }
+
+    #[tokio::test]
+    async fn test_async_call_timeout_discards_late_response() {
+        let server = AsyncTcpServer::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr();
+
+        tokio::spawn(async move {
+            let (mut conn, _) = server.accept().await.unwrap();
+
+            // Answer the first request only after the second arrives
+            let first = conn.read_message().await.unwrap();
+            let second = conn.read_message().await.unwrap();
+            conn.write_message(&first.create_response().build()).await.unwrap();
+            conn.write_message(&second.create_response().build()).await.unwrap();
+        });
+
+        let mut client = AsyncTcpClient::connect(addr).await.unwrap();
+
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001)).build();
+        let result = client.call_timeout(request, Duration::from_millis(50)).await;
+        assert!(matches!(result, Err(SomeIpError::Timeout)));
+
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0002)).build();
+        let response = client.call(request).await.unwrap();
+        assert_eq!(response.header.method_id, MethodId(0x0002));
+        assert!(client.try_recv_notification().is_none());
+        assert!(client.abandoned.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_async_call_cancelable() {
+        let server = AsyncTcpServer::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr();
+
+        tokio::spawn(async move {
+            let (mut conn, _) = server.accept().await.unwrap();
+
+            // Hold the first response until the second request arrives
+            let first = conn.read_message().await.unwrap();
+            let second = conn.read_message().await.unwrap();
+
+            let response = first.create_response().payload(b"first".as_slice()).build();
+            conn.write_message(&response).await.unwrap();
+            let response = second.create_response().payload(b"second".as_slice()).build();
+            conn.write_message(&response).await.unwrap();
+        });
+
+        let mut client = AsyncTcpClient::connect(addr).await.unwrap();
+
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001)).build();
+        let (call, handle) = client.call_cancelable(request);
+        let aborter = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            handle.abort();
+        });
+        let result = call.await;
+        assert!(matches!(result, Err(SomeIpError::Cancelled)));
+        aborter.await.unwrap();
+
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0002)).build();
+        let response = client.call(request).await.unwrap();
+        assert_eq!(response.header.method_id, MethodId(0x0002));
+        assert_eq!(response.payload.as_ref(), b"second");
+        assert!(client.abandoned.lock().unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn test_async_session_id_increment() {
         let server = AsyncTcpServer::bind("127.0.0.1:0").await.unwrap();
@@ -277,4 +883,189 @@ mod tests {
             assert_eq!(response.header.session_id, SessionId(expected_session));
         }
     }
+
+    #[tokio::test]
+    async fn test_async_call_pipelined() {
+        let server = AsyncTcpServer::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr();
+
+        tokio::spawn(async move {
+            let (mut conn, _) = server.accept().await.unwrap();
+
+            // Answer each window of two in reverse order
+            let first = conn.read_message().await.unwrap();
+            let second = conn.read_message().await.unwrap();
+            conn.write_message(&second.create_response().payload(second.payload.clone()).build())
+                .await
+                .unwrap();
+            let third = conn.read_message().await.unwrap();
+            conn.write_message(&third.create_response().payload(third.payload.clone()).build())
+                .await
+                .unwrap();
+            conn.write_message(&first.create_response().payload(first.payload.clone()).build())
+                .await
+                .unwrap();
+        });
+
+        let mut client = AsyncTcpClient::connect(addr).await.unwrap();
+        let requests = (1..=3u8)
+            .map(|i| {
+                SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+                    .payload(vec![i])
+                    .build()
+            })
+            .collect();
+
+        let responses = client.call_pipelined(requests, 2).await.unwrap();
+        let payloads: Vec<u8> = responses.iter().map(|r| r.payload[0]).collect();
+        assert_eq!(payloads, vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_async_call_pipelined_abandons_in_flight() {
+        let server = AsyncTcpServer::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr();
+
+        tokio::spawn(async move {
+            let (mut conn, _) = server.accept().await.unwrap();
+
+            // Answer the batch only after the client gave up on it
+            let first = conn.read_message().await.unwrap();
+            let second = conn.read_message().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            for request in [first, second] {
+                conn.write_message(&request.create_response().build()).await.unwrap();
+            }
+
+            let request = conn.read_message().await.unwrap();
+            let response = request.create_response().payload(b"later".as_slice()).build();
+            conn.write_message(&response).await.unwrap();
+        });
+
+        let mut client = AsyncTcpClient::connect(addr).await.unwrap();
+        let requests = (0..2)
+            .map(|_| SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001)).build())
+            .collect();
+        let result = timeout(Duration::from_millis(50), client.call_pipelined(requests, 2)).await;
+        assert!(result.is_err());
+
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0002)).build();
+        let response = client.call(request).await.unwrap();
+        assert_eq!(response.payload.as_ref(), b"later");
+        assert!(client.try_recv_notification().is_none());
+        assert!(client.abandoned.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_async_pipeline_window() {
+        let server = AsyncTcpServer::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr();
+
+        let server_handle = tokio::spawn(async move {
+            let (mut conn, _) = server.accept().await.unwrap();
+            let first = conn.read_message().await.unwrap();
+            let second = conn.read_message().await.unwrap();
+
+            // The window is full, so the third request must not be sent yet
+            let early = timeout(Duration::from_millis(100), conn.read_message()).await;
+            assert!(early.is_err());
+
+            conn.write_message(&second.create_response().payload(second.payload.clone()).build())
+                .await
+                .unwrap();
+            let third = conn.read_message().await.unwrap();
+            conn.write_message(&third.create_response().payload(third.payload.clone()).build())
+                .await
+                .unwrap();
+            conn.write_message(&first.create_response().payload(first.payload.clone()).build())
+                .await
+                .unwrap();
+        });
+
+        let client = AsyncTcpClient::connect(addr).await.unwrap();
+        let mut pipeline = client.pipeline(2);
+
+        let mut responses = Vec::new();
+        for i in 1..=3u8 {
+            let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001))
+                .payload(vec![i])
+                .build();
+            responses.push(pipeline.send(request).await.unwrap());
+            assert!(pipeline.in_flight() <= 2);
+        }
+
+        for (i, response) in (1..=3u8).zip(responses) {
+            assert_eq!(response.await.unwrap().payload.as_ref(), &[i]);
+        }
+        assert_eq!(pipeline.in_flight(), 0);
+        assert!(pipeline.try_recv_notification().is_none());
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_async_pipeline_drop_response_future() {
+        let server = AsyncTcpServer::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr();
+
+        let server_handle = tokio::spawn(async move {
+            let (mut conn, _) = server.accept().await.unwrap();
+            let first = conn.read_message().await.unwrap();
+            let second = conn.read_message().await.unwrap();
+            for request in [first, second] {
+                let response = request.create_response().payload(request.payload.clone()).build();
+                conn.write_message(&response).await.unwrap();
+            }
+        });
+
+        let client = AsyncTcpClient::connect(addr).await.unwrap();
+        let mut pipeline = client.pipeline(1);
+        let request = |i: u8| {
+            SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001)).payload(vec![i]).build()
+        };
+
+        // Dropping the unanswered future frees the only window slot
+        drop(pipeline.send(request(1)).await.unwrap());
+        assert_eq!(pipeline.in_flight(), 0);
+
+        let response = timeout(Duration::from_secs(2), pipeline.send(request(2)))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.await.unwrap().payload.as_ref(), &[2]);
+
+        // The late response to the dropped request was discarded
+        assert!(pipeline.try_recv_notification().is_none());
+        assert!(pipeline.abandoned.lock().unwrap().is_empty());
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_async_pipeline_notification_limit() {
+        let server = AsyncTcpServer::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr();
+
+        let server_handle = tokio::spawn(async move {
+            let (mut conn, _) = server.accept().await.unwrap();
+            let request = conn.read_message().await.unwrap();
+            for _ in 0..=MAX_PIPELINE_NOTIFICATIONS {
+                let notification =
+                    SomeIpMessage::notification(ServiceId(0x1234), MethodId::event(0x0001))
+                        .build();
+                conn.write_message(&notification).await.unwrap();
+            }
+            conn.write_message(&request.create_response().build()).await.unwrap();
+        });
+
+        let client = AsyncTcpClient::connect(addr).await.unwrap();
+        let mut pipeline = client.pipeline(1);
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001)).build();
+        pipeline.send(request).await.unwrap().await.unwrap();
+
+        let mut received = 0;
+        while pipeline.try_recv_notification().is_some() {
+            received += 1;
+        }
+        assert_eq!(received, MAX_PIPELINE_NOTIFICATIONS);
+        server_handle.await.unwrap();
+    }
 }
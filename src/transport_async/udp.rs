@@ -4,18 +4,21 @@ use std::net::{Ipv4Addr, SocketAddr};
 use std::sync::atomic::{AtomicU16, Ordering};
 use std::time::Duration;
 
+use bytes::Bytes;
 use tokio::net::{ToSocketAddrs, UdpSocket};
 use tokio::time::timeout;
 
 use crate::error::{Result, SomeIpError};
-use crate::header::{ClientId, SessionId};
+use crate::header::{ClientId, MethodId, ServiceId, SessionId};
 use crate::message::SomeIpMessage;
+use crate::transport::udp::MAX_DATAGRAM_SIZE;
 use crate::types::ReturnCode;
 
 /// Default maximum UDP datagram size for SOME/IP.
 pub const DEFAULT_MAX_DATAGRAM_SIZE: usize = 1400;
 
 /// Default UDP port for SOME/IP.
+#[allow(dead_code)]
 pub const DEFAULT_PORT: u16 = 30490;
 
 /// An async SOME/IP UDP client.
@@ -26,6 +29,7 @@ pub struct AsyncUdpClient {
     client_id: ClientId,
     session_counter: AtomicU16,
     recv_buffer: Vec<u8>,
+    max_datagram_size: usize,
     connected_addr: Option<SocketAddr>,
 }
 
@@ -42,7 +46,9 @@ impl AsyncUdpClient {
             socket,
             client_id: ClientId(0x0001),
             session_counter: AtomicU16::new(1),
-            recv_buffer: vec![0u8; DEFAULT_MAX_DATAGRAM_SIZE],
+            // One spare byte to detect datagrams larger than the maximum
+            recv_buffer: vec![0u8; DEFAULT_MAX_DATAGRAM_SIZE + 1],
+            max_datagram_size: DEFAULT_MAX_DATAGRAM_SIZE,
             connected_addr: None,
         })
     }
@@ -66,9 +72,22 @@ impl AsyncUdpClient {
         self.client_id
     }
 
-    /// Set the maximum datagram size.
+    /// Set the maximum datagram size, capped at [`MAX_DATAGRAM_SIZE`].
+    ///
+    /// Larger datagrams are reported as [`SomeIpError::DatagramTruncated`].
     pub fn set_max_datagram_size(&mut self, size: usize) {
-        self.recv_buffer.resize(size, 0);
+        self.max_datagram_size = size.min(MAX_DATAGRAM_SIZE);
+        self.recv_buffer.resize(self.max_datagram_size + 1, 0);
+    }
+
+    /// Parse a received datagram, rejecting ones that did not fit the buffer.
+    fn parse_datagram(&self, len: usize) -> Result<SomeIpMessage> {
+        if len > self.max_datagram_size {
+            return Err(SomeIpError::DatagramTruncated {
+                buffer_size: self.max_datagram_size,
+            });
+        }
+        SomeIpMessage::from_bytes(&self.recv_buffer[..len])
     }
 
     /// Get the next session ID.
@@ -100,7 +119,7 @@ impl AsyncUdpClient {
         // Wait for matching response
         loop {
             let len = self.socket.recv(&mut self.recv_buffer).await?;
-            let response = SomeIpMessage::from_bytes(&self.recv_buffer[..len])?;
+            let response = self.parse_datagram(len)?;
 
             if response.header.request_id() == request_id {
                 return Ok(response);
@@ -119,6 +138,22 @@ impl AsyncUdpClient {
             .map_err(|_| SomeIpError::Timeout)?
     }
 
+    /// Build a request, send it to the connected address and wait for the response.
+    ///
+    /// Returns [`SomeIpError::Timeout`] if no matching response arrives in time.
+    pub async fn request(
+        &mut self,
+        service_id: ServiceId,
+        method_id: MethodId,
+        payload: impl Into<Bytes>,
+        duration: Duration,
+    ) -> Result<SomeIpMessage> {
+        let message = SomeIpMessage::request(service_id, method_id)
+            .payload(payload)
+            .build();
+        self.call_timeout(message, duration).await
+    }
+
     /// Send a request to a specific address and wait for a response.
     pub async fn call_to(
         &mut self,
@@ -136,7 +171,7 @@ impl AsyncUdpClient {
         // Wait for matching response
         loop {
             let (len, _) = self.socket.recv_from(&mut self.recv_buffer).await?;
-            let response = SomeIpMessage::from_bytes(&self.recv_buffer[..len])?;
+            let response = self.parse_datagram(len)?;
 
             if response.header.request_id() == request_id {
                 return Ok(response);
@@ -179,7 +214,7 @@ impl AsyncUdpClient {
     /// Receive a message.
     pub async fn receive(&mut self) -> Result<(SomeIpMessage, SocketAddr)> {
         let (len, addr) = self.socket.recv_from(&mut self.recv_buffer).await?;
-        let message = SomeIpMessage::from_bytes(&self.recv_buffer[..len])?;
+        let message = self.parse_datagram(len)?;
         Ok((message, addr))
     }
 
@@ -255,6 +290,24 @@ impl AsyncUdpServer {
         self.send_to(&response, addr).await
     }
 
+    /// Send a response to a request with a specific return code.
+    ///
+    /// The message type stays `Response`, unlike [`respond_error`](Self::respond_error).
+    pub async fn respond_with_code(
+        &self,
+        request: &SomeIpMessage,
+        return_code: ReturnCode,
+        payload: impl Into<bytes::Bytes>,
+        addr: SocketAddr,
+    ) -> Result<()> {
+        let response = request
+            .create_response()
+            .return_code(return_code)
+            .payload(payload)
+            .build();
+        self.send_to(&response, addr).await
+    }
+
     /// Send an error response to a request.
     pub async fn respond_error(
         &self,
@@ -280,7 +333,36 @@ impl AsyncUdpServer {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::header::{MethodId, ServiceId};
+
+    #[tokio::test]
+    async fn test_async_udp_truncated_response() {
+        let mut server = AsyncUdpServer::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = server.local_addr();
+
+        let server_handle = tokio::spawn(async move {
+            for _ in 0..2 {
+                let (request, addr) = server.receive().await.unwrap();
+                server.respond(&request, vec![0xAB; 2000], addr).await.unwrap();
+            }
+        });
+
+        let mut client = AsyncUdpClient::bind("127.0.0.1:0").await.unwrap();
+        client.connect(server_addr).await.unwrap();
+
+        let request = SomeIpMessage::request(ServiceId(0x1234), MethodId(0x0001)).build();
+        let result = client.call(request.clone()).await;
+        assert!(matches!(
+            result,
+            Err(SomeIpError::DatagramTruncated { buffer_size: 1400 })
+        ));
+
+        client.set_max_datagram_size(100_000);
+        assert_eq!(client.max_datagram_size, MAX_DATAGRAM_SIZE);
+        let response = client.call(request).await.unwrap();
+        assert_eq!(response.payload.len(), 2000);
+
+        server_handle.await.unwrap();
+    }
 
     #[tokio::test]
     async fn test_async_udp_client_server() {
@@ -314,6 +396,33 @@ mod tests {
         server_handle.await.unwrap();
     }
 
+    #[tokio::test]
+    async fn test_async_udp_request() {
+        let mut server = AsyncUdpServer::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = server.local_addr();
+
+        // Echo server
+        let server_handle = tokio::spawn(async move {
+            let (request, client_addr) = server.receive().await.unwrap();
+            server
+                .respond(&request, request.payload.clone(), client_addr)
+                .await
+                .unwrap();
+        });
+
+        let mut client = AsyncUdpClient::new().await.unwrap();
+        client.connect(server_addr).await.unwrap();
+
+        let timeout = Duration::from_secs(1);
+        let response = client
+            .request(ServiceId(0x1234), MethodId(0x0001), b"echo".as_slice(), timeout)
+            .await
+            .unwrap();
+        assert_eq!(response.payload.as_ref(), b"echo");
+
+        server_handle.await.unwrap();
+    }
+
     #[tokio::test]
     async fn test_async_udp_fire_and_forget() {
         let mut server = AsyncUdpServer::bind("127.0.0.1:0").await.unwrap();
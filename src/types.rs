@@ -1,5 +1,11 @@
 //! Core SOME/IP types and constants.
 
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use crate::error::SomeIpError;
+
 /// SOME/IP protocol version (always 0x01).
 pub const PROTOCOL_VERSION: u8 = 0x01;
 
@@ -30,6 +36,20 @@ pub enum MessageType {
 }
 
 impl MessageType {
+    /// All message types.
+    pub const ALL: &[Self] = &[
+        Self::Request,
+        Self::RequestNoReturn,
+        Self::Notification,
+        Self::Response,
+        Self::Error,
+        Self::TpRequest,
+        Self::TpRequestNoReturn,
+        Self::TpNotification,
+        Self::TpResponse,
+        Self::TpError,
+    ];
+
     /// Create a MessageType from a raw byte value.
     pub fn from_u8(value: u8) -> Option<Self> {
         match value {
@@ -100,10 +120,11 @@ impl MessageType {
 }
 
 /// SOME/IP return codes as defined in the specification.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 #[repr(u8)]
 pub enum ReturnCode {
     /// No error occurred.
+    #[default]
     Ok = 0x00,
     /// An unspecified error occurred.
     NotOk = 0x01,
@@ -138,6 +159,26 @@ pub enum ReturnCode {
 }
 
 impl ReturnCode {
+    /// All return codes.
+    pub const ALL: &[Self] = &[
+        Self::Ok,
+        Self::NotOk,
+        Self::UnknownService,
+        Self::UnknownMethod,
+        Self::NotReady,
+        Self::NotReachable,
+        Self::Timeout,
+        Self::WrongProtocolVersion,
+        Self::WrongInterfaceVersion,
+        Self::MalformedMessage,
+        Self::WrongMessageType,
+        Self::E2ERepeated,
+        Self::E2EWrongSequence,
+        Self::E2E,
+        Self::E2ENotAvailable,
+        Self::E2ENoNewData,
+    ];
+
     /// Create a ReturnCode from a raw byte value.
     pub fn from_u8(value: u8) -> Option<Self> {
         match value {
@@ -172,9 +213,51 @@ impl ReturnCode {
     }
 }
 
-impl Default for ReturnCode {
-    fn default() -> Self {
-        Self::Ok
+impl fmt::Display for MessageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl FromStr for MessageType {
+    type Err = SomeIpError;
+
+    /// Parse a message type name (case-insensitive), e.g. "notification".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|t| t.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                SomeIpError::io(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Unknown message type: {}", s),
+                ))
+            })
+    }
+}
+
+impl fmt::Display for ReturnCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl FromStr for ReturnCode {
+    type Err = SomeIpError;
+
+    /// Parse a return code name (case-insensitive), e.g. "notok".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|c| c.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                SomeIpError::io(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Unknown return code: {}", s),
+                ))
+            })
     }
 }
 
@@ -197,6 +280,47 @@ mod tests {
         assert!(!MessageType::Response.expects_response());
     }
 
+    #[test]
+    fn test_message_type_to_base() {
+        let pairs = [
+            (MessageType::TpRequest, MessageType::Request),
+            (MessageType::TpRequestNoReturn, MessageType::RequestNoReturn),
+            (MessageType::TpNotification, MessageType::Notification),
+            (MessageType::TpResponse, MessageType::Response),
+            (MessageType::TpError, MessageType::Error),
+        ];
+        for (tp, base) in pairs {
+            assert!(tp.is_tp());
+            assert_eq!(tp.to_base(), base);
+            assert_eq!(base.to_base(), base);
+        }
+    }
+
+    #[test]
+    fn test_message_type_display_from_str() {
+        for &message_type in MessageType::ALL {
+            let name = message_type.to_string();
+            assert_eq!(name.parse::<MessageType>().unwrap(), message_type);
+            assert_eq!(name.to_uppercase().parse::<MessageType>().unwrap(), message_type);
+        }
+        assert_eq!(MessageType::Notification.to_string(), "Notification");
+        assert_eq!("request".parse::<MessageType>().unwrap(), MessageType::Request);
+        let err = "bogus".parse::<MessageType>().unwrap_err();
+        assert!(matches!(err, SomeIpError::Io(e) if e.kind() == io::ErrorKind::InvalidInput));
+    }
+
+    #[test]
+    fn test_return_code_display_from_str() {
+        for &return_code in ReturnCode::ALL {
+            let name = return_code.to_string();
+            assert_eq!(name.parse::<ReturnCode>().unwrap(), return_code);
+            assert_eq!(name.to_lowercase().parse::<ReturnCode>().unwrap(), return_code);
+            assert_eq!(ReturnCode::from_u8(return_code as u8), Some(return_code));
+        }
+        assert_eq!(ReturnCode::UnknownService.to_string(), "UnknownService");
+        assert!("".parse::<ReturnCode>().is_err());
+    }
+
     #[test]
     fn test_return_code_from_u8() {
         assert_eq!(ReturnCode::from_u8(0x00), Some(ReturnCode::Ok));